@@ -4,8 +4,14 @@ use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use basset::hub::{
-    AllHistoryResponse, CurrentBatchResponse, InstantiateMsg, Parameters, QueryMsg, StateResponse,
-    UnbondRequestsResponse, WhitelistedValidatorsResponse, WithdrawableUnbondedResponse,
+    ActiveBatchesResponse, AllHistoryResponse, BatchExchangeRateResponse, BatchMaturitiesResponse,
+    BatchWaitListResponse, BondReconciliationResponse, CompoundHealthResponse,
+    ContractVersionResponse, CurrentBatchResponse, DelegationCountResponse, IdleBalanceResponse,
+    InstantiateMsg, KeeperConfigResponse, LiquidityReserveResponse, Parameters,
+    PegFeePreviewResponse, QueryMsg, SimulateCompoundResponse, SimulateUndelegateResponse,
+    SlashHistoryResponse, StakedValueResponse, StateResponse, StuckBatchesResponse,
+    UnbondRequestsResponse, UnbondTimelineResponse, UserStatsResponse, ValidatorInfoResponse,
+    WhitelistedValidatorsResponse, WithdrawableAddressesResponse, WithdrawableUnbondedResponse,
 };
 use basset::hub::{Config, ExecuteMsg, State};
 
@@ -27,4 +33,25 @@ fn main() {
     export_schema(&schema_for!(UnbondRequestsResponse), &out_dir);
     export_schema(&schema_for!(CurrentBatchResponse), &out_dir);
     export_schema(&schema_for!(AllHistoryResponse), &out_dir);
+    export_schema(&schema_for!(ValidatorInfoResponse), &out_dir);
+    export_schema(&schema_for!(CompoundHealthResponse), &out_dir);
+    export_schema(&schema_for!(StakedValueResponse), &out_dir);
+    export_schema(&schema_for!(BatchMaturitiesResponse), &out_dir);
+    export_schema(&schema_for!(KeeperConfigResponse), &out_dir);
+    export_schema(&schema_for!(StuckBatchesResponse), &out_dir);
+    export_schema(&schema_for!(UnbondTimelineResponse), &out_dir);
+    export_schema(&schema_for!(IdleBalanceResponse), &out_dir);
+    export_schema(&schema_for!(UserStatsResponse), &out_dir);
+    export_schema(&schema_for!(ActiveBatchesResponse), &out_dir);
+    export_schema(&schema_for!(BondReconciliationResponse), &out_dir);
+    export_schema(&schema_for!(BatchWaitListResponse), &out_dir);
+    export_schema(&schema_for!(SimulateCompoundResponse), &out_dir);
+    export_schema(&schema_for!(ContractVersionResponse), &out_dir);
+    export_schema(&schema_for!(PegFeePreviewResponse), &out_dir);
+    export_schema(&schema_for!(SlashHistoryResponse), &out_dir);
+    export_schema(&schema_for!(SimulateUndelegateResponse), &out_dir);
+    export_schema(&schema_for!(DelegationCountResponse), &out_dir);
+    export_schema(&schema_for!(LiquidityReserveResponse), &out_dir);
+    export_schema(&schema_for!(WithdrawableAddressesResponse), &out_dir);
+    export_schema(&schema_for!(BatchExchangeRateResponse), &out_dir);
 }
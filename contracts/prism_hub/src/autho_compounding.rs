@@ -1,11 +1,17 @@
 use std::ops::Mul;
 
 use crate::contract::query_total_issued;
-use crate::state::{CONFIG, CURRENT_BATCH, PARAMETERS, STATE};
-use basset::hub::{Parameters, State};
+use crate::error::HubError;
+use crate::math::round_multiplication;
+use crate::state::{
+    read_active_validators, read_unreleased_unbond_history, ACCRUED_FEE, CONFIG, CURRENT_BATCH,
+    PARAMETERS, STATE,
+};
+use crate::utility::next_selection_seed;
+use basset::hub::{Config, Parameters, State};
 use cosmwasm_std::{
-    BankMsg, Coin, CosmosMsg, Decimal, DepsMut, Env, MessageInfo, Response, StakingMsg, StdError,
-    StdResult, Uint128,
+    attr, BankMsg, CanonicalAddr, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo,
+    Response, StakingMsg, StdError, StdResult, Uint128,
 };
 use rand::{Rng, SeedableRng, XorShiftRng};
 
@@ -15,7 +21,7 @@ pub fn execute_update_exchange_rate(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-) -> StdResult<Response> {
+) -> Result<Response, HubError> {
     let mut state: State = STATE.load(deps.storage)?;
     let contract_address = env.contract.address;
 
@@ -24,11 +30,15 @@ pub fn execute_update_exchange_rate(
 
     // Permission check
     if rewards_contract != info.sender {
-        return Err(StdError::generic_err("Unauthorized"));
+        return Err(HubError::Unauthorized {});
     }
 
     let params: Parameters = PARAMETERS.load(deps.storage)?;
     let coin_denom = params.underlying_coin_denom;
+    // any other coin sent along would be stranded on the hub
+    if info.funds.len() > 1usize {
+        return Err(HubError::MultipleCoins {});
+    }
     let payment = info
         .funds
         .iter()
@@ -43,61 +53,123 @@ pub fn execute_update_exchange_rate(
     // claimed_rewards = current_balance - prev_balance;
     let claimed_rewards = payment.amount;
 
-    let protocol_fee = if params.protocol_fee != Decimal::zero() {
+    // no fee is taken during the promotional window
+    let fee_free = params
+        .fee_free_until
+        .map_or(false, |until| env.block.time.seconds() < until);
+    let protocol_fee = if fee_free {
+        Uint128::zero()
+    } else if params.protocol_fee != Decimal::zero() && params.round_protocol_fee {
+        round_multiplication(claimed_rewards, params.protocol_fee)
+    } else if params.protocol_fee != Decimal::zero() {
         claimed_rewards.mul(params.protocol_fee)
     } else {
         Uint128::zero()
     };
 
     let user_rewards = claimed_rewards.checked_sub(protocol_fee as Uint128)?;
+    // the reserved part stays idle and only backs the supply once restaked
+    let reserved_rewards = user_rewards * params.liquidity_reserve_ratio;
+    let delegated_rewards = user_rewards.checked_sub(reserved_rewards)?;
 
     let current_batch = CURRENT_BATCH.load(deps.storage)?;
     let requested_with_fee = current_batch.requested_with_fee;
     let total_issued = query_total_issued(deps.as_ref())?;
 
+    let actual_supply = total_issued.checked_add(requested_with_fee)?;
+    if actual_supply.is_zero() {
+        return Err(
+            StdError::generic_err("Rewards can not be compounded without any supply").into(),
+        );
+    }
+
     // exchange_rate += user_rewards / total_balance;
-    state.exchange_rate += Decimal::from_ratio(user_rewards, total_issued + requested_with_fee);
-    state.total_bond_amount += user_rewards;
+    state.exchange_rate += Decimal::from_ratio(delegated_rewards, actual_supply);
+    state.total_bond_amount = state.total_bond_amount.checked_add(delegated_rewards)?;
+    state.total_rewards_compounded = state.total_rewards_compounded.checked_add(user_rewards)?;
+    state.cumulative_rewards_compounded = state
+        .cumulative_rewards_compounded
+        .checked_add(claimed_rewards)?;
+    state.cumulative_protocol_fees = state.cumulative_protocol_fees.checked_add(protocol_fee)?;
 
-    STATE.save(deps.storage, &state)?;
+    // an exchange rate above the bound indicates an accounting error
+    if let Some(max_exchange_rate) = params.max_exchange_rate {
+        if state.exchange_rate > max_exchange_rate {
+            return Err(StdError::generic_err(format!(
+                "The exchange rate {} exceeds the maximum exchange rate {}",
+                state.exchange_rate, max_exchange_rate
+            ))
+            .into());
+        }
+    }
 
-    let all_delegations = deps
-        .querier
-        .query_all_delegations(contract_address)
-        .expect("There must be at least one delegation");
+    // the fee is kept on the hub while accrual is on or until it reaches the transfer minimum
+    let accrued_fee = ACCRUED_FEE.may_load(deps.storage)?.unwrap_or_default();
+    let pending_fee = accrued_fee + protocol_fee;
+    let accrue_fee = !protocol_fee.is_zero()
+        && (params.accrue_protocol_fee || pending_fee < params.min_protocol_fee_transfer);
+    // the reserve stays on the hub too, a release must not pay it to the batches
+    state.prev_hub_balance = state.prev_hub_balance.checked_add(reserved_rewards)?;
+    state.liquidity_reserve = state.liquidity_reserve.checked_add(reserved_rewards)?;
+    if accrue_fee {
+        // the accrued fee stays on the hub, it must not be taken as unbonded funds
+        state.prev_hub_balance += protocol_fee;
+    } else if !protocol_fee.is_zero() {
+        // the previously accrued fee leaves the hub with this transfer
+        state.prev_hub_balance = state.prev_hub_balance.saturating_sub(accrued_fee);
+    }
+
+    // compounded rewards go to the reward validator if one is set
+    let reward_validator = match config.reward_validator.clone() {
+        Some(validator) => validator,
+        None => {
+            // all the stake may have been undelegated, e.g. by an emergency wind-down,
+            // and the validators not accepting new bonds are skipped
+            let active_validators = read_active_validators(deps.storage)?;
+            let all_delegations: Vec<String> = deps
+                .querier
+                .query_all_delegations(contract_address)?
+                .into_iter()
+                .map(|delegation| delegation.validator)
+                .filter(|validator| active_validators.contains(validator))
+                .collect();
+            if all_delegations.is_empty() {
+                return Err(StdError::generic_err(
+                    "There are no delegations to compound the rewards into",
+                )
+                .into());
+            }
 
-    let mut rng = XorShiftRng::seed_from_u64(env.block.height);
+            let seed = next_selection_seed(deps.storage, env.block.height)?;
+            let mut rng = XorShiftRng::seed_from_u64(seed);
 
-    let random_index = rng.gen_range(0, all_delegations.len());
+            let random_index = rng.gen_range(0, all_delegations.len());
+            all_delegations.get(random_index).unwrap().to_string()
+        }
+    };
+
+    STATE.save(deps.storage, &state)?;
 
     let mut messages: Vec<CosmosMsg> = vec![];
 
-    if protocol_fee as Uint128 != Uint128::zero() {
-        match config.protocol_fee_collector {
-            Some(fee_collector) => {
-                messages.push(CosmosMsg::Bank(BankMsg::Send {
-                    to_address: deps.api.addr_humanize(&fee_collector)?.to_string(),
-                    amount: vec![Coin::new(protocol_fee.u128(), &coin_denom)],
-                }));
-            }
-            None => {
-                return Err(StdError::generic_err(
-                    "protocol fee collector address has not been set",
-                ));
-            }
-        }
+    if accrue_fee {
+        ACCRUED_FEE.save(deps.storage, &pending_fee)?;
+    } else if protocol_fee as Uint128 != Uint128::zero() {
+        ACCRUED_FEE.save(deps.storage, &Uint128::zero())?;
+        messages.append(&mut protocol_fee_msgs(
+            deps.as_ref(),
+            &config,
+            pending_fee,
+            &coin_denom,
+        )?);
     };
 
-    if user_rewards != Uint128::zero() {
+    if delegated_rewards != Uint128::zero() {
         messages.push(
             // send the delegate message
             CosmosMsg::Staking(StakingMsg::Delegate {
-                validator: all_delegations
-                    .get(random_index)
-                    .unwrap()
-                    .validator
-                    .to_string(),
-                amount: Coin::new(user_rewards.u128(), coin_denom),
+                validator: reward_validator,
+                amount: Coin::new(delegated_rewards.u128(), coin_denom),
             }),
         );
     }
@@ -108,3 +180,221 @@ pub fn execute_update_exchange_rate(
         .add_attribute("reward_collected", claimed_rewards.to_string())
         .add_attribute("protocol_fee", protocol_fee.to_string()))
 }
+
+/// Send the accrued protocol fee.
+/// Only the fee collectors are allowed to execute
+pub fn execute_claim_protocol_fee(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, HubError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let is_collector = config.protocol_fee_collector.as_ref() == Some(&sender_raw)
+        || config
+            .protocol_fee_collectors
+            .iter()
+            .any(|(collector, _)| *collector == sender_raw);
+    if !is_collector {
+        return Err(HubError::Unauthorized {});
+    }
+
+    let accrued_fee = ACCRUED_FEE.may_load(deps.storage)?.unwrap_or_default();
+    if accrued_fee.is_zero() {
+        return Err(HubError::NoAccruedFee {});
+    }
+
+    ACCRUED_FEE.save(deps.storage, &Uint128::zero())?;
+    STATE.update(deps.storage, |mut state| -> StdResult<State> {
+        state.prev_hub_balance = state.prev_hub_balance.saturating_sub(accrued_fee);
+        Ok(state)
+    })?;
+
+    let coin_denom = PARAMETERS.load(deps.storage)?.underlying_coin_denom;
+    let messages = protocol_fee_msgs(deps.as_ref(), &config, accrued_fee, &coin_denom)?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "claim_protocol_fee"),
+        attr("amount", accrued_fee),
+    ]))
+}
+
+/// Delegate the idle underlying balance above the dust threshold to the least staked validator.
+/// The unbonded amount owed to the unreleased batches, the released withdrawals
+/// and the accrued protocol fee are never restaked.
+/// Permissionless
+pub fn execute_restake_idle(deps: DepsMut, env: Env) -> Result<Response, HubError> {
+    let params = PARAMETERS.load(deps.storage)?;
+    let coin_denom = params.underlying_coin_denom;
+    let mut state = STATE.load(deps.storage)?;
+
+    let hub_balance = deps
+        .querier
+        .query_balance(&env.contract.address, &*coin_denom)?
+        .amount;
+
+    // the withdrawals and the accrued fee are held below prev_hub_balance,
+    // anything above it or kept as unbonded amount is left for the unreleased batches
+    let unbonded_pool =
+        state.actual_unbonded_amount + hub_balance.saturating_sub(state.prev_hub_balance);
+    let reserved = read_unreleased_unbond_history(deps.storage, state.last_processed_batch)?
+        .iter()
+        .fold(Uint128::zero(), |total, history| {
+            total + history.amount * history.withdraw_rate
+        });
+    let received_idle = unbonded_pool.saturating_sub(reserved);
+    let restake_amount = (received_idle + state.liquidity_reserve)
+        .saturating_sub(state.total_bond_amount * params.liquidity_reserve_ratio)
+        .saturating_sub(params.restake_dust_threshold);
+    if restake_amount.is_zero() {
+        return Err(StdError::generic_err("No idle balance above the dust threshold").into());
+    }
+
+    let validator = least_staked_validator(deps.as_ref(), &env)?;
+
+    draw_idle_liquidity(
+        &mut state,
+        hub_balance,
+        unbonded_pool,
+        received_idle,
+        restake_amount,
+    )?;
+
+    // the restaked funds back the existing supply
+    let requested_with_fee = CURRENT_BATCH.load(deps.storage)?.requested_with_fee;
+    let total_issued = query_total_issued(deps.as_ref())?;
+    state.total_bond_amount = state.total_bond_amount.checked_add(restake_amount)?;
+    state.update_exchange_rate(total_issued, requested_with_fee);
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: validator.clone(),
+            amount: Coin::new(restake_amount.u128(), coin_denom),
+        }))
+        .add_attributes(vec![
+            attr("action", "restake_idle"),
+            attr("validator", validator),
+            attr("amount", restake_amount),
+        ]))
+}
+
+/// The idle underlying balance left once the unreleased batches, the released withdrawals
+/// and the accrued protocol fee are set aside, including the liquidity reserve
+pub fn idle_liquidity(deps: Deps, env: &Env, state: &State) -> StdResult<Uint128> {
+    let coin_denom = PARAMETERS.load(deps.storage)?.underlying_coin_denom;
+    let hub_balance = deps
+        .querier
+        .query_balance(&env.contract.address, &*coin_denom)?
+        .amount;
+    let unbonded_pool =
+        state.actual_unbonded_amount + hub_balance.saturating_sub(state.prev_hub_balance);
+    let reserved = read_unreleased_unbond_history(deps.storage, state.last_processed_batch)?
+        .iter()
+        .fold(Uint128::zero(), |total, history| {
+            total + history.amount * history.withdraw_rate
+        });
+    Ok(unbonded_pool.saturating_sub(reserved) + state.liquidity_reserve)
+}
+
+/// Account `amount` leaving the idle liquidity, taken from the received balance first
+/// and from the liquidity reserve for the rest.
+/// The received balance is accounted like a release does, minus the drawn amount
+pub(crate) fn draw_idle_liquidity(
+    state: &mut State,
+    hub_balance: Uint128,
+    unbonded_pool: Uint128,
+    received_idle: Uint128,
+    amount: Uint128,
+) -> StdResult<()> {
+    let from_reserve = amount.saturating_sub(received_idle);
+    state.liquidity_reserve = state.liquidity_reserve.checked_sub(from_reserve)?;
+    state.actual_unbonded_amount = unbonded_pool.checked_sub(amount - from_reserve)?;
+    state.prev_hub_balance = hub_balance.checked_sub(amount)?;
+    Ok(())
+}
+
+/// The whitelisted validator accepting new bonds with the smallest delegation of the hub
+fn least_staked_validator(deps: Deps, env: &Env) -> StdResult<String> {
+    let delegations = deps
+        .querier
+        .query_all_delegations(env.contract.address.clone())?;
+
+    let mut least_staked: Option<(String, Uint128)> = None;
+    for validator in read_active_validators(deps.storage)? {
+        let delegated = delegations
+            .iter()
+            .find(|delegation| delegation.validator == validator)
+            .map(|delegation| delegation.amount.amount)
+            .unwrap_or_default();
+        if least_staked
+            .as_ref()
+            .map_or(true, |(_, amount)| delegated < *amount)
+        {
+            least_staked = Some((validator, delegated));
+        }
+    }
+
+    least_staked
+        .map(|(validator, _)| validator)
+        .ok_or_else(|| StdError::generic_err("No validator accepts new bonds"))
+}
+
+/// Send the protocol fee to the weighted fee collectors, or to the single collector.
+fn protocol_fee_msgs(
+    deps: Deps,
+    config: &Config,
+    protocol_fee: Uint128,
+    coin_denom: &str,
+) -> StdResult<Vec<CosmosMsg>> {
+    if !config.protocol_fee_collectors.is_empty() {
+        return split_protocol_fee(
+            deps,
+            &config.protocol_fee_collectors,
+            protocol_fee,
+            coin_denom,
+        );
+    }
+
+    match &config.protocol_fee_collector {
+        Some(fee_collector) => Ok(vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: deps.api.addr_humanize(fee_collector)?.to_string(),
+            amount: vec![Coin::new(protocol_fee.u128(), coin_denom)],
+        })]),
+        None => Err(StdError::generic_err(
+            "protocol fee collector address has not been set",
+        )),
+    }
+}
+
+/// Split the protocol fee between the weighted fee collectors.
+/// The rounding remainder is sent to the first collector.
+fn split_protocol_fee(
+    deps: Deps,
+    collectors: &[(CanonicalAddr, Decimal)],
+    protocol_fee: Uint128,
+    coin_denom: &str,
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut shares: Vec<Uint128> = collectors
+        .iter()
+        .map(|(_, weight)| protocol_fee * *weight)
+        .collect();
+    let distributed = shares
+        .iter()
+        .fold(Uint128::zero(), |total, share| total + *share);
+    shares[0] += protocol_fee.checked_sub(distributed)?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for ((collector, _), share) in collectors.iter().zip(shares) {
+        if share.is_zero() {
+            continue;
+        }
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: deps.api.addr_humanize(collector)?.to_string(),
+            amount: vec![Coin::new(share.u128(), coin_denom)],
+        }));
+    }
+
+    Ok(messages)
+}
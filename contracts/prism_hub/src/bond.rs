@@ -1,31 +1,65 @@
 use crate::contract::{query_total_issued, slashing};
-use crate::math::decimal_division;
-use crate::state::{is_valid_validator, CONFIG, CURRENT_BATCH, PARAMETERS, STATE};
-use basset::hub::State;
+use crate::error::HubError;
+use crate::math::{decimal_division, peg_fee};
+use crate::state::{
+    is_valid_validator, read_white_validator, CONFIG, CURRENT_BATCH, LAST_BOND_TIME, PARAMETERS,
+    STATE, USER_STATS,
+};
+use basset::hub::{State, UserStats, ValidatorStatus};
 use cosmwasm_std::{
-    attr, to_binary, CosmosMsg, DepsMut, Env, MessageInfo, Response, StakingMsg, StdError,
-    StdResult, Uint128, WasmMsg,
+    attr, to_binary, Addr, CosmosMsg, DepsMut, Env, MessageInfo, Response, StakingMsg, StdResult,
+    Uint128, WasmMsg,
 };
 use cw20::Cw20ExecuteMsg;
 
 pub fn execute_bond(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    validator: String,
+) -> Result<Response, HubError> {
+    let recipient = info.sender.clone();
+    bond(deps, env, info, validator, recipient, None)
+}
+
+/// Bond the sent coins on behalf of `recipient`, who receives the minted token.
+/// With `min_mint`, the bond fails if less would be minted.
+pub fn execute_bond_for(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    validator: String,
+    recipient: String,
+    min_mint: Option<Uint128>,
+) -> Result<Response, HubError> {
+    let recipient = deps.api.addr_validate(&recipient)?;
+    bond(deps, env, info, validator, recipient, min_mint)
+}
+
+fn bond(
     mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     validator: String,
-) -> StdResult<Response> {
+    recipient: Addr,
+    min_mint: Option<Uint128>,
+) -> Result<Response, HubError> {
     // validator must be whitelisted
     let is_valid = is_valid_validator(deps.storage, validator.clone())?;
     if !is_valid {
-        return Err(StdError::generic_err(
-            "The chosen validator is currently not supported",
-        ));
+        return Err(HubError::ValidatorNotWhitelisted {});
+    }
+    if read_white_validator(deps.storage, validator.clone())?.validator_status
+        == ValidatorStatus::NoNewBonds
+    {
+        return Err(HubError::ValidatorNoNewBonds {});
     }
 
     let params = PARAMETERS.load(deps.storage)?;
     let coin_denom = params.underlying_coin_denom;
     let threshold = params.er_threshold;
     let recovery_fee = params.peg_recovery_fee;
+    let round_up_peg_fee = params.round_up_peg_fee;
 
     // current batch requested fee is need for accurate exchange rate computation.
     let current_batch = CURRENT_BATCH.load(deps.storage)?;
@@ -33,21 +67,19 @@ pub fn execute_bond(
 
     // coin must have be sent along with transaction and it should be in underlying coin denom
     if info.funds.len() > 1usize {
-        return Err(StdError::generic_err(
-            "More than one coin is sent; only one asset is supported",
-        ));
+        return Err(HubError::MultipleCoins {});
     }
 
     let payment = info
         .funds
         .iter()
         .find(|x| x.denom == coin_denom && x.amount > Uint128::zero())
-        .ok_or_else(|| {
-            StdError::generic_err(format!("No {} assets are provided to bond", coin_denom))
+        .ok_or_else(|| HubError::NoFundsToBond {
+            denom: coin_denom.clone(),
         })?;
 
     // check slashing
-    slashing(&mut deps, env)?;
+    slashing(&mut deps, env.clone())?;
 
     let state = STATE.load(deps.storage)?;
     let sender = info.sender;
@@ -57,13 +89,24 @@ pub fn execute_bond(
 
     // peg recovery fee should be considered
     let mint_amount = decimal_division(payment.amount, state.exchange_rate);
-    let mut mint_amount_with_fee = mint_amount;
-    if state.exchange_rate < threshold {
-        let max_peg_fee = mint_amount * recovery_fee;
-        let required_peg_fee = ((total_supply + mint_amount + current_batch.requested_with_fee)
-            .checked_sub(state.total_bond_amount + payment.amount))?;
-        let peg_fee = Uint128::min(max_peg_fee, required_peg_fee);
-        mint_amount_with_fee = (mint_amount.checked_sub(peg_fee))?;
+    let peg_fee = peg_fee(
+        mint_amount,
+        state.exchange_rate,
+        threshold,
+        recovery_fee,
+        round_up_peg_fee,
+        total_supply + mint_amount + current_batch.requested_with_fee,
+        state.total_bond_amount + payment.amount,
+    );
+    let mint_amount_with_fee = mint_amount.saturating_sub(peg_fee);
+
+    if let Some(min_mint) = min_mint {
+        if mint_amount_with_fee < min_mint {
+            return Err(HubError::MintBelowMinimum {
+                minted: mint_amount_with_fee,
+                min_mint,
+            });
+        }
     }
 
     // total supply should be updated for exchange rate calculation.
@@ -76,6 +119,22 @@ pub fn execute_bond(
         Ok(prev_state)
     })?;
 
+    // the bond is credited to the recipient of the minted token
+    USER_STATS.update(deps.storage, &recipient, |stats| -> StdResult<UserStats> {
+        let mut stats = stats.unwrap_or_default();
+        stats.total_bonded = stats.total_bonded.checked_add(payment.amount)?;
+        stats.bond_count += 1;
+        Ok(stats)
+    })?;
+    // only a self-bond starts the unbond cool-off, a third party bonding for the
+    // recipient must not delay the unbond of the recipient
+    if recipient == sender {
+        LAST_BOND_TIME.save(deps.storage, &recipient, &env.block.time.seconds())?;
+    }
+
+    // The delegate always precedes the mint. Neither message has a reply, so a failing
+    // delegate reverts the whole bond including the mint. Catching the failure in a reply
+    // would commit the mint without the delegation, so none must be added.
     let mut messages: Vec<CosmosMsg> = vec![
         // send the delegate message
         CosmosMsg::Staking(StakingMsg::Delegate {
@@ -84,9 +143,9 @@ pub fn execute_bond(
         }),
     ];
 
-    // issue the basset token for sender
+    // issue the basset token for the recipient
     let mint_msg = Cw20ExecuteMsg::Mint {
-        recipient: sender.to_string(),
+        recipient: recipient.to_string(),
         amount: mint_amount_with_fee,
     };
 
@@ -109,6 +168,7 @@ pub fn execute_bond(
     Ok(Response::new().add_messages(messages).add_attributes(vec![
         attr("action", "mint"),
         attr("from", sender),
+        attr("recipient", recipient),
         attr("bonded", payment.amount),
         attr("minted", mint_amount_with_fee),
     ]))
@@ -1,13 +1,20 @@
+use crate::autho_compounding::draw_idle_liquidity;
+use crate::error::HubError;
 use crate::state::{
-    read_validators, remove_white_validators, store_white_validators, ADMIN, CONFIG, PARAMETERS,
+    is_valid_validator, read_active_validators, read_unreleased_unbond_history, read_validators,
+    read_white_validator, remove_white_validators, store_white_validator, store_white_validators,
+    ADMIN, CONFIG, DEREGISTER_REDELEGATE_REPLY_ID, EMERGENCY_LOG, EMERGENCY_RECOVERY_ADDRESS,
+    PARAMETERS, PAUSE, PENDING_DEREGISTER, STATE, VALIDATOR_BLOCKLIST,
 };
-use basset::hub::{Config, ExecuteMsg, Parameters};
+use basset::hub::{Config, ExecuteMsg, OptionalParam, Parameters, ValidatorStatus};
 use cosmwasm_std::{
-    attr, to_binary, Addr, CosmosMsg, Decimal, DepsMut, Env, MessageInfo, Response, StakingMsg,
-    StdError, StdResult, WasmMsg,
+    attr, coin, to_binary, Addr, BankMsg, CanonicalAddr, CosmosMsg, Decimal, Deps, DepsMut,
+    DistributionMsg, Env, MessageInfo, QueryRequest, Reply, Response, StakingMsg, StdError,
+    StdResult, SubMsg, Uint128, Validator, WasmMsg, WasmQuery,
 };
+use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, MinterResponse, TokenInfoResponse};
 
-use crate::utility::unwrap_assert_admin;
+use crate::utility::{next_selection_seed, unwrap_assert_admin};
 use rand::{Rng, SeedableRng, XorShiftRng};
 
 /// Update general parameters
@@ -22,10 +29,45 @@ pub fn execute_update_params(
     peg_recovery_fee: Option<Decimal>,
     er_threshold: Option<Decimal>,
     protocol_fee: Option<Decimal>,
-) -> StdResult<Response> {
+    min_undelegate_per_validator: Option<Uint128>,
+    max_validators: Option<u64>,
+    max_exchange_rate: Option<Decimal>,
+    max_requests_per_batch: Option<u64>,
+    track_positive_drift: Option<bool>,
+    round_protocol_fee: Option<bool>,
+    accrue_protocol_fee: Option<bool>,
+    round_up_peg_fee: Option<bool>,
+    min_compound_interval: Option<u64>,
+    restake_dust_threshold: Option<Uint128>,
+    min_protocol_fee_transfer: Option<Uint128>,
+    max_withdraws_per_update: Option<u32>,
+    unbond_return_denom: Option<String>,
+    fee_free_until: Option<u64>,
+    max_validator_commission: Option<Decimal>,
+    instant_redeem_fee: Option<Decimal>,
+    max_open_unbond_batches_per_user: Option<u64>,
+    unbond_cooloff: Option<u64>,
+    liquidity_reserve_ratio: Option<Decimal>,
+) -> Result<Response, HubError> {
     // only owner can send this message
     unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
 
+    if max_withdraws_per_update == Some(0) {
+        return Err(StdError::generic_err(
+            "The max withdraws per update must be greater than zero",
+        )
+        .into());
+    }
+
+    if instant_redeem_fee.map_or(false, |fee| fee > Decimal::one()) {
+        return Err(StdError::generic_err("The instant redeem fee can not be more than 1").into());
+    }
+    if liquidity_reserve_ratio.map_or(false, |ratio| ratio > Decimal::one()) {
+        return Err(
+            StdError::generic_err("The liquidity reserve ratio can not be more than 1").into(),
+        );
+    }
+
     let params: Parameters = PARAMETERS.load(deps.storage)?;
 
     let new_params = Parameters {
@@ -35,6 +77,29 @@ pub fn execute_update_params(
         peg_recovery_fee: peg_recovery_fee.unwrap_or(params.peg_recovery_fee),
         er_threshold: er_threshold.unwrap_or(params.er_threshold),
         protocol_fee: protocol_fee.unwrap_or(params.protocol_fee),
+        min_undelegate_per_validator: min_undelegate_per_validator
+            .unwrap_or(params.min_undelegate_per_validator),
+        max_validators: max_validators.unwrap_or(params.max_validators),
+        max_exchange_rate: max_exchange_rate.or(params.max_exchange_rate),
+        max_requests_per_batch: max_requests_per_batch.unwrap_or(params.max_requests_per_batch),
+        track_positive_drift: track_positive_drift.unwrap_or(params.track_positive_drift),
+        round_protocol_fee: round_protocol_fee.unwrap_or(params.round_protocol_fee),
+        accrue_protocol_fee: accrue_protocol_fee.unwrap_or(params.accrue_protocol_fee),
+        round_up_peg_fee: round_up_peg_fee.unwrap_or(params.round_up_peg_fee),
+        min_compound_interval: min_compound_interval.unwrap_or(params.min_compound_interval),
+        restake_dust_threshold: restake_dust_threshold.unwrap_or(params.restake_dust_threshold),
+        min_protocol_fee_transfer: min_protocol_fee_transfer
+            .unwrap_or(params.min_protocol_fee_transfer),
+        max_withdraws_per_update: max_withdraws_per_update.or(params.max_withdraws_per_update),
+        unbond_return_denom: unbond_return_denom.or(params.unbond_return_denom),
+        fee_free_until: fee_free_until.or(params.fee_free_until),
+        max_validator_commission: max_validator_commission.or(params.max_validator_commission),
+        instant_redeem_fee: instant_redeem_fee.or(params.instant_redeem_fee),
+        max_open_unbond_batches_per_user: max_open_unbond_batches_per_user
+            .unwrap_or(params.max_open_unbond_batches_per_user),
+        unbond_cooloff: unbond_cooloff.unwrap_or(params.unbond_cooloff),
+        liquidity_reserve_ratio: liquidity_reserve_ratio.unwrap_or(params.liquidity_reserve_ratio),
+        keeper_reward: params.keeper_reward,
     };
 
     PARAMETERS.save(deps.storage, &new_params)?;
@@ -42,22 +107,56 @@ pub fn execute_update_params(
     Ok(Response::new().add_attributes(vec![attr("action", "update_params")]))
 }
 
+/// Unset the given optional parameters, including the reward validator of the config.
+/// Only creator/owner is allowed to execute
+pub fn execute_unset_params(
+    deps: DepsMut,
+    info: MessageInfo,
+    params: Vec<OptionalParam>,
+) -> Result<Response, HubError> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    let mut last_params = PARAMETERS.load(deps.storage)?;
+    for param in &params {
+        match param {
+            OptionalParam::MaxExchangeRate => last_params.max_exchange_rate = None,
+            OptionalParam::MaxWithdrawsPerUpdate => last_params.max_withdraws_per_update = None,
+            OptionalParam::UnbondReturnDenom => last_params.unbond_return_denom = None,
+            OptionalParam::FeeFreeUntil => last_params.fee_free_until = None,
+            OptionalParam::MaxValidatorCommission => last_params.max_validator_commission = None,
+            OptionalParam::InstantRedeemFee => last_params.instant_redeem_fee = None,
+            OptionalParam::RewardValidator => {
+                CONFIG.update(deps.storage, |mut last_config| -> StdResult<Config> {
+                    last_config.reward_validator = None;
+                    Ok(last_config)
+                })?;
+            }
+        }
+    }
+    PARAMETERS.save(deps.storage, &last_params)?;
+
+    Ok(Response::new().add_attributes(vec![attr("action", "unset_params")]))
+}
+
 /// Update the config. Update the owner, reward and token contracts.
 /// Only creator/owner is allowed to execute
 pub fn execute_update_config(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     token_contract: Option<String>,
     protocol_fee_collector: Option<String>,
-) -> StdResult<Response> {
+    reward_validator: Option<String>,
+    rewards_contract: Option<String>,
+) -> Result<Response, HubError> {
     unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
 
+    let mut messages: Vec<CosmosMsg> = vec![];
+
     if token_contract.is_some() && CONFIG.load(deps.storage)?.token_contract_registered {
-        return Err(StdError::generic_err(
-            "Token contract has been registered. Cannot change the token contract",
-        ));
+        return Err(HubError::TokenAlreadyRegistered {});
     } else if let Some(token) = token_contract {
+        assert_hub_is_minter(deps.as_ref(), &env, &token)?;
         let token_raw = deps.api.addr_canonicalize(token.as_str())?;
 
         CONFIG.update(deps.storage, |mut last_config| -> StdResult<Config> {
@@ -72,11 +171,264 @@ pub fn execute_update_config(
 
         CONFIG.update(deps.storage, |mut last_config| -> StdResult<Config> {
             last_config.protocol_fee_collector = Some(collector);
+            last_config.protocol_fee_collectors = vec![];
             Ok(last_config)
         })?;
     }
 
-    Ok(Response::new().add_attributes(vec![attr("action", "update_config")]))
+    if let Some(validator) = reward_validator {
+        if !is_valid_validator(deps.storage, validator.clone())? {
+            return Err(StdError::generic_err("The reward validator must be whitelisted").into());
+        }
+        if read_white_validator(deps.storage, validator.clone())?.validator_status
+            == ValidatorStatus::NoNewBonds
+        {
+            return Err(HubError::ValidatorNoNewBonds {});
+        }
+
+        CONFIG.update(deps.storage, |mut last_config| -> StdResult<Config> {
+            last_config.reward_validator = Some(validator);
+            Ok(last_config)
+        })?;
+    }
+
+    if let Some(rewards) = rewards_contract {
+        let rewards_raw = deps.api.addr_canonicalize(rewards.as_str())?;
+
+        CONFIG.update(deps.storage, |mut last_config| -> StdResult<Config> {
+            last_config.rewards_contract = Some(rewards_raw);
+            Ok(last_config)
+        })?;
+
+        // the staking rewards must be withdrawn to the new rewards contract
+        messages.push(CosmosMsg::Distribution(
+            DistributionMsg::SetWithdrawAddress { address: rewards },
+        ));
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![attr("action", "update_config")]))
+}
+
+/// Transfer cw20 tokens that were sent to the hub by mistake.
+/// The basset token is refused, so that the tokens held for the users can not be drained.
+/// Only creator/owner is allowed to execute
+pub fn execute_recover_token(
+    deps: DepsMut,
+    info: MessageInfo,
+    token_addr: String,
+    amount: Uint128,
+    recipient: String,
+) -> Result<Response, HubError> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    let token = deps.api.addr_validate(&token_addr)?;
+    let recipient = deps.api.addr_validate(&recipient)?;
+    if CONFIG.load(deps.storage)?.token_contract
+        == Some(deps.api.addr_canonicalize(token.as_str())?)
+    {
+        return Err(StdError::generic_err("The basset token can not be recovered").into());
+    }
+
+    let transfer = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount,
+        })?,
+        funds: vec![],
+    });
+
+    Ok(Response::new().add_message(transfer).add_attributes(vec![
+        attr("action", "recover_token"),
+        attr("token", token),
+        attr("recipient", recipient),
+        attr("amount", amount),
+    ]))
+}
+
+/// The hub mints and burns the token, so the token contract must answer
+/// cw20 queries and list the hub as its minter.
+fn assert_hub_is_minter(deps: Deps, env: &Env, token_contract: &str) -> StdResult<()> {
+    let _: TokenInfoResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: token_contract.to_string(),
+        msg: to_binary(&Cw20QueryMsg::TokenInfo {})?,
+    }))?;
+    let minter: Option<MinterResponse> =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: token_contract.to_string(),
+            msg: to_binary(&Cw20QueryMsg::Minter {})?,
+        }))?;
+
+    match minter {
+        Some(minter) if minter.minter == env.contract.address.as_str() => Ok(()),
+        _ => Err(StdError::generic_err(
+            "The hub must be the minter of the token contract",
+        )),
+    }
+}
+
+/// Replace the weighted protocol fee collectors. The weights must sum to one.
+/// Only creator/owner is allowed to execute
+pub fn execute_set_fee_collectors(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    collectors: Vec<(String, Decimal)>,
+) -> Result<Response, HubError> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    let mut total_weight = Decimal::zero();
+    let mut fee_collectors: Vec<(CanonicalAddr, Decimal)> = vec![];
+    for (collector, weight) in collectors {
+        total_weight += weight;
+        fee_collectors.push((deps.api.addr_canonicalize(collector.as_str())?, weight));
+    }
+
+    if total_weight != Decimal::one() {
+        return Err(
+            StdError::generic_err("The weights of the fee collectors must sum to one").into(),
+        );
+    }
+
+    CONFIG.update(deps.storage, |mut last_config| -> StdResult<Config> {
+        last_config.protocol_fee_collectors = fee_collectors;
+        Ok(last_config)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![attr("action", "set_fee_collectors")]))
+}
+
+/// Set the keeper reward and the minimum interval between keeper compounds,
+/// the interval is `min_compound_interval`. It must fit in an epoch, zero disables it.
+/// Only creator/owner is allowed to execute
+pub fn execute_set_keeper_config(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    reward: Uint128,
+    min_interval: u64,
+) -> Result<Response, HubError> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    let mut params = PARAMETERS.load(deps.storage)?;
+    if min_interval > params.epoch_period {
+        return Err(StdError::generic_err(format!(
+            "The keeper min interval can not be longer than the epoch period {}",
+            params.epoch_period
+        ))
+        .into());
+    }
+
+    params.keeper_reward = reward;
+    params.min_compound_interval = min_interval;
+    PARAMETERS.save(deps.storage, &params)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_keeper_config"),
+        attr("reward", reward),
+        attr("min_interval", min_interval.to_string()),
+    ]))
+}
+
+/// Undelegate every delegation of the hub to wind the pool down during an incident.
+/// The contract must be paused first, the undelegated coins return to the hub
+/// and are sent to `recovery_address` by `EmergencyRecover` once they arrive.
+/// Only creator/owner is allowed to execute
+pub fn execute_emergency_undelegate_all(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recovery_note: String,
+    recovery_address: String,
+) -> Result<Response, HubError> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    if !PAUSE.load(deps.storage)? {
+        return Err(HubError::NotPaused {});
+    }
+
+    let recovery_address = deps.api.addr_validate(&recovery_address)?;
+
+    let messages: Vec<CosmosMsg> = deps
+        .querier
+        .query_all_delegations(env.contract.address)?
+        .into_iter()
+        .map(|delegation| {
+            CosmosMsg::Staking(StakingMsg::Undelegate {
+                validator: delegation.validator,
+                amount: delegation.amount,
+            })
+        })
+        .collect();
+
+    EMERGENCY_LOG.save(
+        deps.storage,
+        &(env.block.time.seconds(), recovery_note.clone()),
+    )?;
+    EMERGENCY_RECOVERY_ADDRESS.save(deps.storage, &recovery_address)?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "emergency_undelegate_all"),
+        attr("recovery_note", recovery_note),
+        attr("recovery_address", recovery_address),
+    ]))
+}
+
+/// Send the coins returned to the hub since the emergency undelegation to the
+/// recovery address. The unbonded amount owed to the unreleased batches is kept
+/// like `idle_liquidity` does, and the balance held for released batches, the
+/// accrued fee and the liquidity reserve are below `prev_hub_balance` and stay on the hub.
+/// Only creator/owner is allowed to execute, while the contract is paused
+pub fn execute_emergency_recover(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, HubError> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    if !PAUSE.load(deps.storage)? {
+        return Err(HubError::NotPaused {});
+    }
+
+    let recovery_address = EMERGENCY_RECOVERY_ADDRESS
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("No emergency undelegation to recover"))?;
+
+    let params = PARAMETERS.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    let hub_balance = deps
+        .querier
+        .query_balance(env.contract.address, &params.underlying_coin_denom)?
+        .amount;
+    let received = hub_balance.saturating_sub(state.prev_hub_balance);
+    let unbonded_pool = state.actual_unbonded_amount + received;
+    let reserved = read_unreleased_unbond_history(deps.storage, state.last_processed_batch)?
+        .iter()
+        .fold(Uint128::zero(), |total, history| {
+            total + history.amount * history.withdraw_rate
+        });
+    let recovered = unbonded_pool.saturating_sub(reserved);
+    if recovered.is_zero() {
+        return Err(HubError::NothingToWithdraw {
+            denom: params.underlying_coin_denom,
+        });
+    }
+
+    draw_idle_liquidity(&mut state, hub_balance, unbonded_pool, recovered, recovered)?;
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: recovery_address.to_string(),
+            amount: vec![coin(recovered.u128(), params.underlying_coin_denom)],
+        })
+        .add_attributes(vec![
+            attr("action", "emergency_recover"),
+            attr("recovery_address", recovery_address),
+            attr("amount", recovered),
+        ]))
 }
 
 /// Register a white listed validator.
@@ -86,27 +438,16 @@ pub fn execute_register_validator(
     env: Env,
     info: MessageInfo,
     validator: String,
-) -> StdResult<Response> {
+) -> Result<Response, HubError> {
     let admin = ADMIN.get(deps.as_ref())?.unwrap();
 
     let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
     let contract_raw = deps.api.addr_canonicalize(env.contract.address.as_str())?;
     if info.sender != admin && contract_raw != sender_raw {
-        return Err(StdError::generic_err("Caller is not admin"));
-    }
-    // given validator must be first a validator in the system.
-    let exists = deps
-        .querier
-        .query_all_validators()?
-        .iter()
-        .any(|val| val.address == validator);
-    if !exists {
-        return Err(StdError::generic_err(
-            "The specified address is not a validator",
-        ));
+        return Err(HubError::NotAdmin {});
     }
 
-    store_white_validators(deps.storage, validator.clone())?;
+    whitelist_validator(deps, &env, &validator)?;
 
     Ok(Response::new().add_attributes(vec![
         attr("action", "register_validator"),
@@ -114,62 +455,345 @@ pub fn execute_register_validator(
     ]))
 }
 
+/// Register several validators at once, none is registered if any of them is rejected.
+/// Only creator/owner is allowed to execute
+pub fn execute_register_validators(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    validators: Vec<String>,
+) -> Result<Response, HubError> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    // reject an unknown or blocklisted address before anything is stored
+    for validator in &validators {
+        assert_not_blocklisted(deps.as_ref(), validator)?;
+        assert_is_validator(deps.as_ref(), validator)?;
+    }
+
+    let mut attrs = vec![attr("action", "register_validators")];
+    for validator in validators {
+        whitelist_validator(deps.branch(), &env, &validator)?;
+        attrs.push(attr("validator", validator));
+    }
+
+    Ok(Response::new().add_attributes(attrs))
+}
+
+/// Add the validator to the blocklist, it can not be registered until it is removed.
+/// Only creator/owner is allowed to execute
+pub fn execute_blocklist_validator(
+    deps: DepsMut,
+    info: MessageInfo,
+    validator: String,
+) -> Result<Response, HubError> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    VALIDATOR_BLOCKLIST.save(deps.storage, validator.clone(), &())?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "blocklist_validator"),
+        attr("validator", validator),
+    ]))
+}
+
+/// Remove the validator from the blocklist.
+/// Only creator/owner is allowed to execute
+pub fn execute_unblocklist_validator(
+    deps: DepsMut,
+    info: MessageInfo,
+    validator: String,
+) -> Result<Response, HubError> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    if !VALIDATOR_BLOCKLIST.has(deps.storage, validator.clone()) {
+        return Err(StdError::generic_err("Validator is not blocklisted").into());
+    }
+    VALIDATOR_BLOCKLIST.remove(deps.storage, validator.clone());
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "unblocklist_validator"),
+        attr("validator", validator),
+    ]))
+}
+
+fn assert_not_blocklisted(deps: Deps, validator: &str) -> Result<(), HubError> {
+    if VALIDATOR_BLOCKLIST.has(deps.storage, validator.to_string()) {
+        return Err(HubError::ValidatorBlocklisted {
+            validator: validator.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Check that the validator is not blocklisted, is a validator of the chain
+/// and charges no more than the maximum commission
+fn assert_can_whitelist(deps: Deps, validator: &str) -> Result<(), HubError> {
+    assert_not_blocklisted(deps, validator)?;
+    // given validator must be first a validator in the system.
+    let chain_validator = assert_is_validator(deps, validator)?;
+
+    let params = PARAMETERS.load(deps.storage)?;
+    if let Some(max_commission) = params.max_validator_commission {
+        if chain_validator.commission > max_commission {
+            return Err(HubError::ValidatorCommissionTooHigh {
+                commission: chain_validator.commission,
+                max_commission,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that the validator can be whitelisted and store it
+fn whitelist_validator(deps: DepsMut, env: &Env, validator: &str) -> Result<(), HubError> {
+    assert_can_whitelist(deps.as_ref(), validator)?;
+    let validator = validator.to_string();
+
+    // keep the number of whitelisted validators under the cap
+    let max_validators = PARAMETERS.load(deps.storage)?.max_validators;
+    if !is_valid_validator(deps.storage, validator.clone())?
+        && read_validators(deps.storage)?.len() as u64 >= max_validators
+    {
+        return Err(StdError::generic_err(format!(
+            "Cannot whitelist more than {} validators",
+            max_validators
+        ))
+        .into());
+    }
+
+    store_white_validators(deps.storage, validator, env.block.time.seconds())?;
+
+    Ok(())
+}
+
 /// Deregister a previously-whitelisted validator.
+/// If a `replacement` is given, it is whitelisted in the same call and
+/// receives the delegation of the removed validator.
 /// Only creator/owner is allowed to execute
 pub fn execute_deregister_validator(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     validator: String,
-) -> StdResult<Response> {
+    replacement: Option<String>,
+) -> Result<Response, HubError> {
     unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
 
+    if let Some(replacement) = &replacement {
+        if *replacement == validator {
+            return Err(
+                StdError::generic_err("The replacement must be a different validator").into(),
+            );
+        }
+    }
+
     let validators_before_remove = read_validators(deps.storage)?;
 
-    if validators_before_remove.len() == 1 {
-        return Err(StdError::generic_err(
-            "Cannot remove the last whitelisted validator",
-        ));
+    if validators_before_remove.len() == 1 && replacement.is_none() {
+        return Err(StdError::generic_err("Cannot remove the last whitelisted validator").into());
     }
 
+    // the replacement is checked before the validator is removed,
+    // it receives a new delegation
+    if let Some(replacement) = &replacement {
+        if !is_valid_validator(deps.storage, replacement.clone())? {
+            assert_can_whitelist(deps.as_ref(), replacement)?;
+        } else if read_white_validator(deps.storage, replacement.clone())?.validator_status
+            == ValidatorStatus::NoNewBonds
+        {
+            return Err(HubError::ValidatorNoNewBonds {});
+        }
+    }
+
+    let removed = read_white_validator(deps.storage, validator.clone())?;
     remove_white_validators(deps.storage, validator.to_string())?;
 
+    // compounded rewards can not go to a removed validator anymore
+    let mut config = CONFIG.load(deps.storage)?;
+    let was_reward_validator = config.reward_validator.as_ref() == Some(&validator);
+    if was_reward_validator {
+        config.reward_validator = None;
+        CONFIG.save(deps.storage, &config)?;
+    }
+
+    // whitelist the replacement, the set size does not grow
+    if let Some(replacement) = &replacement {
+        if !is_valid_validator(deps.storage, replacement.clone())? {
+            whitelist_validator(deps.branch(), &env, replacement)?;
+        }
+    }
+
     let query = deps
         .querier
         .query_delegation(env.contract.address.clone(), validator.clone());
 
     let mut replaced_val = Addr::unchecked("");
-    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut messages: Vec<SubMsg> = vec![];
 
     if let Ok(q) = query {
         let delegated_amount = q;
-        let validators = read_validators(deps.storage)?;
 
-        // redelegate the amount to a random validator.
-        let block_height = env.block.height;
-        let mut rng = XorShiftRng::seed_from_u64(block_height);
-        let random_index = rng.gen_range(0, validators.len());
-        replaced_val = Addr::unchecked(validators.get(random_index).unwrap().as_str());
+        // redelegate the amount to the replacement or a random validator.
+        replaced_val = match replacement.clone() {
+            Some(replacement) => Addr::unchecked(replacement),
+            None => {
+                let validators = read_active_validators(deps.storage)?;
+                if validators.is_empty() {
+                    return Err(StdError::generic_err("No validator accepts new bonds").into());
+                }
+                let block_height = env.block.height;
+                let seed = next_selection_seed(deps.storage, block_height)?;
+                let mut rng = XorShiftRng::seed_from_u64(seed);
+                let random_index = rng.gen_range(0, validators.len());
+                Addr::unchecked(validators.get(random_index).unwrap().as_str())
+            }
+        };
 
         if let Some(delegation) = delegated_amount {
-            messages.push(CosmosMsg::Staking(StakingMsg::Redelegate {
-                src_validator: validator.to_string(),
-                dst_validator: replaced_val.to_string(),
-                amount: delegation.amount,
-            }));
+            // the validator is whitelisted again if the redelegate fails
+            PENDING_DEREGISTER.save(deps.storage, &(removed, was_reward_validator))?;
+            messages.push(SubMsg::reply_always(
+                CosmosMsg::Staking(StakingMsg::Redelegate {
+                    src_validator: validator.to_string(),
+                    dst_validator: replaced_val.to_string(),
+                    amount: delegation.amount,
+                }),
+                DEREGISTER_REDELEGATE_REPLY_ID,
+            ));
 
             let msg = ExecuteMsg::UpdateGlobalIndex {};
-            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
                 contract_addr: env.contract.address.to_string(),
                 msg: to_binary(&msg)?,
                 funds: vec![],
-            }));
+            })));
         }
     }
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
+    let mut attrs = vec![
         attr("action", "de_register_validator"),
         attr("validator", validator),
         attr("new-validator", replaced_val),
+    ];
+    if let Some(replacement) = replacement {
+        attrs.push(attr("replacement", replacement));
+    }
+
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attributes(attrs))
+}
+
+/// Stop new bonds to a whitelisted validator, its delegation is kept.
+/// Only creator/owner is allowed to execute
+pub fn execute_soft_deregister_validator(
+    deps: DepsMut,
+    info: MessageInfo,
+    validator: String,
+) -> Result<Response, HubError> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    let mut record = read_white_validator(deps.storage, validator.clone())?;
+    record.validator_status = ValidatorStatus::NoNewBonds;
+    store_white_validator(deps.storage, &record)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "soft_deregister_validator"),
+        attr("validator", validator),
+    ]))
+}
+
+/// Restore the whitelist record of a deregistered validator whose stake
+/// could not be redelegated, so that the stake is not left unmanaged.
+/// The pending record is dropped once the redelegate succeeds.
+pub fn reply_deregister_redelegate(deps: DepsMut, msg: Reply) -> StdResult<Response> {
+    let (validator, was_reward_validator) = PENDING_DEREGISTER.load(deps.storage)?;
+    PENDING_DEREGISTER.remove(deps.storage);
+
+    match msg.result.into_result() {
+        Ok(_) => Ok(Response::new().add_attributes(vec![
+            attr("action", "deregister_redelegate"),
+            attr("validator", validator.address),
+        ])),
+        Err(error) => {
+            store_white_validator(deps.storage, &validator)?;
+            if was_reward_validator {
+                CONFIG.update(deps.storage, |mut last_config| -> StdResult<Config> {
+                    last_config.reward_validator = Some(validator.address.clone());
+                    Ok(last_config)
+                })?;
+            }
+
+            Ok(Response::new().add_attributes(vec![
+                attr("action", "restore_validator"),
+                attr("validator", validator.address),
+                attr("error", error),
+            ]))
+        }
+    }
+}
+
+/// Check that the address is a validator of the chain and return it.
+fn assert_is_validator(deps: Deps, validator: &str) -> StdResult<Validator> {
+    deps.querier
+        .query_all_validators()?
+        .into_iter()
+        .find(|val| val.address == validator)
+        .ok_or_else(|| StdError::generic_err("The specified address is not a validator"))
+}
+
+/// Redelegate a part of the pooled delegation to another whitelisted validator.
+/// The stake of the hub is moved as a whole since delegations are not tracked per user.
+/// Only creator/owner is allowed to execute
+pub fn execute_rebond(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    from: String,
+    to: String,
+    amount: Uint128,
+) -> Result<Response, HubError> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    if !is_valid_validator(deps.storage, to.clone())? {
+        return Err(StdError::generic_err("The destination validator is not whitelisted").into());
+    }
+
+    let redelegatable = deps
+        .querier
+        .query_delegation(env.contract.address.clone(), from.clone())?
+        .map(|delegation| delegation.can_redelegate.amount)
+        .unwrap_or_default();
+    if amount.is_zero() || amount > redelegatable {
+        return Err(StdError::generic_err(format!(
+            "Can only rebond up to {} from the source validator",
+            redelegatable
+        ))
+        .into());
+    }
+
+    let messages: Vec<CosmosMsg> = vec![
+        CosmosMsg::Staking(StakingMsg::Redelegate {
+            src_validator: from.clone(),
+            dst_validator: to.clone(),
+            amount: coin(
+                amount.u128(),
+                PARAMETERS.load(deps.storage)?.underlying_coin_denom,
+            ),
+        }),
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_binary(&ExecuteMsg::UpdateGlobalIndex {})?,
+            funds: vec![],
+        }),
+    ];
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "rebond"),
+        attr("from", from),
+        attr("to", to),
+        attr("amount", amount),
     ]))
 }
@@ -2,34 +2,63 @@
 use cosmwasm_std::entry_point;
 use cosmwasm_std::DistributionMsg::SetWithdrawAddress;
 use cosmwasm_std::{
-    attr, from_binary, to_binary, Addr, Binary, CosmosMsg, Decimal, Deps, DepsMut, DistributionMsg,
-    Env, MessageInfo, QueryRequest, Response, StakingMsg, StdError, StdResult, SubMsg, Uint128,
-    WasmMsg, WasmQuery,
+    attr, coins, from_binary, to_binary, Addr, BankMsg, Binary, CosmosMsg, Decimal, Deps, DepsMut,
+    DistributionMsg, Env, MessageInfo, QueryRequest, Reply, Response, StakingMsg, StdError,
+    StdResult, SubMsg, Uint128, WasmMsg, WasmQuery,
 };
 
 use crate::config::{
-    execute_deregister_validator, execute_register_validator, execute_update_config,
-    execute_update_params,
+    execute_blocklist_validator, execute_deregister_validator, execute_emergency_recover,
+    execute_emergency_undelegate_all, execute_rebond, execute_recover_token,
+    execute_register_validator, execute_register_validators, execute_set_fee_collectors,
+    execute_set_keeper_config, execute_soft_deregister_validator, execute_unblocklist_validator,
+    execute_unset_params, execute_update_config, execute_update_params,
+    reply_deregister_redelegate,
 };
+use crate::error::HubError;
 
 use crate::state::{
-    all_unbond_history, get_unbond_requests, query_get_finished_amount, read_validators, ADMIN,
-    CONFIG, CURRENT_BATCH, PARAMETERS, PAUSE, STATE,
+    all_unbond_history, get_unbond_requests, query_get_finished_amount, read_batch_wait_list,
+    read_slash_history, read_unbond_history, read_unreleased_unbond_history,
+    read_validators_limitation, read_white_validator, read_withdrawable_addresses,
+    store_slash_event, ACCRUED_FEE, ADMIN, BATCH_EXCHANGE_RATE, CONFIG, CURRENT_BATCH,
+    DEREGISTER_REDELEGATE_REPLY_ID, INSTANTIATE_REGISTER_REPLY_ID, PARAMETERS, PAUSE,
+    PENDING_INITIAL_BOND, RATE_FROZEN, STATE, USER_STATS, WITHDRAW_CURSOR,
+};
+use crate::unbond::{
+    execute_force_batch_rollover, execute_instant_redeem, execute_process_matured_batches,
+    execute_reassign_unbond_requests, execute_unbond, execute_unbond_direct,
+    execute_withdraw_unbonded, plan_undelegations,
 };
-use crate::unbond::{execute_unbond, execute_withdraw_unbonded};
 
-use crate::autho_compounding::execute_update_exchange_rate;
-use crate::bond::execute_bond;
-use crate::migration::migrate_config;
-use crate::utility::{is_contract_paused, unwrap_assert_admin, validate_params};
+use crate::autho_compounding::{
+    execute_claim_protocol_fee, execute_restake_idle, execute_update_exchange_rate, idle_liquidity,
+};
+use crate::bond::{execute_bond, execute_bond_for};
+use crate::math::{decimal_division, min_effective_reward, peg_fee};
+use crate::migration::{migrate_batch_wait_list, migrate_config, migrate_state};
+use crate::utility::{is_contract_paused, is_rate_frozen, unwrap_assert_admin, validate_params};
 use basset::hub::{
-    AllHistoryResponse, Config, ConfigResponse, CurrentBatch, CurrentBatchResponse, Cw20HookMsg,
-    ExecuteMsg, InstantiateMsg, MigrateMsg, Parameters, QueryMsg, State, StateResponse,
-    UnbondRequestsResponse, WhitelistedValidatorsResponse, WithdrawableUnbondedResponse,
+    ActiveBatch, ActiveBatchesResponse, AllHistoryResponse, BatchExchangeRateResponse,
+    BatchMaturitiesResponse, BatchMaturity, BatchWaitListResponse, BondOrUnbond,
+    BondReconciliationResponse, CompoundHealthResponse, Config, ConfigResponse,
+    ContractVersionResponse, CurrentBatch, CurrentBatchResponse, Cw20HookMsg,
+    DelegationCountResponse, ExecuteMsg, IdleBalanceResponse, InstantiateMsg, KeeperConfigResponse,
+    LiquidityReserveResponse, MigrateMsg, Parameters, PegFeePreviewResponse, QueryMsg,
+    SimulateCompoundResponse, SimulateUndelegateResponse, SlashHistoryResponse,
+    StakedValueResponse, State, StateResponse, StuckBatch, StuckBatchesResponse,
+    UnbondRequestsResponse, UnbondTimelineEntry, UnbondTimelineResponse, UserStatsResponse,
+    ValidatorInfoResponse, WhitelistedValidatorsResponse, WithdrawableAddressesResponse,
+    WithdrawableUnbondedResponse, DEFAULT_MAX_VALIDATORS,
 };
 use basset::rewards::ExecuteMsg::ProcessRewards;
-use cw20::{Cw20QueryMsg, Cw20ReceiveMsg, TokenInfoResponse};
+use cw2::{get_contract_version, set_contract_version};
+use cw20::{BalanceResponse, Cw20QueryMsg, Cw20ReceiveMsg, TokenInfoResponse};
 use cw_controllers::AdminError;
+use signed_integer::SignedInt;
+
+const CONTRACT_NAME: &str = "crates.io:prism-hub";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -41,16 +70,16 @@ pub fn instantiate(
     let sender = info.sender.clone();
     let _sndr_raw = deps.api.addr_canonicalize(sender.as_str())?;
 
-    // keep pause false
-    PAUSE.save(deps.storage, &false)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    PAUSE.save(deps.storage, &msg.start_paused.unwrap_or(false))?;
 
+    // the initial bond is optional, the hub can be instantiated without funds
     let payment = info
         .funds
         .iter()
-        .find(|x| x.denom == msg.underlying_coin_denom && x.amount > Uint128::zero())
-        .ok_or_else(|| {
-            StdError::generic_err(format!("No {} assets are provided to bond", "uluna"))
-        })?;
+        .find(|x| x.denom == msg.underlying_coin_denom && x.amount > Uint128::zero());
+    let bond_amount = payment.map(|coin| coin.amount).unwrap_or_default();
 
     //set the admin
     let admin = deps.api.addr_validate(info.sender.as_str())?;
@@ -62,6 +91,8 @@ pub fn instantiate(
         token_contract: None,
         protocol_fee_collector: None,
         rewards_contract: Some(deps.api.addr_canonicalize(&msg.rewards_contract)?),
+        protocol_fee_collectors: vec![],
+        reward_validator: None,
     };
     CONFIG.save(deps.storage, &data)?;
 
@@ -72,7 +103,14 @@ pub fn instantiate(
         last_index_modification: env.block.time.seconds(),
         last_unbonded_time: env.block.time.seconds(),
         last_processed_batch: 0u64,
-        total_bond_amount: payment.amount,
+        open_batches_count: 0u64,
+        total_burned: Uint128::zero(),
+        total_rewards_compounded: Uint128::zero(),
+        cumulative_rewards_compounded: Uint128::zero(),
+        cumulative_protocol_fees: Uint128::zero(),
+        selection_nonce: 0u64,
+        liquidity_reserve: Uint128::zero(),
+        total_bond_amount: bond_amount,
         ..Default::default()
     };
 
@@ -89,6 +127,26 @@ pub fn instantiate(
         peg_recovery_fee: msg.peg_recovery_fee,
         er_threshold: msg.er_threshold,
         protocol_fee: msg.protocol_fee,
+        min_undelegate_per_validator: Uint128::zero(),
+        max_validators: DEFAULT_MAX_VALIDATORS,
+        max_exchange_rate: None,
+        max_requests_per_batch: 0,
+        track_positive_drift: false,
+        round_protocol_fee: false,
+        accrue_protocol_fee: false,
+        round_up_peg_fee: false,
+        min_compound_interval: 0,
+        restake_dust_threshold: Uint128::zero(),
+        min_protocol_fee_transfer: Uint128::zero(),
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: 0,
+        unbond_cooloff: 0,
+        liquidity_reserve_ratio: Decimal::zero(),
+        keeper_reward: Uint128::zero(),
     };
 
     PARAMETERS.save(deps.storage, &params)?;
@@ -96,26 +154,29 @@ pub fn instantiate(
     let batch = CurrentBatch {
         id: 1,
         requested_with_fee: Default::default(),
+        requests_count: 0,
     };
     CURRENT_BATCH.save(deps.storage, &batch)?;
 
     let mut messages = vec![];
 
-    // register the given validator
+    // register the given validator, the initial bond is delegated by the reply
+    // so that it is not sent to a validator that failed to register
     let register_validator = ExecuteMsg::RegisterValidator {
         validator: msg.validator.clone(),
     };
-    messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: env.contract.address.to_string(),
-        msg: to_binary(&register_validator).unwrap(),
-        funds: vec![],
-    })));
+    messages.push(SubMsg::reply_always(
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_binary(&register_validator).unwrap(),
+            funds: vec![],
+        }),
+        INSTANTIATE_REGISTER_REPLY_ID,
+    ));
 
-    // send the delegate message
-    messages.push(SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
-        validator: msg.validator.to_string(),
-        amount: payment.clone(),
-    })));
+    if let Some(payment) = payment {
+        PENDING_INITIAL_BOND.save(deps.storage, &(msg.validator.clone(), payment.clone()))?;
+    }
 
     messages.push(SubMsg::new(CosmosMsg::Distribution(SetWithdrawAddress {
         address: msg.rewards_contract,
@@ -125,12 +186,17 @@ pub fn instantiate(
         .add_submessages(messages)
         .add_attributes(vec![
             attr("register-validator", msg.validator),
-            attr("bond", payment.amount),
+            attr("bond", bond_amount),
         ]))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, HubError> {
     match msg {
         ExecuteMsg::Pause {} => {
             unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
@@ -144,6 +210,23 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             PAUSE.save(deps.storage, &false)?;
             Ok(Response::new())
         }
+        ExecuteMsg::FreezeExchangeRate {} => {
+            unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+            RATE_FROZEN.save(deps.storage, &true)?;
+            Ok(Response::new().add_attribute("action", "freeze_exchange_rate"))
+        }
+        ExecuteMsg::UnfreezeExchangeRate {} => {
+            unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+            RATE_FROZEN.save(deps.storage, &false)?;
+            Ok(Response::new().add_attribute("action", "unfreeze_exchange_rate"))
+        }
+        ExecuteMsg::EmergencyUndelegateAll {
+            recovery_note,
+            recovery_address,
+        } => execute_emergency_undelegate_all(deps, env, info, recovery_note, recovery_address),
+        ExecuteMsg::EmergencyRecover {} => execute_emergency_recover(deps, env, info),
         ExecuteMsg::Receive(msg) => {
             is_contract_paused(deps.as_ref())?;
             receive_cw20(deps, env, info, msg)
@@ -152,36 +235,101 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             is_contract_paused(deps.as_ref())?;
             execute_bond(deps, env, info, validator)
         }
+        ExecuteMsg::BondFor {
+            validator,
+            recipient,
+            min_mint,
+        } => {
+            is_contract_paused(deps.as_ref())?;
+            execute_bond_for(deps, env, info, validator, recipient, min_mint)
+        }
+        ExecuteMsg::Unbond { amount } => {
+            is_contract_paused(deps.as_ref())?;
+            execute_unbond_direct(deps, env, info, amount)
+        }
         ExecuteMsg::UpdateGlobalIndex {} => {
             is_contract_paused(deps.as_ref())?;
-            execute_update_global(deps, env)
+            is_rate_frozen(deps.as_ref())?;
+            execute_update_global(deps, env, info)
         }
         ExecuteMsg::UpdateExchangeRate {} => {
             is_contract_paused(deps.as_ref())?;
+            is_rate_frozen(deps.as_ref())?;
             execute_update_exchange_rate(deps, env, info)
         }
-        ExecuteMsg::WithdrawUnbonded {} => {
+        ExecuteMsg::ClaimProtocolFee {} => {
             is_contract_paused(deps.as_ref())?;
-            execute_withdraw_unbonded(deps, env, info)
+            execute_claim_protocol_fee(deps, env, info)
         }
-        ExecuteMsg::RegisterValidator { validator } => {
+        ExecuteMsg::WithdrawUnbonded { recipient } => {
             is_contract_paused(deps.as_ref())?;
+            execute_withdraw_unbonded(deps, env, info, recipient)
+        }
+        ExecuteMsg::RegisterValidator { validator } => {
+            // the registration sent by instantiate also runs when the contract starts paused
+            if info.sender != env.contract.address {
+                is_contract_paused(deps.as_ref())?;
+            }
             execute_register_validator(deps, env, info, validator)
         }
-        ExecuteMsg::DeregisterValidator { validator } => {
+        ExecuteMsg::RegisterValidators { validators } => {
             is_contract_paused(deps.as_ref())?;
-            execute_deregister_validator(deps, env, info, validator)
+            execute_register_validators(deps, env, info, validators)
+        }
+        ExecuteMsg::BlocklistValidator { validator } => {
+            execute_blocklist_validator(deps, info, validator)
+        }
+        ExecuteMsg::UnblocklistValidator { validator } => {
+            execute_unblocklist_validator(deps, info, validator)
+        }
+        ExecuteMsg::DeregisterValidator {
+            validator,
+            replacement,
+        } => {
+            is_contract_paused(deps.as_ref())?;
+            execute_deregister_validator(deps, env, info, validator, replacement)
+        }
+        ExecuteMsg::SoftDeregisterValidator { validator } => {
+            is_contract_paused(deps.as_ref())?;
+            execute_soft_deregister_validator(deps, info, validator)
         }
         ExecuteMsg::CheckSlashing {} => {
             is_contract_paused(deps.as_ref())?;
             execute_slashing(deps, env)
         }
+        ExecuteMsg::RestakeIdle {} => {
+            is_contract_paused(deps.as_ref())?;
+            execute_restake_idle(deps, env)
+        }
+        ExecuteMsg::ProcessMaturedBatches { limit } => {
+            is_contract_paused(deps.as_ref())?;
+            execute_process_matured_batches(deps, env, limit)
+        }
         ExecuteMsg::UpdateParams {
             epoch_period,
             unbonding_period,
             peg_recovery_fee,
             er_threshold,
             protocol_fee,
+            min_undelegate_per_validator,
+            max_validators,
+            max_exchange_rate,
+            max_requests_per_batch,
+            track_positive_drift,
+            round_protocol_fee,
+            accrue_protocol_fee,
+            round_up_peg_fee,
+            min_compound_interval,
+            restake_dust_threshold,
+            min_protocol_fee_transfer,
+            max_withdraws_per_update,
+            unbond_return_denom,
+            fee_free_until,
+            max_validator_commission,
+            instant_redeem_fee,
+            max_open_unbond_batches_per_user,
+            unbond_cooloff,
+            liquidity_reserve_ratio,
         } => {
             is_contract_paused(deps.as_ref())?;
             execute_update_params(
@@ -193,14 +341,78 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
                 peg_recovery_fee,
                 er_threshold,
                 protocol_fee,
+                min_undelegate_per_validator,
+                max_validators,
+                max_exchange_rate,
+                max_requests_per_batch,
+                track_positive_drift,
+                round_protocol_fee,
+                accrue_protocol_fee,
+                round_up_peg_fee,
+                min_compound_interval,
+                restake_dust_threshold,
+                min_protocol_fee_transfer,
+                max_withdraws_per_update,
+                unbond_return_denom,
+                fee_free_until,
+                max_validator_commission,
+                instant_redeem_fee,
+                max_open_unbond_batches_per_user,
+                unbond_cooloff,
+                liquidity_reserve_ratio,
             )
         }
+        ExecuteMsg::UnsetParams { params } => {
+            is_contract_paused(deps.as_ref())?;
+            execute_unset_params(deps, info, params)
+        }
         ExecuteMsg::UpdateConfig {
             token_contract,
             protocol_fee_collector,
+            reward_validator,
+            rewards_contract,
         } => {
             is_contract_paused(deps.as_ref())?;
-            execute_update_config(deps, env, info, token_contract, protocol_fee_collector)
+            execute_update_config(
+                deps,
+                env,
+                info,
+                token_contract,
+                protocol_fee_collector,
+                reward_validator,
+                rewards_contract,
+            )
+        }
+        ExecuteMsg::Rebond { from, to, amount } => {
+            is_contract_paused(deps.as_ref())?;
+            execute_rebond(deps, env, info, from, to, amount)
+        }
+        ExecuteMsg::RecoverToken {
+            token_addr,
+            amount,
+            recipient,
+        } => {
+            is_contract_paused(deps.as_ref())?;
+            execute_recover_token(deps, info, token_addr, amount, recipient)
+        }
+        ExecuteMsg::SetFeeCollectors { collectors } => {
+            is_contract_paused(deps.as_ref())?;
+            execute_set_fee_collectors(deps, env, info, collectors)
+        }
+        ExecuteMsg::SetKeeperConfig {
+            reward,
+            min_interval,
+        } => {
+            is_contract_paused(deps.as_ref())?;
+            execute_set_keeper_config(deps, env, info, reward, min_interval)
+        }
+        ExecuteMsg::ForceBatchRollover {} => {
+            is_contract_paused(deps.as_ref())?;
+            execute_force_batch_rollover(deps, env, info)
+        }
+        ExecuteMsg::ReassignUnbondRequests { from, to } => {
+            is_contract_paused(deps.as_ref())?;
+            execute_reassign_unbond_requests(deps, info, from, to)
         }
         ExecuteMsg::UpdateAdmin { admin } => {
             is_contract_paused(deps.as_ref())?;
@@ -208,8 +420,8 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             match ADMIN.execute_update_admin(deps, info, Some(admin)) {
                 Ok(r) => Ok(r),
                 Err(e) => match e {
-                    AdminError::NotAdmin {} => Err(StdError::generic_err("Caller is not admin")),
-                    AdminError::Std(std_error) => Err(std_error),
+                    AdminError::NotAdmin {} => Err(HubError::NotAdmin {}),
+                    AdminError::Std(std_error) => Err(std_error.into()),
                 },
             }
         }
@@ -222,7 +434,7 @@ pub fn receive_cw20(
     env: Env,
     info: MessageInfo,
     cw20_msg: Cw20ReceiveMsg,
-) -> StdResult<Response> {
+) -> Result<Response, HubError> {
     let contract_addr = info.sender.clone();
 
     match from_binary(&cw20_msg.msg) {
@@ -234,17 +446,39 @@ pub fn receive_cw20(
                     .token_contract
                     .expect("the token contract must have been registered")
             {
-                return Err(StdError::generic_err("unauthorized"));
+                return Err(HubError::Unauthorized {});
             }
             execute_unbond(deps, env, info, cw20_msg.amount, cw20_msg.sender)
         }
-        Err(err) => Err(err),
+        Ok(Cw20HookMsg::InstantRedeem {}) => {
+            // only token contract can execute this message
+            let conf = CONFIG.load(deps.storage)?;
+            if deps.api.addr_canonicalize(contract_addr.as_str())?
+                != conf
+                    .token_contract
+                    .expect("the token contract must have been registered")
+            {
+                return Err(HubError::Unauthorized {});
+            }
+            execute_instant_redeem(deps, env, cw20_msg.amount, cw20_msg.sender)
+        }
+        Err(err) => Err(err.into()),
     }
 }
 
 /// Update general parameters
 /// Permissionless
-pub fn execute_update_global(deps: DepsMut, env: Env) -> StdResult<Response> {
+pub fn execute_update_global(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, HubError> {
+    let params = PARAMETERS.load(deps.storage)?;
+    let last_index_modification = STATE.load(deps.storage)?.last_index_modification;
+    if env.block.time.seconds() < last_index_modification + params.min_compound_interval {
+        return Err(HubError::CompoundTooSoon {});
+    }
+
     let mut messages: Vec<SubMsg> = vec![];
 
     let contract_addr = env.contract.address.clone();
@@ -253,8 +487,16 @@ pub fn execute_update_global(deps: DepsMut, env: Env) -> StdResult<Response> {
         .api
         .addr_humanize(&CONFIG.load(deps.storage)?.rewards_contract.unwrap())?;
 
+    // the balance before the withdrawals, for keepers to tell the rewards apart
+    let principle_balance = deps
+        .querier
+        .query_balance(&contract_addr, &*params.underlying_coin_denom)?
+        .amount;
+
     // Send withdraw message
-    let mut withdraw_msgs = withdraw_all_rewards(&deps, contract_addr)?;
+    let mut withdraw_msgs =
+        withdraw_all_rewards(&mut deps, contract_addr, params.max_withdraws_per_update)?;
+    let validators_withdrawn = withdraw_msgs.len();
     messages.append(&mut withdraw_msgs);
 
     // messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
@@ -269,23 +511,58 @@ pub fn execute_update_global(deps: DepsMut, env: Env) -> StdResult<Response> {
         funds: vec![],
     })));
 
+    // the keeper is paid out of the accrued protocol fee, as far as it covers the reward
+    let accrued_fee = ACCRUED_FEE.may_load(deps.storage)?.unwrap_or_default();
+    let keeper_reward = params.keeper_reward.min(accrued_fee);
+    if !keeper_reward.is_zero() {
+        ACCRUED_FEE.save(deps.storage, &accrued_fee.checked_sub(keeper_reward)?)?;
+        messages.push(SubMsg::new(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: coins(keeper_reward.u128(), &*params.underlying_coin_denom),
+        }));
+    }
+
     //update state last modified
     STATE.update(deps.storage, |mut last_state| -> StdResult<State> {
         last_state.last_index_modification = env.block.time.seconds();
+        // the accrued fee was held below prev_hub_balance
+        last_state.prev_hub_balance = last_state.prev_hub_balance.checked_sub(keeper_reward)?;
         Ok(last_state)
     })?;
 
     Ok(Response::new()
         .add_submessages(messages)
-        .add_attributes(vec![attr("action", "update_global_index")]))
+        .add_attributes(vec![
+            attr("action", "update_global_index"),
+            attr("validators_withdrawn", validators_withdrawn.to_string()),
+            attr("principle_balance_before", principle_balance),
+            attr("keeper_reward", keeper_reward),
+        ]))
 }
 
-/// Create withdraw requests for all validators
-fn withdraw_all_rewards(deps: &DepsMut, delegator: Addr) -> StdResult<Vec<SubMsg>> {
+/// Create withdraw requests for all validators.
+/// With `max_withdraws`, only that many validators are withdrawn from,
+/// starting where the previous call stopped.
+fn withdraw_all_rewards(
+    deps: &mut DepsMut,
+    delegator: Addr,
+    max_withdraws: Option<u32>,
+) -> StdResult<Vec<SubMsg>> {
     let mut messages: Vec<SubMsg> = vec![];
     let delegations = deps.querier.query_all_delegations(delegator);
 
-    if let Ok(delegations) = delegations {
+    if let Ok(mut delegations) = delegations {
+        if let Some(max_withdraws) = max_withdraws {
+            let max_withdraws = max_withdraws as usize;
+            let count = delegations.len();
+            if count > max_withdraws {
+                let cursor = WITHDRAW_CURSOR.may_load(deps.storage)?.unwrap_or_default();
+                let start = (cursor % count as u64) as usize;
+                delegations.rotate_left(start);
+                delegations.truncate(max_withdraws);
+                WITHDRAW_CURSOR.save(deps.storage, &(((start + max_withdraws) % count) as u64))?;
+            }
+        }
         for delegation in delegations {
             let msg: CosmosMsg =
                 CosmosMsg::Distribution(DistributionMsg::WithdrawDelegatorReward {
@@ -326,6 +603,24 @@ pub fn slashing(deps: &mut DepsMut, env: Env) -> StdResult<()> {
 
         // Slashing happens if the expected amount is less than stored amount
         if state_total_bonded.u128() > actual_total_bonded.u128() {
+            let state = STATE.update(deps.storage, |mut state| -> StdResult<State> {
+                state.total_bond_amount = actual_total_bonded;
+                state.update_exchange_rate(total_issued, current_requested_fee);
+                Ok(state)
+            })?;
+            store_slash_event(
+                deps.storage,
+                env.block.time.seconds(),
+                state_total_bonded,
+                actual_total_bonded,
+                state.exchange_rate,
+            )?;
+        } else if params.track_positive_drift
+            && actual_total_bonded.u128() > state_total_bonded.u128()
+        {
+            // Delegation amounts do not include pending rewards, and compounded rewards
+            // are added to the stored amount in UpdateExchangeRate, so any surplus here
+            // was delegated to the hub from outside.
             STATE.update(deps.storage, |mut state| -> StdResult<State> {
                 state.total_bond_amount = actual_total_bonded;
                 state.update_exchange_rate(total_issued, current_requested_fee);
@@ -338,7 +633,7 @@ pub fn slashing(deps: &mut DepsMut, env: Env) -> StdResult<()> {
 }
 
 /// Handler for tracking slashing
-pub fn execute_slashing(mut deps: DepsMut, env: Env) -> StdResult<Response> {
+pub fn execute_slashing(mut deps: DepsMut, env: Env) -> Result<Response, HubError> {
     // call slashing
     slashing(&mut deps, env)?;
     // read state for log
@@ -349,13 +644,64 @@ pub fn execute_slashing(mut deps: DepsMut, env: Env) -> StdResult<Response> {
     ]))
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> StdResult<Response> {
+    match msg.id {
+        DEREGISTER_REDELEGATE_REPLY_ID => reply_deregister_redelegate(deps, msg),
+        INSTANTIATE_REGISTER_REPLY_ID => reply_instantiate_register(deps, msg),
+        _ => Err(StdError::generic_err(format!(
+            "Unknown reply id {}",
+            msg.id
+        ))),
+    }
+}
+
+/// Delegate the initial bond once the initial validator is registered.
+/// If the registration failed, the initial bond stays on the hub and is no longer
+/// counted as bonded, so it can be restaked once a validator is registered.
+fn reply_instantiate_register(deps: DepsMut, msg: Reply) -> StdResult<Response> {
+    let pending_bond = PENDING_INITIAL_BOND.may_load(deps.storage)?;
+    PENDING_INITIAL_BOND.remove(deps.storage);
+
+    match msg.result.into_result() {
+        Ok(_) => {
+            let mut response =
+                Response::new().add_attribute("action", "instantiate_register_validator");
+            if let Some((validator, payment)) = pending_bond {
+                response = response.add_message(CosmosMsg::Staking(StakingMsg::Delegate {
+                    validator,
+                    amount: payment,
+                }));
+            }
+            Ok(response)
+        }
+        Err(error) => {
+            if let Some((_, payment)) = &pending_bond {
+                STATE.update(deps.storage, |mut state| -> StdResult<State> {
+                    state.total_bond_amount =
+                        state.total_bond_amount.saturating_sub(payment.amount);
+                    Ok(state)
+                })?;
+            }
+            Ok(Response::new().add_attributes(vec![
+                attr("action", "instantiate_register_validator_failed"),
+                attr("error", error),
+            ]))
+        }
+    }
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::State {} => to_binary(&query_state(deps)?),
         QueryMsg::CurrentBatch {} => to_binary(&query_current_batch(deps)?),
-        QueryMsg::WhitelistedValidators {} => to_binary(&query_white_validators(deps)?),
+        QueryMsg::WhitelistedValidators {
+            start_after,
+            limit,
+            reverse,
+        } => to_binary(&query_white_validators(deps, start_after, limit, reverse)?),
         QueryMsg::WithdrawableUnbonded { address } => {
             to_binary(&query_withdrawable_unbonded(deps, address, env)?)
         }
@@ -364,7 +710,47 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::AllHistory { start_from, limit } => {
             to_binary(&query_unbond_requests_limitation(deps, start_from, limit)?)
         }
+        QueryMsg::BatchHistory { batch_id } => {
+            to_binary(&read_unbond_history(deps.storage, batch_id)?)
+        }
         QueryMsg::Admin {} => to_binary(&ADMIN.query_admin(deps)?),
+        QueryMsg::ValidatorInfo { validator } => {
+            to_binary(&query_validator_info(deps, env, validator)?)
+        }
+        QueryMsg::CompoundHealth {} => to_binary(&query_compound_health(deps)?),
+        QueryMsg::StakedValue { address } => to_binary(&query_staked_value(deps, address)?),
+        QueryMsg::BatchMaturities {} => to_binary(&query_batch_maturities(deps)?),
+        QueryMsg::StuckBatches {} => to_binary(&query_stuck_batches(deps, env)?),
+        QueryMsg::UnbondTimeline { address } => to_binary(&query_unbond_timeline(deps, address)?),
+        QueryMsg::IdleBalance {} => to_binary(&query_idle_balance(deps, env)?),
+        QueryMsg::UserStats { address } => to_binary(&query_user_stats(deps, address)?),
+        QueryMsg::ActiveBatches {} => to_binary(&query_active_batches(deps, env)?),
+        QueryMsg::BondReconciliation {} => to_binary(&query_bond_reconciliation(deps, env)?),
+        QueryMsg::BatchWaitList {
+            batch_id,
+            start_after,
+            limit,
+        } => to_binary(&query_batch_wait_list(deps, batch_id, start_after, limit)?),
+        QueryMsg::SimulateCompound {} => to_binary(&query_simulate_compound(deps, env)?),
+        QueryMsg::ContractVersion {} => to_binary(&query_contract_version(deps)?),
+        QueryMsg::PegFeePreview { action, amount } => {
+            to_binary(&query_peg_fee_preview(deps, action, amount)?)
+        }
+        QueryMsg::SimulateUndelegate { amount } => {
+            to_binary(&query_simulate_undelegate(deps, env, amount)?)
+        }
+        QueryMsg::LiquidityReserve {} => to_binary(&query_liquidity_reserve(deps, env)?),
+        QueryMsg::DelegationCount {} => to_binary(&query_delegation_count(deps, env)?),
+        QueryMsg::SlashHistory { start_after, limit } => {
+            to_binary(&query_slash_history(deps, start_after, limit)?)
+        }
+        QueryMsg::BatchExchangeRate { batch_id } => {
+            to_binary(&query_batch_exchange_rate(deps, batch_id)?)
+        }
+        QueryMsg::WithdrawableAddresses { start_after, limit } => {
+            to_binary(&query_withdrawable_addresses(deps, start_after, limit)?)
+        }
+        QueryMsg::KeeperConfig {} => to_binary(&query_keeper_config(deps)?),
     }
 }
 
@@ -404,10 +790,18 @@ fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         None
     };
 
+    let fee_collectors = config
+        .protocol_fee_collectors
+        .iter()
+        .map(|(collector, weight)| Ok((deps.api.addr_humanize(collector)?.to_string(), *weight)))
+        .collect::<StdResult<Vec<(String, Decimal)>>>()?;
+
     Ok(ConfigResponse {
         token_contract: token,
         protocol_fee_collector: fee_collector,
         rewards_contract,
+        protocol_fee_collectors: fee_collectors,
+        reward_validator: config.reward_validator,
     })
 }
 
@@ -423,21 +817,366 @@ fn query_state(deps: Deps) -> StdResult<StateResponse> {
         actual_unbonded_amount: state.actual_unbonded_amount,
         last_unbonded_time: state.last_unbonded_time,
         last_processed_batch: state.last_processed_batch,
+        open_batches_count: state.open_batches_count,
+        total_burned: state.total_burned,
+        total_rewards_compounded: state.total_rewards_compounded,
+        cumulative_rewards_compounded: state.cumulative_rewards_compounded,
+        cumulative_protocol_fees: state.cumulative_protocol_fees,
+        selection_nonce: state.selection_nonce,
+        liquidity_reserve: state.liquidity_reserve,
     };
     Ok(res)
 }
 
-fn query_white_validators(deps: Deps) -> StdResult<WhitelistedValidatorsResponse> {
-    let validators = read_validators(deps.storage)?;
+fn query_white_validators(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+    reverse: Option<bool>,
+) -> StdResult<WhitelistedValidatorsResponse> {
+    let validators =
+        read_validators_limitation(deps.storage, start_after, limit, reverse.unwrap_or(false))?;
     let response = WhitelistedValidatorsResponse { validators };
     Ok(response)
 }
 
+fn query_validator_info(
+    deps: Deps,
+    env: Env,
+    validator: String,
+) -> StdResult<ValidatorInfoResponse> {
+    let record = read_white_validator(deps.storage, validator.clone())?;
+    let current_delegation = deps
+        .querier
+        .query_delegation(env.contract.address, validator)?
+        .map(|delegation| delegation.amount.amount)
+        .unwrap_or_default();
+
+    Ok(ValidatorInfoResponse {
+        added_at: record.added_at,
+        current_delegation,
+        validator_status: record.validator_status,
+    })
+}
+
+/// Check whether rewards are still reflected on the exchange rate.
+/// Once the supply is too large for the precision of Decimal,
+/// small rewards are rounded to zero and compounding stalls.
+fn query_compound_health(deps: Deps) -> StdResult<CompoundHealthResponse> {
+    let requested_with_fee = CURRENT_BATCH.load(deps.storage)?.requested_with_fee;
+    let total_issued = query_total_issued(deps)?;
+
+    let min_effective_reward = min_effective_reward(total_issued + requested_with_fee);
+
+    Ok(CompoundHealthResponse {
+        stalled: min_effective_reward > Uint128::new(1),
+        min_effective_reward,
+    })
+}
+
+fn query_staked_value(deps: Deps, address: String) -> StdResult<StakedValueResponse> {
+    if deps.api.addr_validate(address.as_str()).is_err() {
+        return Err(StdError::generic_err("invalid address"));
+    }
+    if CONFIG.load(deps.storage)?.token_contract.is_none() {
+        return Err(StdError::generic_err(
+            "The token contract has not been registered",
+        ));
+    }
+
+    let bluna_balance = query_token_balance(deps, address)?;
+    let exchange_rate = STATE.load(deps.storage)?.exchange_rate;
+
+    Ok(StakedValueResponse {
+        bluna_balance,
+        underlying_value: bluna_balance * exchange_rate,
+    })
+}
+
+fn query_batch_maturities(deps: Deps) -> StdResult<BatchMaturitiesResponse> {
+    let last_processed_batch = STATE.load(deps.storage)?.last_processed_batch;
+    let unbonding_period = PARAMETERS.load(deps.storage)?.unbonding_period;
+
+    let batches = read_unreleased_unbond_history(deps.storage, last_processed_batch)?
+        .into_iter()
+        .map(|history| BatchMaturity {
+            batch_id: history.batch_id,
+            matures_at: history.time + unbonding_period,
+        })
+        .collect();
+
+    Ok(BatchMaturitiesResponse { batches })
+}
+
+fn query_contract_version(deps: Deps) -> StdResult<ContractVersionResponse> {
+    let version = get_contract_version(deps.storage)?;
+    Ok(ContractVersionResponse {
+        contract: version.contract,
+        version: version.version,
+    })
+}
+
+/// The undelegations are planned like an undelegated batch, at the stored exchange rate
+fn query_simulate_undelegate(
+    deps: Deps,
+    env: Env,
+    amount: Uint128,
+) -> StdResult<SimulateUndelegateResponse> {
+    let params = PARAMETERS.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
+    let delegations = deps.querier.query_all_delegations(env.contract.address)?;
+    let undelegations = plan_undelegations(
+        delegations,
+        amount * state.exchange_rate,
+        params.min_undelegate_per_validator,
+        env.block.height,
+    )?;
+    Ok(SimulateUndelegateResponse { undelegations })
+}
+
+fn query_liquidity_reserve(deps: Deps, env: Env) -> StdResult<LiquidityReserveResponse> {
+    let params = PARAMETERS.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
+    Ok(LiquidityReserveResponse {
+        reserve: idle_liquidity(deps, &env, &state)?,
+        target: state.total_bond_amount * params.liquidity_reserve_ratio,
+    })
+}
+
+fn query_delegation_count(deps: Deps, env: Env) -> StdResult<DelegationCountResponse> {
+    let delegations = deps.querier.query_all_delegations(env.contract.address)?;
+    Ok(DelegationCountResponse {
+        count: delegations.len() as u64,
+    })
+}
+
+fn query_slash_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<SlashHistoryResponse> {
+    let slashes = read_slash_history(deps.storage, start_after, limit)?;
+    Ok(SlashHistoryResponse { slashes })
+}
+
+/// The peg recovery fee a bond or unbond would be charged at the current exchange rate
+fn query_peg_fee_preview(
+    deps: Deps,
+    action: BondOrUnbond,
+    amount: Uint128,
+) -> StdResult<PegFeePreviewResponse> {
+    let params = PARAMETERS.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
+    let requested_with_fee = CURRENT_BATCH.load(deps.storage)?.requested_with_fee;
+    let total_supply = query_total_issued(deps).unwrap_or_default();
+
+    let fee = match action {
+        BondOrUnbond::Bond => {
+            let mint_amount = decimal_division(amount, state.exchange_rate);
+            peg_fee(
+                mint_amount,
+                state.exchange_rate,
+                params.er_threshold,
+                params.peg_recovery_fee,
+                params.round_up_peg_fee,
+                total_supply + mint_amount + requested_with_fee,
+                state.total_bond_amount + amount,
+            )
+        }
+        BondOrUnbond::Unbond => peg_fee(
+            amount,
+            state.exchange_rate,
+            params.er_threshold,
+            params.peg_recovery_fee,
+            params.round_up_peg_fee,
+            total_supply + requested_with_fee,
+            state.total_bond_amount,
+        ),
+    };
+
+    Ok(PegFeePreviewResponse { fee })
+}
+
+/// The withdraw messages an UpdateGlobalIndex would send now and the rewards they would claim
+fn query_simulate_compound(deps: Deps, env: Env) -> StdResult<SimulateCompoundResponse> {
+    let coin_denom = PARAMETERS.load(deps.storage)?.underlying_coin_denom;
+    let delegations = deps
+        .querier
+        .query_all_delegations(env.contract.address.clone())?;
+
+    let mut validators = vec![];
+    let mut claimable_rewards = Uint128::zero();
+    for delegation in delegations {
+        if let Some(full_delegation) = deps
+            .querier
+            .query_delegation(env.contract.address.clone(), delegation.validator.clone())?
+        {
+            claimable_rewards = full_delegation
+                .accumulated_rewards
+                .iter()
+                .filter(|reward| reward.denom == coin_denom)
+                .fold(claimable_rewards, |total, reward| total + reward.amount);
+        }
+        validators.push(delegation.validator);
+    }
+
+    Ok(SimulateCompoundResponse {
+        validators,
+        claimable_rewards,
+    })
+}
+
+fn query_batch_wait_list(
+    deps: Deps,
+    batch_id: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<BatchWaitListResponse> {
+    let requests = read_batch_wait_list(deps.storage, batch_id, start_after, limit)?;
+    Ok(BatchWaitListResponse { batch_id, requests })
+}
+
+/// The exchange rate snapshotted when the batch rolled over, a batch still open has none.
+/// The batches closed before the snapshot was stored report their recorded rate
+fn query_batch_exchange_rate(deps: Deps, batch_id: u64) -> StdResult<BatchExchangeRateResponse> {
+    let applied_exchange_rate = match BATCH_EXCHANGE_RATE.may_load(deps.storage, batch_id)? {
+        Some(rate) => rate,
+        None => read_unbond_history(deps.storage, batch_id)?.applied_exchange_rate,
+    };
+    Ok(BatchExchangeRateResponse {
+        batch_id,
+        applied_exchange_rate,
+    })
+}
+
+fn query_withdrawable_addresses(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<WithdrawableAddressesResponse> {
+    let (addresses, last_requester) =
+        read_withdrawable_addresses(deps.storage, start_after, limit)?;
+    Ok(WithdrawableAddressesResponse {
+        addresses,
+        last_requester,
+    })
+}
+
+/// Compare the stored bonded amount with the delegations of the hub,
+/// the same comparison the slashing check makes before updating the state
+fn query_bond_reconciliation(deps: Deps, env: Env) -> StdResult<BondReconciliationResponse> {
+    let coin_denom = PARAMETERS.load(deps.storage)?.underlying_coin_denom;
+    let state_total_bond = STATE.load(deps.storage)?.total_bond_amount;
+
+    let chain_total_delegated = deps
+        .querier
+        .query_all_delegations(env.contract.address)?
+        .into_iter()
+        .filter(|delegation| delegation.amount.denom == coin_denom)
+        .fold(Uint128::zero(), |total, delegation| {
+            total + delegation.amount.amount
+        });
+
+    Ok(BondReconciliationResponse {
+        state_total_bond,
+        chain_total_delegated,
+        drift: SignedInt::from_subtraction(chain_total_delegated, state_total_bond),
+    })
+}
+
+/// Every closed batch that has not been released yet.
+/// The batches up to the last processed one are all released, so they are not read
+fn query_active_batches(deps: Deps, env: Env) -> StdResult<ActiveBatchesResponse> {
+    let unbonding_period = PARAMETERS.load(deps.storage)?.unbonding_period;
+    let last_processed_batch = STATE.load(deps.storage)?.last_processed_batch;
+    let now = env.block.time.seconds();
+
+    let batches = read_unreleased_unbond_history(deps.storage, last_processed_batch)?
+        .into_iter()
+        .map(|history| ActiveBatch {
+            id: history.batch_id,
+            requested_with_fee: history.amount,
+            creation_time: history.time,
+            matured: history.time + unbonding_period <= now,
+        })
+        .collect();
+
+    Ok(ActiveBatchesResponse { batches })
+}
+
+fn query_idle_balance(deps: Deps, env: Env) -> StdResult<IdleBalanceResponse> {
+    let coin_denom = PARAMETERS.load(deps.storage)?.underlying_coin_denom;
+    let amount = deps
+        .querier
+        .query_balance(env.contract.address, coin_denom)?
+        .amount;
+    Ok(IdleBalanceResponse { amount })
+}
+
+fn query_user_stats(deps: Deps, address: String) -> StdResult<UserStatsResponse> {
+    let addr = deps.api.addr_validate(&address)?;
+    let stats = USER_STATS
+        .may_load(deps.storage, &addr)?
+        .unwrap_or_default();
+    Ok(UserStatsResponse {
+        address,
+        total_bonded: stats.total_bonded,
+        total_unbonded: stats.total_unbonded,
+        bond_count: stats.bond_count,
+        unbond_count: stats.unbond_count,
+    })
+}
+
+/// Matured batches are funded in order from the unbonded amount received since the last release,
+/// the ones left without funds are reported as stuck.
+fn query_stuck_batches(deps: Deps, env: Env) -> StdResult<StuckBatchesResponse> {
+    let state = STATE.load(deps.storage)?;
+    let params = PARAMETERS.load(deps.storage)?;
+
+    let hub_balance = deps
+        .querier
+        .query_balance(env.contract.address, &*params.underlying_coin_denom)?
+        .amount;
+    let mut available =
+        state.actual_unbonded_amount + hub_balance.saturating_sub(state.prev_hub_balance);
+
+    let mut batches = vec![];
+    for history in read_unreleased_unbond_history(deps.storage, state.last_processed_batch)? {
+        let matures_at = history.time + params.unbonding_period;
+        if matures_at > env.block.time.seconds() {
+            break;
+        }
+
+        let expected_amount = history.amount * history.withdraw_rate;
+        if expected_amount <= available {
+            available = available.checked_sub(expected_amount)?;
+        } else {
+            available = Uint128::zero();
+            batches.push(StuckBatch {
+                batch_id: history.batch_id,
+                matures_at,
+                expected_amount,
+            });
+        }
+    }
+
+    Ok(StuckBatchesResponse { batches })
+}
+
+fn query_keeper_config(deps: Deps) -> StdResult<KeeperConfigResponse> {
+    let params = PARAMETERS.load(deps.storage)?;
+    Ok(KeeperConfigResponse {
+        reward: params.keeper_reward,
+        min_interval: params.min_compound_interval,
+    })
+}
+
 fn query_current_batch(deps: Deps) -> StdResult<CurrentBatchResponse> {
     let current_batch = CURRENT_BATCH.load(deps.storage)?;
     Ok(CurrentBatchResponse {
         id: current_batch.id,
         requested_with_fee: current_batch.requested_with_fee,
+        requests_count: current_batch.requests_count,
     })
 }
 
@@ -479,6 +1218,24 @@ pub(crate) fn query_total_issued(deps: Deps) -> StdResult<Uint128> {
     Ok(token_info.total_supply)
 }
 
+pub(crate) fn query_token_balance(deps: Deps, address: String) -> StdResult<Uint128> {
+    let token_address = deps
+        .api
+        .addr_humanize(
+            &CONFIG
+                .load(deps.storage)?
+                .token_contract
+                .expect("token contract must have been registered"),
+        )?
+        .to_string();
+    let balance: BalanceResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: token_address,
+        msg: to_binary(&Cw20QueryMsg::Balance { address })?,
+    }))?;
+
+    Ok(balance.balance)
+}
+
 fn query_unbond_requests(deps: Deps, address: String) -> StdResult<UnbondRequestsResponse> {
     if deps.api.addr_validate(address.as_str()).is_err() {
         return Err(StdError::generic_err("invalid address"));
@@ -488,6 +1245,32 @@ fn query_unbond_requests(deps: Deps, address: String) -> StdResult<UnbondRequest
     Ok(res)
 }
 
+fn query_unbond_timeline(deps: Deps, address: String) -> StdResult<UnbondTimelineResponse> {
+    if deps.api.addr_validate(address.as_str()).is_err() {
+        return Err(StdError::generic_err("invalid address"));
+    }
+    let params = PARAMETERS.load(deps.storage)?;
+    let last_unbonded_time = STATE.load(deps.storage)?.last_unbonded_time;
+
+    let requests = get_unbond_requests(deps.storage, address.clone())?
+        .into_iter()
+        .map(|(batch_id, requested_amount)| {
+            // the current batch has no history until it is undelegated
+            let batch_time = match read_unbond_history(deps.storage, batch_id) {
+                Ok(history) => history.time,
+                Err(_) => last_unbonded_time + params.epoch_period,
+            };
+            UnbondTimelineEntry {
+                batch_id,
+                requested_amount,
+                estimated_release_time: batch_time + params.unbonding_period,
+            }
+        })
+        .collect();
+
+    Ok(UnbondTimelineResponse { address, requests })
+}
+
 fn query_unbond_requests_limitation(
     deps: Deps,
     start: Option<u64>,
@@ -508,6 +1291,9 @@ pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> StdResult<Response>
 
     let rewards_contract = deps.api.addr_canonicalize(&msg.rewards_contract)?;
     migrate_config(deps.storage, Some(rewards_contract))?;
+    migrate_state(deps.storage)?;
+    migrate_batch_wait_list(deps.storage)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     Ok(Response::new()
         .add_messages(messages)
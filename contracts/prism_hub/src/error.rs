@@ -0,0 +1,77 @@
+use cosmwasm_std::{Decimal, OverflowError, StdError, Uint128};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum HubError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Caller is not admin")]
+    NotAdmin {},
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Contract is paused cannot perform the tx")]
+    Paused {},
+
+    #[error("The exchange rate is frozen")]
+    RateFrozen {},
+
+    #[error("The contract must be paused to undelegate everything")]
+    NotPaused {},
+
+    #[error("The chosen validator is currently not supported")]
+    ValidatorNotWhitelisted {},
+
+    #[error("The chosen validator does not accept new bonds")]
+    ValidatorNoNewBonds {},
+
+    #[error("More than one coin is sent; only one asset is supported")]
+    MultipleCoins {},
+
+    #[error("No {denom} assets are provided to bond")]
+    NoFundsToBond { denom: String },
+
+    #[error("The minted amount {minted} is below the minimum {min_mint}")]
+    MintBelowMinimum { minted: Uint128, min_mint: Uint128 },
+
+    #[error("unbond amount exceeds balance")]
+    UnbondExceedsBalance {},
+
+    #[error("The current batch is empty")]
+    EmptyBatch {},
+
+    #[error("No withdrawable {denom} assets are available yet")]
+    NothingToWithdraw { denom: String },
+
+    #[error("Requests are open in {max} unreleased batches, withdraw the matured batches first")]
+    TooManyOpenBatches { max: u64 },
+
+    #[error("Unbonding is in the cool-off after the last bond until {until}")]
+    UnbondCoolOff { until: u64 },
+
+    #[error("Compound called before the minimum compound interval has passed")]
+    CompoundTooSoon {},
+
+    #[error("No protocol fee has accrued")]
+    NoAccruedFee {},
+
+    #[error("Token contract has been registered. Cannot change the token contract")]
+    TokenAlreadyRegistered {},
+
+    #[error("Validator {validator} is blocklisted")]
+    ValidatorBlocklisted { validator: String },
+
+    #[error("The validator commission {commission} exceeds the maximum {max_commission}")]
+    ValidatorCommissionTooHigh {
+        commission: Decimal,
+        max_commission: Decimal,
+    },
+}
+
+impl From<OverflowError> for HubError {
+    fn from(err: OverflowError) -> Self {
+        HubError::Std(err.into())
+    }
+}
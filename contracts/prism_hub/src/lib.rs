@@ -1,6 +1,7 @@
 extern crate core;
 
 pub mod contract;
+pub mod error;
 pub mod state;
 
 mod autho_compounding;
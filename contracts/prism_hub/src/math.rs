@@ -1,12 +1,42 @@
-use cosmwasm_bignumber::Decimal256;
+use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{Decimal, Uint128};
 
-const DECIMAL_FRACTIONAL: Uint128 = Uint128::new(1_000_000_000u128);
+const DECIMAL_PRECISION: u128 = 1_000_000_000_000_000_000u128;
 
-/// return a / b
+/// return a / b rounded down, computed in 256 bits so that `a` is not scaled
+/// in 128 bits and `b` keeps all of its fractional digits.
+/// A quotient above 128 bits is clamped at `Uint128::MAX`
 pub fn decimal_division(a: Uint128, b: Decimal) -> Uint128 {
-    let decimal = Decimal::from_ratio(a, b * DECIMAL_FRACTIONAL);
-    decimal * DECIMAL_FRACTIONAL
+    let a_u256: Uint256 = a.into();
+    let b_u256: Decimal256 = b.into();
+    let quotient = a_u256 / b_u256;
+    if quotient > Uint256::from(u128::MAX) {
+        Uint128::MAX
+    } else {
+        quotient.into()
+    }
+}
+
+/// return the smallest reward for which reward / supply is not rounded to zero
+pub fn min_effective_reward(supply: Uint128) -> Uint128 {
+    let supply = supply.u128();
+    let mut min_reward = supply / DECIMAL_PRECISION;
+    if supply % DECIMAL_PRECISION != 0 {
+        min_reward += 1;
+    }
+    Uint128::new(min_reward.max(1))
+}
+
+/// return a * b rounded to the nearest integer, halves are rounded up
+pub fn round_multiplication(a: Uint128, b: Decimal) -> Uint128 {
+    let floor = a * b;
+    let exact = _decimal_multiplication_in_256(Decimal::from_ratio(a, 1u128), b);
+    let remainder = _decimal_subtraction_in_256(exact, Decimal::from_ratio(floor, 1u128));
+    if remainder >= Decimal::percent(50) {
+        floor + Uint128::new(1)
+    } else {
+        floor
+    }
 }
 
 /// return a * b
@@ -33,6 +63,45 @@ pub fn _decimal_subtraction_in_256(a: Decimal, b: Decimal) -> Decimal {
     c_u256
 }
 
+/// return a * b rounded up
+pub fn ceil_multiplication(a: Uint128, b: Decimal) -> Uint128 {
+    let floor = a * b;
+    let exact = _decimal_multiplication_in_256(Decimal::from_ratio(a, 1u128), b);
+    if exact > Decimal::from_ratio(floor, 1u128) {
+        floor + Uint128::new(1)
+    } else {
+        floor
+    }
+}
+
+/// return the maximum peg recovery fee of `amount`, rounded up if `round_up` is set
+pub fn max_peg_fee(amount: Uint128, recovery_fee: Decimal, round_up: bool) -> Uint128 {
+    if round_up {
+        ceil_multiplication(amount, recovery_fee)
+    } else {
+        amount * recovery_fee
+    }
+}
+
+/// return the peg recovery fee charged on `amount` below the exchange rate threshold.
+/// The fee never exceeds the part of `issued` that `backing` does not cover.
+pub fn peg_fee(
+    amount: Uint128,
+    exchange_rate: Decimal,
+    threshold: Decimal,
+    recovery_fee: Decimal,
+    round_up: bool,
+    issued: Uint128,
+    backing: Uint128,
+) -> Uint128 {
+    if exchange_rate >= threshold {
+        return Uint128::zero();
+    }
+    let max_fee = max_peg_fee(amount, recovery_fee, round_up);
+    // no fee is required if the bonded amount already backs the supply
+    Uint128::min(max_fee, issued.saturating_sub(backing))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -45,6 +114,118 @@ mod tests {
         assert_eq!(res, Uint128::new(500));
     }
 
+    #[test]
+    fn test_decimal_division_large_amount() {
+        // scaling `a` by 10^18 in 128 bits overflows above u128::MAX / 10^18
+        let a = Uint128::new(u128::MAX / 1_000_000_000);
+        assert_eq!(decimal_division(a, Decimal::one()), a);
+        assert_eq!(
+            decimal_division(a, Decimal::percent(50)),
+            Uint128::new(a.u128() * 2)
+        );
+    }
+
+    #[test]
+    fn test_decimal_division_overflow() {
+        assert_eq!(
+            decimal_division(Uint128::new(u128::MAX), Decimal::percent(50)),
+            Uint128::MAX
+        );
+    }
+
+    #[test]
+    fn test_decimal_division_precision() {
+        // truncating b to nine digits used to return 30_000_000_030
+        let b = Decimal::from_ratio(1u128, 3u128);
+        assert_eq!(
+            decimal_division(Uint128::new(10_000_000_000), b),
+            Uint128::new(30_000_000_000)
+        );
+    }
+
+    #[test]
+    fn test_min_effective_reward() {
+        assert_eq!(min_effective_reward(Uint128::zero()), Uint128::new(1));
+        assert_eq!(min_effective_reward(Uint128::new(1000)), Uint128::new(1));
+        assert_eq!(
+            min_effective_reward(Uint128::new(DECIMAL_PRECISION)),
+            Uint128::new(1)
+        );
+        assert_eq!(
+            min_effective_reward(Uint128::new(DECIMAL_PRECISION + 1)),
+            Uint128::new(2)
+        );
+        let supply = Uint128::new(DECIMAL_PRECISION + 1);
+        assert!(Decimal::from_ratio(Uint128::new(1), supply).is_zero());
+        assert!(!Decimal::from_ratio(Uint128::new(2), supply).is_zero());
+    }
+
+    #[test]
+    fn test_ceil_multiplication() {
+        let b = Decimal::percent(10);
+        assert_eq!(ceil_multiplication(Uint128::new(10), b), Uint128::new(1));
+        assert_eq!(ceil_multiplication(Uint128::new(11), b), Uint128::new(2));
+        assert_eq!(ceil_multiplication(Uint128::zero(), b), Uint128::zero());
+    }
+
+    #[test]
+    fn test_peg_fee() {
+        let rate = Decimal::percent(90);
+        let threshold = Decimal::one();
+        let fee = Decimal::percent(1);
+        let amount = Uint128::new(1000);
+        // capped at the uncovered supply
+        assert_eq!(
+            peg_fee(
+                amount,
+                rate,
+                threshold,
+                fee,
+                false,
+                Uint128::new(105),
+                Uint128::new(100)
+            ),
+            Uint128::new(5)
+        );
+        assert_eq!(
+            peg_fee(
+                amount,
+                rate,
+                threshold,
+                fee,
+                false,
+                Uint128::new(500),
+                Uint128::new(100)
+            ),
+            Uint128::new(10)
+        );
+        // no fee at or above the threshold
+        assert_eq!(
+            peg_fee(
+                amount,
+                threshold,
+                threshold,
+                fee,
+                false,
+                Uint128::new(500),
+                Uint128::new(100)
+            ),
+            Uint128::zero()
+        );
+    }
+
+    #[test]
+    fn test_round_multiplication() {
+        let b = Decimal::percent(10);
+        assert_eq!(round_multiplication(Uint128::new(14), b), Uint128::new(1));
+        assert_eq!(round_multiplication(Uint128::new(15), b), Uint128::new(2));
+        assert_eq!(round_multiplication(Uint128::new(20), b), Uint128::new(2));
+        assert_eq!(
+            round_multiplication(Uint128::new(100), Decimal::zero()),
+            Uint128::zero()
+        );
+    }
+
     #[test]
     fn test_decimal_multiplication() {
         let a = Uint128::new(100);
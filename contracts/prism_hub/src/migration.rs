@@ -1,6 +1,9 @@
-use crate::state::CONFIG;
+use crate::state::{
+    all_unbond_history, BATCH_WAIT_LIST, CONFIG, CURRENT_BATCH, PREFIX_WAIT_MAP, STATE,
+};
 use basset::hub::Config;
-use cosmwasm_std::{CanonicalAddr, StdResult, Storage};
+use cosmwasm_std::{from_slice, CanonicalAddr, Order, StdResult, Storage, Uint128};
+use cosmwasm_storage::ReadonlyBucket;
 use cw_storage_plus::Item;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -16,21 +19,106 @@ fn read_legacy_config(storage: &dyn Storage) -> StdResult<LegacyConfig> {
     config.load(storage)
 }
 
+/// Store the config with the fields added since the deployment.
+/// A config that is already migrated keeps its fields, only the rewards contract is updated.
 pub fn migrate_config(
     storage: &mut dyn Storage,
     rewards_contract: Option<CanonicalAddr>,
 ) -> StdResult<()> {
-    let legacy_config = read_legacy_config(storage)?;
-
-    CONFIG.save(
-        storage,
-        &Config {
-            token_contract_registered: false,
-            token_contract: legacy_config.token_contract,
-            protocol_fee_collector: legacy_config.protocol_fee_collector,
+    let config = match CONFIG.load(storage) {
+        Ok(config) => Config {
             rewards_contract,
+            ..config
         },
-    )?;
+        Err(_) => {
+            let legacy_config = read_legacy_config(storage)?;
+            Config {
+                token_contract_registered: false,
+                token_contract: legacy_config.token_contract,
+                protocol_fee_collector: legacy_config.protocol_fee_collector,
+                rewards_contract,
+                protocol_fee_collectors: vec![],
+                reward_validator: None,
+            }
+        }
+    };
 
+    CONFIG.save(storage, &config)
+}
+
+/// Number of unbond histories read at a time by the state migration
+const HISTORY_PAGE: u32 = 100;
+
+#[derive(Serialize, Deserialize)]
+struct StoredCounters {
+    open_batches_count: Option<u64>,
+    total_burned: Option<Uint128>,
+}
+
+/// Store the state with the fields added since the deployment.
+/// `total_burned` is backfilled with the amounts burned into the stored batches and
+/// the current batch, and `open_batches_count` with the number of unreleased batches.
+/// The reward counters `total_rewards_compounded`, `cumulative_rewards_compounded`
+/// and `cumulative_protocol_fees` are not recorded anywhere, so they start from zero
+/// and only count from the migration forward. Fields that are already stored keep
+/// their values.
+pub fn migrate_state(storage: &mut dyn Storage) -> StdResult<()> {
+    let stored: Item<StoredCounters> = Item::new("\u{0}\u{5}state");
+    let stored = stored.load(storage)?;
+    let mut state = STATE.load(storage)?;
+
+    if stored.total_burned.is_none() || stored.open_batches_count.is_none() {
+        let mut total_burned = CURRENT_BATCH
+            .may_load(storage)?
+            .map(|batch| batch.requested_with_fee)
+            .unwrap_or_default();
+        let mut open_batches_count = 0u64;
+        let mut start = None;
+        loop {
+            let histories = all_unbond_history(storage, start, Some(HISTORY_PAGE))?;
+            let last = match histories.last() {
+                Some(history) => history.batch_id,
+                None => break,
+            };
+            for history in histories {
+                total_burned = total_burned.checked_add(history.amount)?;
+                if !history.released {
+                    open_batches_count += 1;
+                }
+            }
+            start = Some(last);
+        }
+
+        if stored.total_burned.is_none() {
+            state.total_burned = total_burned;
+        }
+        if stored.open_batches_count.is_none() {
+            state.open_batches_count = open_batches_count;
+        }
+    }
+
+    STATE.save(storage, &state)
+}
+
+/// Index the stored unbond requests by batch, the requests made after the
+/// migration are indexed when they are stored.
+pub fn migrate_batch_wait_list(storage: &mut dyn Storage) -> StdResult<()> {
+    let requests = ReadonlyBucket::<Uint128>::new(storage, PREFIX_WAIT_MAP)
+        .range(None, None, Order::Ascending)
+        .map(|item| {
+            let (key, amount) = item?;
+            let addr_len = u16::from_be_bytes([key[0], key[1]]) as usize;
+            let (addr, batch) = key[2..].split_at(addr_len);
+            Ok((
+                from_slice::<u64>(batch)?,
+                from_slice::<String>(addr)?,
+                amount,
+            ))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for (batch_id, addr, amount) in requests {
+        BATCH_WAIT_LIST.save(storage, (batch_id, addr.as_str()), &amount)?;
+    }
     Ok(())
 }
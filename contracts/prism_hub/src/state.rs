@@ -1,14 +1,27 @@
-use cosmwasm_std::{from_slice, to_vec, Addr, Order, StdError, StdResult, Storage, Uint128};
-use cosmwasm_storage::{Bucket, PrefixedStorage, ReadonlyBucket, ReadonlyPrefixedStorage};
+use cosmwasm_std::{
+    from_slice, to_vec, Addr, Coin, Decimal, Order, StdError, StdResult, Storage, Uint128,
+};
+use cosmwasm_storage::{
+    to_length_prefixed, Bucket, PrefixedStorage, ReadonlyBucket, ReadonlyPrefixedStorage,
+};
 use cw_controllers::Admin;
-use cw_storage_plus::Item;
+use cw_storage_plus::{Bound, Item, Map};
 
-use basset::hub::{Config, CurrentBatch, Parameters, State, UnbondHistory, UnbondRequest};
+use basset::hub::{
+    Config, CurrentBatch, Parameters, SlashEvent, State, UnbondHistory, UnbondRequest, UserStats,
+    ValidatorStatus, WhitelistedValidator,
+};
 
 pub type LastBatch = u64;
 
 pub const ADMIN: Admin = Admin::new("admin");
 pub static PAUSE: Item<bool> = Item::new("pause");
+/// Set while the exchange rate must not move, unset on older deployments
+pub const RATE_FROZEN: Item<bool> = Item::new("rate_frozen");
+/// Block time and note of the last emergency undelegation
+pub const EMERGENCY_LOG: Item<(u64, String)> = Item::new("emergency_log");
+/// Receiver of the coins returned by the last emergency undelegation
+pub const EMERGENCY_RECOVERY_ADDRESS: Item<Addr> = Item::new("emergency_recovery_address");
 
 pub static PREFIX_WAIT_MAP: &[u8] = b"wait";
 pub static PREFIX_AIRDROP_INFO: &[u8] = b"airedrop_info";
@@ -18,6 +31,32 @@ pub const CONFIG: Item<Config> = Item::new("\u{0}\u{6}config");
 pub const PARAMETERS: Item<Parameters> = Item::new("\u{0}\u{b}parameteres");
 pub const CURRENT_BATCH: Item<CurrentBatch> = Item::new("\u{0}\u{d}current_batch");
 pub const STATE: Item<State> = Item::new("\u{0}\u{5}state");
+/// Protocol fee kept on the hub while `accrue_protocol_fee` is set
+pub const ACCRUED_FEE: Item<Uint128> = Item::new("accrued_fee");
+/// Whitelist record of the validator whose stake is being redelegated by a deregister
+/// and whether it was the reward validator
+pub const PENDING_DEREGISTER: Item<(WhitelistedValidator, bool)> = Item::new("pending_deregister");
+/// Validator and amount of the initial bond, delegated once the validator is registered
+pub const PENDING_INITIAL_BOND: Item<(String, Coin)> = Item::new("pending_initial_bond");
+/// Position in the delegations where the next capped UpdateGlobalIndex starts withdrawing
+pub const WITHDRAW_CURSOR: Item<u64> = Item::new("withdraw_cursor");
+/// Lifetime bond and unbond totals per user
+pub const USER_STATS: Map<&Addr, UserStats> = Map::new("user_stats");
+/// Block time of the last bond credited to each user
+pub const LAST_BOND_TIME: Map<&Addr, u64> = Map::new("last_bond_time");
+/// Validators that can never be whitelisted, e.g. after being tombstoned
+pub const VALIDATOR_BLOCKLIST: Map<String, ()> = Map::new("validator_blocklist");
+/// Unbond requests keyed by batch and requester, the wait map keyed the other way round
+pub const BATCH_WAIT_LIST: Map<(u64, &str), Uint128> = Map::new("batch_wait_list");
+/// State exchange rate at the moment each batch rolled over
+pub const BATCH_EXCHANGE_RATE: Map<u64, Decimal> = Map::new("batch_exchange_rate");
+/// Slashes detected by `slashing`, keyed by an increasing id
+pub const SLASH_HISTORY: Map<u64, SlashEvent> = Map::new("slash_history");
+
+/// Reply id of the redelegate sent by a deregister
+pub const DEREGISTER_REDELEGATE_REPLY_ID: u64 = 1;
+/// Reply id of the validator registration sent by the instantiation
+pub const INSTANTIATE_REGISTER_REPLY_ID: u64 = 2;
 
 /// Store undelegation wait list per each batch
 /// HashMap<user's address, <batch_id, requested_amount>
@@ -34,6 +73,11 @@ pub fn store_unbond_wait_list(
     position_indexer.update(&batch, |asked_already| -> StdResult<Uint128> {
         Ok(asked_already.unwrap_or_default() + amount)
     })?;
+    BATCH_WAIT_LIST.update(
+        storage,
+        (batch_id, sender_address.as_str()),
+        |asked_already| -> StdResult<Uint128> { Ok(asked_already.unwrap_or_default() + amount) },
+    )?;
 
     Ok(())
 }
@@ -47,10 +91,13 @@ pub fn remove_unbond_wait_list(
     let addr = to_vec(&sender_address)?;
     let mut position_indexer: Bucket<Uint128> =
         Bucket::multilevel(storage, &[PREFIX_WAIT_MAP, &addr]);
-    for b in batch_id {
-        let batch = to_vec(&b)?;
+    for b in &batch_id {
+        let batch = to_vec(b)?;
         position_indexer.remove(&batch);
     }
+    for b in batch_id {
+        BATCH_WAIT_LIST.remove(storage, (b, sender_address.as_str()));
+    }
     Ok(())
 }
 
@@ -83,6 +130,82 @@ pub fn get_unbond_requests(storage: &dyn Storage, sender_addr: String) -> StdRes
     Ok(requests)
 }
 
+/// Return the unbond requests of a batch page by page, in the order of the requesters.
+pub fn read_batch_wait_list(
+    storage: &dyn Storage,
+    batch_id: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(String, Uint128)>> {
+    let lim = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+    BATCH_WAIT_LIST
+        .prefix(batch_id)
+        .range(storage, start, None, Order::Ascending)
+        .take(lim)
+        .collect()
+}
+
+/// Return the requesters with a nonzero withdrawable amount in released batches,
+/// in the storage order of the requesters. A page reads at most `limit` requesters,
+/// the last one read is returned to start the next page after while any is left.
+pub fn read_withdrawable_addresses(
+    storage: &dyn Storage,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<(Vec<(String, Uint128)>, Option<String>)> {
+    let lim = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    // skip every batch of the last requester of the previous page
+    let start = match start_after {
+        Some(addr) => {
+            let mut key = to_length_prefixed(&to_vec(&addr)?);
+            key.push(u8::MAX);
+            Some(key)
+        }
+        None => None,
+    };
+
+    let mut requesters: Vec<(Vec<u8>, Uint128)> = vec![];
+    let mut more = false;
+    let res: ReadonlyBucket<Uint128> = ReadonlyBucket::new(storage, PREFIX_WAIT_MAP);
+    for item in res.range(start.as_deref(), None, Order::Ascending) {
+        let (key, amount) = item?;
+        let addr_len = u16::from_be_bytes([key[0], key[1]]) as usize;
+        let (addr, user_batch) = key[2..].split_at(addr_len);
+
+        if requesters
+            .last()
+            .map_or(true, |(last, _)| last.as_slice() != addr)
+        {
+            if requesters.len() >= lim {
+                more = true;
+                break;
+            }
+            requesters.push((addr.to_vec(), Uint128::zero()));
+        }
+
+        if let Ok(h) = read_unbond_history(storage, from_slice(user_batch)?) {
+            if h.released {
+                if let Some((_, withdrawable)) = requesters.last_mut() {
+                    *withdrawable += amount * h.withdraw_rate;
+                }
+            }
+        }
+    }
+
+    let last_requester = match requesters.last() {
+        Some((addr, _)) if more => Some(from_slice(addr)?),
+        _ => None,
+    };
+    let addresses = requesters
+        .into_iter()
+        .filter(|(_, withdrawable)| !withdrawable.is_zero())
+        .map(|(addr, withdrawable)| Ok((from_slice(&addr)?, withdrawable)))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok((addresses, last_requester))
+}
+
 pub fn get_unbond_batches(storage: &dyn Storage, sender_addr: String) -> StdResult<Vec<u64>> {
     let vec = to_vec(&sender_addr)?;
     let mut deprecated_batches: Vec<u64> = vec![];
@@ -155,17 +278,52 @@ pub fn query_get_finished_amount(
     Ok(withdrawable_amount)
 }
 
-/// Store valid validators
+/// Store valid validators along with the time they were whitelisted
 pub fn store_white_validators(
     storage: &mut dyn Storage,
     validator_address: String,
+    added_at: u64,
 ) -> StdResult<()> {
-    let vec = to_vec(&validator_address)?;
-    let value = to_vec(&true)?;
+    store_white_validator(
+        storage,
+        &WhitelistedValidator {
+            address: validator_address,
+            added_at,
+            validator_status: ValidatorStatus::Active,
+        },
+    )
+}
+
+/// Store the whitelist record of a validator as is
+pub fn store_white_validator(
+    storage: &mut dyn Storage,
+    validator: &WhitelistedValidator,
+) -> StdResult<()> {
+    let vec = to_vec(&validator.address)?;
+    let value = to_vec(validator)?;
     PrefixedStorage::new(storage, VALIDATORS).set(&vec, &value);
     Ok(())
 }
 
+/// Read the whitelist record of a validator.
+/// Validators whitelisted before the records were introduced report zero as `added_at`.
+pub fn read_white_validator(
+    storage: &dyn Storage,
+    validator_address: String,
+) -> StdResult<WhitelistedValidator> {
+    let vec = to_vec(&validator_address)?;
+    match ReadonlyPrefixedStorage::new(storage, VALIDATORS).get(&vec) {
+        Some(data) => Ok(from_slice(&data).unwrap_or(WhitelistedValidator {
+            address: validator_address,
+            added_at: 0,
+            validator_status: ValidatorStatus::Active,
+        })),
+        None => Err(StdError::generic_err(
+            "The specified validator is not whitelisted",
+        )),
+    }
+}
+
 /// Remove valid validators
 pub fn remove_white_validators(
     storage: &mut dyn Storage,
@@ -190,6 +348,48 @@ pub fn read_validators(storage: &dyn Storage) -> StdResult<Vec<String>> {
     Ok(validators)
 }
 
+/// Return the whitelisted validators that accept new bonds
+pub fn read_active_validators(storage: &dyn Storage) -> StdResult<Vec<String>> {
+    let mut validators = vec![];
+    for validator in read_validators(storage)? {
+        if read_white_validator(storage, validator.clone())?.validator_status
+            == ValidatorStatus::Active
+        {
+            validators.push(validator);
+        }
+    }
+    Ok(validators)
+}
+
+/// Return whitelisted validators page by page.
+/// With `reverse`, validators are returned in descending order and
+/// `start_after` bounds the page from above.
+pub fn read_validators_limitation(
+    storage: &dyn Storage,
+    start_after: Option<String>,
+    limit: Option<u32>,
+    reverse: bool,
+) -> StdResult<Vec<String>> {
+    let lim = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let bound = match start_after {
+        Some(validator) => Some(to_vec(&validator)?),
+        None => None,
+    };
+
+    let res = ReadonlyPrefixedStorage::new(storage, VALIDATORS);
+    let iter = if reverse {
+        res.range(None, bound.as_deref(), Order::Descending)
+    } else {
+        let start = bound.map(|mut key| {
+            key.push(0);
+            key
+        });
+        res.range(start.as_deref(), None, Order::Ascending)
+    };
+
+    iter.take(lim).map(|(key, _)| from_slice(&key)).collect()
+}
+
 /// Check whether the validator is whitelisted.
 pub fn is_valid_validator(storage: &dyn Storage, validator_address: String) -> StdResult<bool> {
     let vec = to_vec(&validator_address)?;
@@ -224,6 +424,47 @@ pub fn read_unbond_history(storage: &dyn Storage, epoc_id: u64) -> StdResult<Unb
     }
 }
 
+/// Record a slash under the id following the last recorded one
+pub fn store_slash_event(
+    storage: &mut dyn Storage,
+    time: u64,
+    old_bond_amount: Uint128,
+    new_bond_amount: Uint128,
+    exchange_rate: Decimal,
+) -> StdResult<()> {
+    let last_id = SLASH_HISTORY
+        .keys(storage, None, None, Order::Descending)
+        .next()
+        .transpose()?;
+    let id = last_id.map_or(1, |id| id + 1);
+    SLASH_HISTORY.save(
+        storage,
+        id,
+        &SlashEvent {
+            id,
+            time,
+            old_bond_amount,
+            new_bond_amount,
+            exchange_rate,
+        },
+    )
+}
+
+/// Return the recorded slashes after `start_after`, oldest first
+pub fn read_slash_history(
+    storage: &dyn Storage,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<SlashEvent>> {
+    let lim = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    SLASH_HISTORY
+        .range(storage, start, None, Order::Ascending)
+        .take(lim)
+        .map(|item| item.map(|(_, event)| event))
+        .collect()
+}
+
 // settings for pagination
 const MAX_LIMIT: u32 = 100;
 const DEFAULT_LIMIT: u32 = 10;
@@ -249,6 +490,20 @@ pub fn all_unbond_history(
     res
 }
 
+/// Return the unbond histories after `last_processed_batch` that are not released yet.
+pub fn read_unreleased_unbond_history(
+    storage: &dyn Storage,
+    last_processed_batch: u64,
+) -> StdResult<Vec<UnbondHistory>> {
+    let vec = convert(Some(last_processed_batch));
+
+    ReadonlyPrefixedStorage::new(storage, UNBOND_HISTORY_MAP)
+        .range(vec.as_deref(), None, Order::Ascending)
+        .map(|item| from_slice::<UnbondHistory>(&item.1))
+        .filter(|history| !matches!(history, Ok(h) if h.released))
+        .collect()
+}
+
 fn convert(start_after: Option<u64>) -> Option<Vec<u8>> {
     start_after.map(|idx| {
         let mut v = idx.to_be_bytes().to_vec();
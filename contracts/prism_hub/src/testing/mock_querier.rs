@@ -8,7 +8,7 @@ use cosmwasm_std::{
 use cosmwasm_storage::to_length_prefixed;
 use std::collections::HashMap;
 
-use cw20::{BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg};
+use cw20::{BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg, MinterResponse};
 use cw20_base::state::{MinterData, TokenInfo};
 
 pub const MOCK_CONTRACT_ADDR: &str = "cosmos2contract";
@@ -40,6 +40,7 @@ pub struct WasmMockQuerier {
     base: MockQuerier,
     token_querier: TokenQuerier,
     balance_querier: BalanceQuerier,
+    token_minters: HashMap<String, String>,
 }
 
 impl Querier for WasmMockQuerier {
@@ -73,6 +74,8 @@ impl WasmMockQuerier {
                         token_contract: Some(api.addr_canonicalize("token").unwrap()),
                         protocol_fee_collector: None,
                         rewards_contract: None,
+                        protocol_fee_collectors: vec![],
+                        reward_validator: None,
                     };
                     SystemResult::Ok(ContractResult::from(to_binary(
                         &to_binary(&config).unwrap(),
@@ -171,23 +174,13 @@ impl WasmMockQuerier {
             QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
                 match from_binary(msg).unwrap() {
                     Cw20QueryMsg::TokenInfo {} => {
-                        let balances: &HashMap<String, Uint128> =
-                            match self.token_querier.balances.get(contract_addr) {
-                                Some(balances) => balances,
-                                None => {
-                                    return SystemResult::Err(SystemError::InvalidRequest {
-                                        error: format!(
-                                            "No balance info exists for the contract {}",
-                                            contract_addr
-                                        ),
-                                        request: msg.as_slice().into(),
-                                    })
-                                }
-                            };
+                        // a token without balances has not minted anything yet
                         let mut total_supply = Uint128::zero();
 
-                        for balance in balances {
-                            total_supply += *balance.1;
+                        if let Some(balances) = self.token_querier.balances.get(contract_addr) {
+                            for balance in balances {
+                                total_supply += *balance.1;
+                            }
                         }
                         let _api: MockApi = MockApi::default();
                         let token_inf: TokenInfo = TokenInfo {
@@ -196,12 +189,19 @@ impl WasmMockQuerier {
                             decimals: 6,
                             total_supply,
                             mint: Some(MinterData {
-                                minter: Addr::unchecked("hub"),
+                                minter: Addr::unchecked(self.token_minter(contract_addr)),
                                 cap: None,
                             }),
                         };
                         SystemResult::Ok(ContractResult::Ok(to_binary(&token_inf).unwrap()))
                     }
+                    Cw20QueryMsg::Minter {} => {
+                        let minter = Some(MinterResponse {
+                            minter: self.token_minter(contract_addr),
+                            cap: None,
+                        });
+                        SystemResult::Ok(ContractResult::Ok(to_binary(&minter).unwrap()))
+                    }
                     Cw20QueryMsg::Balance { address } => {
                         let balances: &HashMap<String, Uint128> =
                             match self.token_querier.balances.get(contract_addr) {
@@ -239,6 +239,13 @@ impl WasmMockQuerier {
             _ => self.base.handle_query(request),
         }
     }
+    fn token_minter(&self, contract_addr: &str) -> String {
+        self.token_minters
+            .get(contract_addr)
+            .cloned()
+            .unwrap_or_else(|| MOCK_CONTRACT_ADDR.to_string())
+    }
+
     pub fn update_staking(
         &mut self,
         denom: &str,
@@ -308,6 +315,7 @@ impl WasmMockQuerier {
             base,
             token_querier: TokenQuerier::default(),
             balance_querier: BalanceQuerier::default(),
+            token_minters: HashMap::new(),
         }
     }
 
@@ -319,4 +327,10 @@ impl WasmMockQuerier {
     pub fn with_token_balances(&mut self, balances: &[(&String, &[(&String, &Uint128)])]) {
         self.token_querier = TokenQuerier::new(balances);
     }
+
+    // the hub is the minter of every mock token unless set otherwise
+    pub fn with_token_minter(&mut self, token_contract: &str, minter: &str) {
+        self.token_minters
+            .insert(token_contract.to_string(), minter.to_string());
+    }
 }
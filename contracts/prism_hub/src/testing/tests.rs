@@ -17,35 +17,59 @@
 //!      });
 //! 4. Anywhere you see query(deps.as_ref(), ...) you must replace it with query(&mut deps, ...)
 use cosmwasm_std::{
-    coin, from_binary, to_binary, Addr, Api, BankMsg, Coin, CosmosMsg, Decimal, DepsMut,
-    DistributionMsg, Env, FullDelegation, MessageInfo, OwnedDeps, Querier, Response, StakingMsg,
-    StdError, Storage, SubMsg, Uint128, Validator, WasmMsg,
+    attr, coin, coins, from_binary, to_binary, to_vec, Addr, Api, BankMsg, Coin, CosmosMsg,
+    Decimal, DepsMut, DistributionMsg, Env, FullDelegation, MessageInfo, OwnedDeps, Querier, Reply,
+    ReplyOn, Response, StakingMsg, StdError, StdResult, Storage, SubMsg, SubMsgResponse,
+    SubMsgResult, Uint128, Validator, WasmMsg,
 };
+use cosmwasm_storage::Bucket;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::testing::{
+    mock_dependencies_with_balance, mock_env, mock_info, MockApi, MockStorage,
+};
 
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, query, reply};
+use crate::error::HubError;
 use crate::unbond::execute_unbond;
 use basset::hub::QueryMsg;
 use basset::hub::{
-    AllHistoryResponse, ConfigResponse, CurrentBatchResponse, ExecuteMsg, InstantiateMsg,
-    Parameters, StateResponse, UnbondRequestsResponse, WhitelistedValidatorsResponse,
-    WithdrawableUnbondedResponse,
+    ActiveBatch, ActiveBatchesResponse, AllHistoryResponse, BatchExchangeRateResponse,
+    BatchMaturitiesResponse, BatchMaturity, BatchWaitListResponse, BondOrUnbond,
+    BondReconciliationResponse, CompoundHealthResponse, Config, ConfigResponse,
+    ContractVersionResponse, CurrentBatch, CurrentBatchResponse, DelegationCountResponse,
+    ExecuteMsg, IdleBalanceResponse, InstantiateMsg, KeeperConfigResponse,
+    LiquidityReserveResponse, OptionalParam, Parameters, PegFeePreviewResponse,
+    SimulateCompoundResponse, SimulateUndelegateResponse, SlashEvent, SlashHistoryResponse,
+    StakedValueResponse, State, StateResponse, StuckBatch, StuckBatchesResponse, UnbondHistory,
+    UnbondRequestsResponse, UnbondTimelineEntry, UnbondTimelineResponse, UserStatsResponse,
+    ValidatorInfoResponse, ValidatorStatus, WhitelistedValidatorsResponse,
+    WithdrawableAddressesResponse, WithdrawableUnbondedResponse,
 };
 
-use basset::hub::Cw20HookMsg::Unbond;
+use basset::hub::Cw20HookMsg::{InstantRedeem, Unbond};
 use basset::hub::ExecuteMsg::{CheckSlashing, Receive, UpdateAdmin, UpdateConfig, UpdateParams};
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
 
 use super::mock_querier::{mock_dependencies as dependencies, WasmMockQuerier};
 use crate::math::decimal_division;
-use crate::state::{read_unbond_wait_list, ADMIN, PAUSE};
+use crate::migration::{migrate_batch_wait_list, migrate_config, migrate_state, LegacyConfig};
+use crate::state::{
+    is_valid_validator, read_batch_wait_list, read_unbond_history, read_unbond_wait_list,
+    store_unbond_history, ACCRUED_FEE, ADMIN, BATCH_EXCHANGE_RATE, CONFIG, CURRENT_BATCH,
+    DEREGISTER_REDELEGATE_REPLY_ID, EMERGENCY_LOG, EMERGENCY_RECOVERY_ADDRESS,
+    INSTANTIATE_REGISTER_REPLY_ID, PAUSE, PENDING_DEREGISTER, PREFIX_WAIT_MAP, STATE,
+    WITHDRAW_CURSOR,
+};
+use crate::utility::selection_seed;
 use basset::hub::QueryMsg::{Admin, AllHistory, UnbondRequests, WithdrawableUnbonded};
 use basset::rewards::ExecuteMsg::ProcessRewards;
+use basset::rewards::{ExecuteMsg as RewardsExecuteMsg, InstantiateMsg as RewardsInstantiateMsg};
 use cw20::Cw20ExecuteMsg::{Burn, Mint};
 use cw_controllers::AdminResponse;
+use rand::{Rng, SeedableRng, XorShiftRng};
+use signed_integer::SignedInt;
 use std::borrow::BorrowMut;
 
 const DEFAULT_VALIDATOR: &str = "default-validator";
@@ -92,6 +116,7 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
         validator,
         protocol_fee: Default::default(),
         rewards_contract: "rewards_contract".to_string(),
+        start_paused: None,
     };
 
     let owner_info = mock_info(owner.as_str(), &[coin(1000000, "uluna")]);
@@ -100,6 +125,8 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     let register_msg = UpdateConfig {
         token_contract: Some(token_contract),
         protocol_fee_collector: None,
+        reward_validator: None,
+        rewards_contract: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), owner_info, register_msg).unwrap();
@@ -165,6 +192,7 @@ fn proper_initialization() {
         validator: validator.address.clone(),
         protocol_fee: Default::default(),
         rewards_contract: "rewards_contract".to_string(),
+        start_paused: None,
     };
 
     let _owner = "owner1";
@@ -172,33 +200,46 @@ fn proper_initialization() {
 
     // we can just call .unwrap() to assert this was a success
     let res: Response = instantiate(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
-    assert_eq!(3, res.messages.len());
+    assert_eq!(2, res.messages.len());
 
     let register_validator = ExecuteMsg::RegisterValidator {
         validator: validator.address.clone(),
     };
-    let reg_validator_msg = SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: mock_env().contract.address.to_string(),
-        msg: to_binary(&register_validator).unwrap(),
-        funds: vec![],
-    }));
+    let reg_validator_msg = SubMsg::reply_always(
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: mock_env().contract.address.to_string(),
+            msg: to_binary(&register_validator).unwrap(),
+            funds: vec![],
+        }),
+        INSTANTIATE_REGISTER_REPLY_ID,
+    );
 
     assert_eq!(&res.messages[0], &reg_validator_msg);
 
-    let delegate_msg = SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
-        validator: validator.address,
-        amount: coin(1000000, "uluna"),
-    }));
-
-    assert_eq!(&res.messages[1], &delegate_msg);
-
     let set_rewards_address = SubMsg::new(CosmosMsg::Distribution(
         DistributionMsg::SetWithdrawAddress {
             address: "rewards_contract".to_string(),
         },
     ));
 
-    assert_eq!(&res.messages[2], &set_rewards_address);
+    assert_eq!(&res.messages[1], &set_rewards_address);
+
+    // the initial bond is delegated once the validator is registered
+    let registered = Reply {
+        id: INSTANTIATE_REGISTER_REPLY_ID,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+        }),
+    };
+    let res = reply(deps.as_mut(), mock_env(), registered).unwrap();
+
+    let delegate_msg = SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+        validator: validator.address,
+        amount: coin(1000000, "uluna"),
+    }));
+
+    assert_eq!(res.messages, vec![delegate_msg]);
 
     // check parameters storage
     let params = QueryMsg::Parameters {};
@@ -223,6 +264,13 @@ fn proper_initialization() {
         actual_unbonded_amount: Default::default(),
         last_unbonded_time: mock_env().block.time.seconds(),
         last_processed_batch: 0u64,
+        open_batches_count: 0u64,
+        total_burned: Uint128::zero(),
+        total_rewards_compounded: Uint128::zero(),
+        cumulative_rewards_compounded: Uint128::zero(),
+        cumulative_protocol_fees: Uint128::zero(),
+        selection_nonce: 0u64,
+        liquidity_reserve: Uint128::zero(),
     };
     assert_eq!(query_state, expected_result);
 
@@ -234,6 +282,8 @@ fn proper_initialization() {
         token_contract: None,
         protocol_fee_collector: None,
         rewards_contract: Some("rewards_contract".to_string()),
+        protocol_fee_collectors: vec![],
+        reward_validator: None,
     };
 
     assert_eq!(expected_conf, query_conf);
@@ -252,7 +302,34 @@ fn proper_initialization() {
         query_batch,
         CurrentBatchResponse {
             id: 1,
-            requested_with_fee: Default::default()
+            requested_with_fee: Default::default(),
+            requests_count: 0,
+        }
+    );
+}
+
+/// Covers if the instantiation stores the contract version reported by the query.
+#[test]
+fn proper_contract_version() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address,
+    );
+
+    let res: ContractVersionResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::ContractVersion {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        res,
+        ContractVersionResponse {
+            contract: "crates.io:prism-hub".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
         }
     );
 }
@@ -286,10 +363,7 @@ fn proper_register_validator() {
 
     // invalid requests
     let res = execute(deps.as_mut(), mock_env(), owner_info, msg);
-    assert_eq!(
-        res.unwrap_err(),
-        StdError::generic_err("Caller is not admin")
-    );
+    assert_eq!(res.unwrap_err(), HubError::NotAdmin {});
 
     //invalid validator
 
@@ -301,7 +375,9 @@ fn proper_register_validator() {
     let res = execute(deps.as_mut(), mock_env(), owner_info, msg);
     assert_eq!(
         res.unwrap_err(),
-        StdError::generic_err("The specified address is not a validator")
+        HubError::Std(StdError::generic_err(
+            "The specified address is not a validator"
+        ))
     );
 
     // successful call
@@ -314,7 +390,11 @@ fn proper_register_validator() {
     let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
     assert_eq!(0, res.messages.len());
 
-    let query_validatator = QueryMsg::WhitelistedValidators {};
+    let query_validatator = QueryMsg::WhitelistedValidators {
+        start_after: None,
+        limit: None,
+        reverse: None,
+    };
     let query_res: WhitelistedValidatorsResponse =
         from_binary(&query(deps.as_ref(), mock_env(), query_validatator).unwrap()).unwrap();
     assert_eq!(query_res.validators.get(0).unwrap(), &validator.address);
@@ -328,13 +408,201 @@ fn proper_register_validator() {
     assert_eq!(0, res.messages.len());
 
     // check if the validator is sored;
-    let query_validatator2 = QueryMsg::WhitelistedValidators {};
+    let query_validatator2 = QueryMsg::WhitelistedValidators {
+        start_after: None,
+        limit: None,
+        reverse: None,
+    };
     let query_res: WhitelistedValidatorsResponse =
         from_binary(&query(deps.as_ref(), mock_env(), query_validatator2).unwrap()).unwrap();
     assert_eq!(query_res.validators.get(1).unwrap(), &validator2.address);
     assert_eq!(query_res.validators.get(0).unwrap(), &validator.address);
 }
 
+/// Covers if registrations are accepted up to max_validators and rejected beyond it.
+#[test]
+fn proper_register_validator_respect_max_validators() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    let validator3 = sample_validator(DEFAULT_VALIDATOR3.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+
+    let get_params = QueryMsg::Parameters {};
+    let params: Parameters =
+        from_binary(&query(deps.as_ref(), mock_env(), get_params).unwrap()).unwrap();
+    assert_eq!(params.max_validators, 100);
+
+    let update_params = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: Some(2),
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let owner_info = mock_info(owner.as_str(), &[]);
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_params).unwrap();
+
+    // register up to the cap
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_register_validator(deps.as_mut(), validator2);
+
+    // registering a whitelisted validator again does not count
+    do_register_validator(deps.as_mut(), validator);
+
+    let msg = ExecuteMsg::RegisterValidator {
+        validator: validator3.address,
+    };
+    let res = execute(deps.as_mut(), mock_env(), owner_info, msg).unwrap_err();
+    assert_eq!(
+        res,
+        HubError::Std(StdError::generic_err(
+            "Cannot whitelist more than 2 validators"
+        ))
+    );
+}
+
+/// Covers paging through the whitelisted validators in both directions.
+#[test]
+fn proper_whitelisted_validators_pagination() {
+    let mut deps = dependencies(&[]);
+
+    let validators: Vec<Validator> = (1..6)
+        .map(|i| sample_validator(format!("validator{}", i)))
+        .collect();
+    deps.querier.update_staking("uluna", &validators, &[]);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        deps.borrow_mut(),
+        owner,
+        token_contract,
+        validators[0].address.clone(),
+    );
+
+    for validator in validators.iter() {
+        do_register_validator(deps.as_mut(), validator.clone());
+    }
+
+    // first page
+    let first_page = QueryMsg::WhitelistedValidators {
+        start_after: None,
+        limit: Some(3),
+        reverse: None,
+    };
+    let query_res: WhitelistedValidatorsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), first_page).unwrap()).unwrap();
+    assert_eq!(
+        query_res.validators,
+        vec![
+            "validator1".to_string(),
+            "validator2".to_string(),
+            "validator3".to_string()
+        ]
+    );
+
+    // second page starts after the last returned validator
+    let second_page = QueryMsg::WhitelistedValidators {
+        start_after: query_res.validators.last().cloned(),
+        limit: Some(3),
+        reverse: None,
+    };
+    let query_res: WhitelistedValidatorsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), second_page).unwrap()).unwrap();
+    assert_eq!(
+        query_res.validators,
+        vec!["validator4".to_string(), "validator5".to_string()]
+    );
+
+    // reverse order is bounded from above by start_after
+    let reversed = QueryMsg::WhitelistedValidators {
+        start_after: Some("validator4".to_string()),
+        limit: Some(2),
+        reverse: Some(true),
+    };
+    let query_res: WhitelistedValidatorsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), reversed).unwrap()).unwrap();
+    assert_eq!(
+        query_res.validators,
+        vec!["validator3".to_string(), "validator2".to_string()]
+    );
+}
+
+/// Covers if the whitelisting time and the current delegation of a validator are returned.
+#[test]
+fn proper_validator_info() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        deps.borrow_mut(),
+        owner,
+        token_contract,
+        validator.address.clone(),
+    );
+
+    // not whitelisted yet
+    let validator_info = QueryMsg::ValidatorInfo {
+        validator: validator.address.clone(),
+    };
+    let res = query(deps.as_ref(), mock_env(), validator_info.clone());
+    assert_eq!(
+        res.unwrap_err(),
+        StdError::generic_err("The specified validator is not whitelisted")
+    );
+
+    do_register_validator(deps.as_mut(), validator.clone());
+    set_delegation(&mut deps.querier, validator, 10, "uluna");
+
+    let query_res: ValidatorInfoResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), validator_info).unwrap()).unwrap();
+    assert_eq!(
+        query_res,
+        ValidatorInfoResponse {
+            added_at: mock_env().block.time.seconds(),
+            current_delegation: Uint128::new(10),
+            validator_status: ValidatorStatus::Active,
+        }
+    );
+}
+
 /// Covers if delegate message is sent to the specified validator,
 /// mint message is sent to the token contract, state is changed based on new mint,
 /// and check unsuccessful calls, like unsupported validators, and invalid coin.
@@ -439,10 +707,7 @@ fn proper_bond() {
 
     let info = mock_info(&bob, &[coin(10, "uluna")]);
     let res = execute(deps.as_mut(), mock_env(), info, bond);
-    assert_eq!(
-        res.unwrap_err(),
-        StdError::generic_err("The chosen validator is currently not supported")
-    );
+    assert_eq!(res.unwrap_err(), HubError::ValidatorNotWhitelisted {});
 
     // no-send funds
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
@@ -455,7 +720,9 @@ fn proper_bond() {
     let res = execute(deps.as_mut(), mock_env(), info, failed_bond);
     assert_eq!(
         res.unwrap_err(),
-        StdError::generic_err("No uluna assets are provided to bond")
+        HubError::NoFundsToBond {
+            denom: "uluna".to_string(),
+        }
     );
 
     //send other tokens than luna funds
@@ -469,7 +736,9 @@ fn proper_bond() {
     let res = execute(deps.as_mut(), mock_env(), info, failed_bond.clone());
     assert_eq!(
         res.unwrap_err(),
-        StdError::generic_err("No uluna assets are provided to bond")
+        HubError::NoFundsToBond {
+            denom: "uluna".to_string(),
+        }
     );
 
     //bond with more than one coin is not possible
@@ -482,9 +751,93 @@ fn proper_bond() {
     );
 
     let res = execute(deps.as_mut(), mock_env(), info, failed_bond).unwrap_err();
+    assert_eq!(res, HubError::MultipleCoins {});
+}
+
+/// Covers if a bond on behalf of another address issues the token for the recipient
+/// and reverts when less than min_mint would be issued.
+#[test]
+fn proper_bond_for() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let relayer = "relayer".to_string();
+    let recipient = "recipient".to_string();
+    let bond_amount = Uint128::new(10000);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        deps.borrow_mut(),
+        owner,
+        token_contract.clone(),
+        validator.address.clone(),
+    );
+
+    set_delegation(
+        &mut deps.querier,
+        validator.clone(),
+        INITIAL_DEPOSIT_AMOUNT.u128(),
+        "uluna",
+    );
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(
+            &mock_env().contract.address.to_string(),
+            &INITIAL_DEPOSIT_AMOUNT,
+        )],
+    )]);
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    // the bond reverts below min_mint
+    let bond_msg = ExecuteMsg::BondFor {
+        validator: validator.address.clone(),
+        recipient: recipient.clone(),
+        min_mint: Some(bond_amount + Uint128::new(1)),
+    };
+    let info = mock_info(&relayer, &[coin(bond_amount.u128(), "uluna")]);
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg).unwrap_err();
     assert_eq!(
         res,
-        StdError::generic_err("More than one coin is sent; only one asset is supported")
+        HubError::MintBelowMinimum {
+            minted: Uint128::new(10000),
+            min_mint: Uint128::new(10001),
+        }
+    );
+
+    let bond_msg = ExecuteMsg::BondFor {
+        validator: validator.address.clone(),
+        recipient: recipient.clone(),
+        min_mint: Some(bond_amount),
+    };
+    let res = execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
+    assert_eq!(2, res.messages.len());
+
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: validator.address,
+            amount: coin(bond_amount.u128(), "uluna"),
+        }))
+    );
+
+    // the recipient receives the minted token, the relayer does not
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token_contract,
+            msg: to_binary(&Mint {
+                recipient,
+                amount: bond_amount,
+            })
+            .unwrap(),
+            funds: vec![],
+        }))
     );
 }
 
@@ -518,6 +871,7 @@ fn proper_deregister() {
     //must be able to deregister while there is no delegation
     let msg = ExecuteMsg::DeregisterValidator {
         validator: validator.address.clone(),
+        replacement: None,
     };
 
     let owner_info = mock_info(owner.as_str(), &[]);
@@ -537,17 +891,16 @@ fn proper_deregister() {
     // check invalid sender
     let msg = ExecuteMsg::DeregisterValidator {
         validator: validator.address.clone(),
+        replacement: None,
     };
 
     let invalid_info = mock_info("invalid", &[]);
     let res = execute(deps.as_mut(), mock_env(), invalid_info, msg);
-    assert_eq!(
-        res.unwrap_err(),
-        StdError::generic_err("Caller is not admin")
-    );
+    assert_eq!(res.unwrap_err(), HubError::NotAdmin {});
 
     let msg = ExecuteMsg::DeregisterValidator {
         validator: validator.address.clone(),
+        replacement: None,
     };
 
     let owner_info = mock_info(owner.as_str(), &[]);
@@ -581,7 +934,11 @@ fn proper_deregister() {
         _ => panic!("Unexpected message: {:?}", redelegate_msg),
     }
 
-    let query_validator = QueryMsg::WhitelistedValidators {};
+    let query_validator = QueryMsg::WhitelistedValidators {
+        start_after: None,
+        limit: None,
+        reverse: None,
+    };
     let query_res: WhitelistedValidatorsResponse =
         from_binary(&query(deps.as_ref(), mock_env(), query_validator).unwrap()).unwrap();
     assert_eq!(query_res.validators.get(0).unwrap(), &validator2.address);
@@ -590,32 +947,34 @@ fn proper_deregister() {
     // fails if there is only one validator
     let msg = ExecuteMsg::DeregisterValidator {
         validator: validator2.address,
+        replacement: None,
     };
 
     let owner_info = mock_info(owner.as_str(), &[]);
     let res = execute(deps.as_mut(), mock_env(), owner_info, msg).unwrap_err();
     assert_eq!(
         res,
-        StdError::generic_err("Cannot remove the last whitelisted validator")
+        HubError::Std(StdError::generic_err(
+            "Cannot remove the last whitelisted validator"
+        ))
     );
 }
 
-/// Covers if Withdraw message, swap message, and update global index are sent.
+/// Covers if a deregistered validator is whitelisted again, as the reward validator
+/// it was, when its redelegate fails, and is dropped when it succeeds.
 #[test]
-pub fn proper_update_global_index() {
+fn proper_deregister_redelegate_failure() {
     let mut deps = dependencies(&[]);
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
     set_validator_mock(&mut deps.querier);
 
-    let addr1 = "addr1000".to_string();
-    let bond_amount = Uint128::new(10);
-
     let owner = "owner1".to_string();
     let token_contract = "token".to_string();
 
     init(
-        deps.borrow_mut(),
-        owner,
+        &mut deps,
+        owner.clone(),
         token_contract,
         validator.address.clone(),
     );
@@ -623,27 +982,278 @@ pub fn proper_update_global_index() {
     // register_validator
     do_register_validator(deps.as_mut(), validator.clone());
 
-    // fails if there is no delegation
-    let reward_msg = ExecuteMsg::UpdateGlobalIndex {};
-
-    let info = mock_info(&addr1, &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, reward_msg).unwrap();
-    assert_eq!(res.messages.len(), 1);
-    assert_eq!(
-        res.messages[0],
-        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: "rewards_contract".to_string(),
-            msg: to_binary(&ProcessRewards {}).unwrap(),
-            funds: vec![],
-        }))
-    );
+    // register_validator2
+    do_register_validator(deps.as_mut(), validator2.clone());
 
-    // bond
-    do_bond(deps.as_mut(), addr1.clone(), bond_amount, validator.clone());
+    set_delegation(&mut deps.querier, validator.clone(), 10, "uluna");
 
-    //set delegation for query-all-delegation
-    let delegations: [FullDelegation; 1] =
-        [(sample_delegation(validator.address.clone(), coin(bond_amount.u128(), "uluna")))];
+    let owner_info = mock_info(owner.as_str(), &[]);
+    let update_config = UpdateConfig {
+        token_contract: None,
+        protocol_fee_collector: None,
+        reward_validator: Some(validator.address.clone()),
+        rewards_contract: None,
+    };
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_config).unwrap();
+
+    let msg = ExecuteMsg::DeregisterValidator {
+        validator: validator.address.clone(),
+        replacement: None,
+    };
+
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), msg.clone()).unwrap();
+    assert_eq!(2, res.messages.len());
+    assert_eq!(res.messages[0].id, DEREGISTER_REDELEGATE_REPLY_ID);
+    assert_eq!(res.messages[0].reply_on, ReplyOn::Always);
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.reward_validator, None);
+
+    let query_validator = QueryMsg::WhitelistedValidators {
+        start_after: None,
+        limit: None,
+        reverse: None,
+    };
+    let query_res: WhitelistedValidatorsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), query_validator.clone()).unwrap()).unwrap();
+    assert!(!query_res.validators.contains(&validator.address));
+
+    // the redelegate fails
+    let failure = Reply {
+        id: DEREGISTER_REDELEGATE_REPLY_ID,
+        result: SubMsgResult::Err("too many redelegations".to_string()),
+    };
+    let res = reply(deps.as_mut(), mock_env(), failure).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let query_res: WhitelistedValidatorsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), query_validator.clone()).unwrap()).unwrap();
+    assert!(query_res.validators.contains(&validator.address));
+    assert!(query_res.validators.contains(&validator2.address));
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.reward_validator, Some(validator.address.clone()));
+    assert!(PENDING_DEREGISTER
+        .may_load(&deps.storage)
+        .unwrap()
+        .is_none());
+
+    // the pending record is dropped once the redelegate succeeds
+    execute(deps.as_mut(), mock_env(), owner_info, msg).unwrap();
+    let success = Reply {
+        id: DEREGISTER_REDELEGATE_REPLY_ID,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+        }),
+    };
+    reply(deps.as_mut(), mock_env(), success).unwrap();
+    assert!(PENDING_DEREGISTER
+        .may_load(&deps.storage)
+        .unwrap()
+        .is_none());
+
+    let query_res: WhitelistedValidatorsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), query_validator).unwrap()).unwrap();
+    assert_eq!(query_res.validators, vec![validator2.address]);
+}
+
+/// Covers if a validator without delegation can be deregistered
+/// while a replacement is whitelisted in the same call.
+#[test]
+fn proper_deregister_with_replacement() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    let validator3 = sample_validator(DEFAULT_VALIDATOR3.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    // the replacement must be a validator
+    let msg = ExecuteMsg::DeregisterValidator {
+        validator: validator.address.clone(),
+        replacement: Some("invalid".to_string()),
+    };
+    let owner_info = mock_info(owner.as_str(), &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap_err();
+    assert_eq!(
+        res,
+        HubError::Std(StdError::generic_err(
+            "The specified address is not a validator"
+        ))
+    );
+
+    // the last validator can be removed when it is replaced
+    let msg = ExecuteMsg::DeregisterValidator {
+        validator: validator.address.clone(),
+        replacement: Some(validator2.address.clone()),
+    };
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let query_validator = QueryMsg::WhitelistedValidators {
+        start_after: None,
+        limit: None,
+        reverse: None,
+    };
+    let query_res: WhitelistedValidatorsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), query_validator.clone()).unwrap()).unwrap();
+    assert_eq!(query_res.validators, vec![validator2.address.clone()]);
+
+    // the set size stays the same
+    do_register_validator(deps.as_mut(), validator.clone());
+    let msg = ExecuteMsg::DeregisterValidator {
+        validator: validator.address,
+        replacement: Some(validator3.address.clone()),
+    };
+    let res = execute(deps.as_mut(), mock_env(), owner_info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let query_res: WhitelistedValidatorsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), query_validator).unwrap()).unwrap();
+    assert_eq!(
+        query_res.validators,
+        vec![validator2.address, validator3.address]
+    );
+}
+
+/// Covers if the owner can move a part of the pooled delegation
+/// to another whitelisted validator.
+#[test]
+fn proper_rebond() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let delegations: [FullDelegation; 1] =
+        [(sample_delegation(validator.address.clone(), coin(100, "uluna")))];
+    let validators: [Validator; 2] = [(validator.clone()), (validator2.clone())];
+    set_delegation_query(&mut deps.querier, &delegations, &validators);
+
+    // check invalid sender
+    let msg = ExecuteMsg::Rebond {
+        from: validator.address.clone(),
+        to: validator2.address.clone(),
+        amount: Uint128::new(40),
+    };
+    let invalid_info = mock_info("invalid", &[]);
+    let res = execute(deps.as_mut(), mock_env(), invalid_info, msg.clone());
+    assert_eq!(res.unwrap_err(), HubError::NotAdmin {});
+
+    // the destination must be whitelisted
+    let owner_info = mock_info(owner.as_str(), &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), msg.clone());
+    assert_eq!(
+        res.unwrap_err(),
+        HubError::Std(StdError::generic_err(
+            "The destination validator is not whitelisted"
+        ))
+    );
+
+    do_register_validator(deps.as_mut(), validator2.clone());
+
+    // can not move more than the delegation
+    let over_msg = ExecuteMsg::Rebond {
+        from: validator.address.clone(),
+        to: validator2.address.clone(),
+        amount: Uint128::new(101),
+    };
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), over_msg);
+    assert_eq!(
+        res.unwrap_err(),
+        HubError::Std(StdError::generic_err(
+            "Can only rebond up to 100 from the source validator"
+        ))
+    );
+
+    let res = execute(deps.as_mut(), mock_env(), owner_info, msg).unwrap();
+    assert_eq!(2, res.messages.len());
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Staking(StakingMsg::Redelegate {
+            src_validator: validator.address,
+            dst_validator: validator2.address,
+            amount: coin(40, "uluna"),
+        }))
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+            msg: to_binary(&ExecuteMsg::UpdateGlobalIndex {}).unwrap(),
+            funds: vec![],
+        }))
+    );
+}
+
+/// Covers if Withdraw message, swap message, and update global index are sent.
+#[test]
+pub fn proper_update_global_index() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let addr1 = "addr1000".to_string();
+    let bond_amount = Uint128::new(10);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        deps.borrow_mut(),
+        owner,
+        token_contract,
+        validator.address.clone(),
+    );
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    // fails if there is no delegation
+    let reward_msg = ExecuteMsg::UpdateGlobalIndex {};
+
+    let info = mock_info(&addr1, &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, reward_msg).unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "rewards_contract".to_string(),
+            msg: to_binary(&ProcessRewards {}).unwrap(),
+            funds: vec![],
+        }))
+    );
+
+    // bond
+    do_bond(deps.as_mut(), addr1.clone(), bond_amount, validator.clone());
+
+    //set delegation for query-all-delegation
+    let delegations: [FullDelegation; 1] =
+        [(sample_delegation(validator.address.clone(), coin(bond_amount.u128(), "uluna")))];
 
     let validators: [Validator; 1] = [(validator.clone())];
 
@@ -759,7 +1369,7 @@ pub fn proper_update_exchange_rate() {
     let info = mock_info(&addr1, &[]);
     let res = execute(deps.as_mut(), mock_env(), info, update_exchange_rate).unwrap_err();
 
-    assert_eq!(res, StdError::generic_err("Unauthorized"));
+    assert_eq!(res, HubError::Unauthorized {});
 
     let new_balance = Uint128::new(1100);
     deps.querier.with_native_balances(&[(
@@ -1038,7 +1648,7 @@ pub fn proper_receive() {
 
     let invalid_info = mock_info(&invalid, &[]);
     let res = execute(deps.as_mut(), mock_env(), invalid_info, receive);
-    assert_eq!(res.unwrap_err(), StdError::generic_err("unauthorized"));
+    assert_eq!(res.unwrap_err(), HubError::Unauthorized {});
 
     // successful call
     let successful_unbond = Unbond {};
@@ -1280,26 +1890,16 @@ pub fn proper_unbond() {
     assert_eq!(res.history[0].batch_id, 1);
 }
 
-/// Covers if the pick_validator function sends different Undelegate messages
-/// to different validators, when a validator does not have enough delegation.
+/// Covers if the whole position can be unbonded through the cw20 hook,
+/// whose tokens have already left the sender when the hub is called.
 #[test]
-pub fn proper_pick_validator() {
+pub fn proper_unbond_full_balance() {
     let mut deps = dependencies(&[]);
-
-    let addr1 = "addr1000".to_string();
-    let addr2 = "addr2000".to_string();
-    let addr3 = "addr3000".to_string();
-
-    // create 3 validators
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
-    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
-    let validator3 = sample_validator(DEFAULT_VALIDATOR3.to_string());
-
     set_validator_mock(&mut deps.querier);
 
     let owner = "owner1".to_string();
     let token_contract = "token".to_string();
-
     init(
         deps.borrow_mut(),
         owner,
@@ -1307,167 +1907,62 @@ pub fn proper_pick_validator() {
         validator.address.clone(),
     );
 
+    // register_validator
     do_register_validator(deps.as_mut(), validator.clone());
-    do_register_validator(deps.as_mut(), validator2.clone());
-    do_register_validator(deps.as_mut(), validator3.clone());
 
-    // bond to a validator
+    let bob = "bob".to_string();
     do_bond(
         deps.as_mut(),
-        addr1.clone(),
+        bob.clone(),
         Uint128::new(10),
         validator.clone(),
     );
-    do_bond(
-        deps.as_mut(),
-        addr2.clone(),
-        Uint128::new(300),
-        validator2.clone(),
-    );
-    do_bond(
-        deps.as_mut(),
-        addr3.clone(),
-        Uint128::new(200),
-        validator3.clone(),
-    );
-
-    // give validators different delegation amount
-    let delegations: [FullDelegation; 3] = [
-        (sample_delegation(validator.address.clone(), coin(10, "uluna"))),
-        (sample_delegation(validator2.address.clone(), coin(300, "uluna"))),
-        (sample_delegation(validator3.address.clone(), coin(200, "uluna"))),
-    ];
+    set_delegation(&mut deps.querier, validator, 10, "uluna");
 
-    let validators: [Validator; 3] = [
-        (validator.clone()),
-        (validator2.clone()),
-        (validator3.clone()),
-    ];
-    set_delegation_query(&mut deps.querier, &delegations, &validators);
+    // Send has moved bob's whole balance to the hub
     deps.querier.with_token_balances(&[(
         &"token".to_string(),
         &[
-            (&addr3, &Uint128::new(200)),
-            (&addr2, &Uint128::new(300)),
-            (&addr1, &Uint128::new(10)),
+            (&bob, &Uint128::zero()),
+            (&MOCK_CONTRACT_ADDR.to_string(), &Uint128::new(10u128)),
         ],
     )]);
 
-    // send the first burn
-    let token_info = mock_info(&token_contract, &[]);
-    let mut token_env = mock_env();
-
-    let res = do_unbond(
+    let receive = Receive(Cw20ReceiveMsg {
+        sender: bob.clone(),
+        amount: Uint128::new(10),
+        msg: to_binary(&Unbond {}).unwrap(),
+    });
+    let res = execute(
         deps.as_mut(),
-        addr2.clone(),
-        token_env.clone(),
-        token_info.clone(),
-        Uint128::new(50),
-    );
-    assert_eq!(res.messages.len(), 1);
-
-    deps.querier.with_token_balances(&[(
-        &"token".to_string(),
-        &[
-            (&addr3, &Uint128::new(200)),
-            (&addr2, &Uint128::new(250)),
-            (&addr1, &Uint128::new(10)),
-        ],
-    )]);
+        mock_env(),
+        mock_info(&token_contract, &[]),
+        receive,
+    )
+    .unwrap();
+    assert_eq!(1, res.messages.len());
 
-    token_env.block.time = token_env.block.time.plus_seconds(40);
+    let requests: UnbondRequestsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::UnbondRequests { address: bob },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(requests.requests, vec![(1, Uint128::new(10))]);
+}
 
-    // send the second burn
-    let res = do_unbond(
-        deps.as_mut(),
-        addr2.clone(),
-        token_env,
-        token_info,
-        Uint128::new(100),
-    );
-    assert!(res.messages.len() >= 2);
-
-    deps.querier.with_token_balances(&[(
-        &"token".to_string(),
-        &[
-            (&addr3, &Uint128::new(200)),
-            (&addr2, &Uint128::new(150)),
-            (&addr1, &Uint128::new(10)),
-        ],
-    )]);
-
-    //check if the undelegate message is send two more than one validator.
-    if res.messages.len() > 2 {
-        match &res.messages[0].msg {
-            CosmosMsg::Staking(StakingMsg::Undelegate {
-                validator: val,
-                amount,
-            }) => {
-                if val == &validator.address {
-                    assert_eq!(amount.amount, Uint128::new(10))
-                }
-                if val == &validator2.address {
-                    assert_eq!(amount.amount, Uint128::new(150))
-                }
-                if val == &validator3.address {
-                    assert_eq!(amount.amount, Uint128::new(150))
-                }
-            }
-            _ => panic!("Unexpected message: {:?}", &res.messages[1]),
-        }
-
-        match &res.messages[1].msg {
-            CosmosMsg::Staking(StakingMsg::Undelegate {
-                validator: val,
-                amount,
-            }) => {
-                if val == &validator2.address {
-                    assert_eq!(amount.amount, Uint128::new(140))
-                }
-                if val == &validator3.address {
-                    assert_eq!(amount.amount, Uint128::new(140))
-                }
-            }
-            _ => panic!("Unexpected message: {:?}", &res.messages[2]),
-        }
-    } else {
-        match &res.messages[1].msg {
-            CosmosMsg::Staking(StakingMsg::Undelegate {
-                validator: val,
-                amount,
-            }) => {
-                if val == &validator2.address {
-                    assert_eq!(amount.amount, Uint128::new(150))
-                }
-                if val == &validator3.address {
-                    assert_eq!(amount.amount, Uint128::new(150))
-                }
-            }
-            _ => panic!("Unexpected message: {:?}", &res.messages[1]),
-        }
-    }
-}
-
-/// Covers if the pick_validator function sends different Undelegate messages
-/// if the delegations of the user are distributed to several validators
-/// and if the user wants to unbond amount that none of validators has.
+/// Covers if the burned amount is accumulated across unbond requests.
 #[test]
-pub fn proper_pick_validator_respect_distributed_delegation() {
+pub fn proper_total_burned() {
     let mut deps = dependencies(&[]);
-
-    let addr1 = "addr1000".to_string();
-    let addr2 = "addr2000".to_string();
-
-    // create 3 validators
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
-    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
-    let validator3 = sample_validator(DEFAULT_VALIDATOR3.to_string());
-
     set_validator_mock(&mut deps.querier);
 
     let owner = "owner1".to_string();
     let token_contract = "token".to_string();
-
     init(
         deps.borrow_mut(),
         owner,
@@ -1475,1496 +1970,8350 @@ pub fn proper_pick_validator_respect_distributed_delegation() {
         validator.address.clone(),
     );
 
+    // register_validator
     do_register_validator(deps.as_mut(), validator.clone());
-    do_register_validator(deps.as_mut(), validator2.clone());
-    do_register_validator(deps.as_mut(), validator3);
 
-    // bond to a validator
+    let bob = "bob".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+
     do_bond(
         deps.as_mut(),
-        addr1.clone(),
-        Uint128::new(1000),
+        bob.clone(),
+        Uint128::new(100),
         validator.clone(),
     );
-    do_bond(
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
+
+    let token_info = mock_info(&token_contract, &[]);
+    do_unbond(
         deps.as_mut(),
-        addr1.clone(),
-        Uint128::new(1500),
-        validator2.clone(),
+        bob.clone(),
+        mock_env(),
+        token_info.clone(),
+        Uint128::new(10),
     );
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
 
-    // give validators different delegation amount
-    let delegations: [FullDelegation; 2] = [
-        (sample_delegation(validator.address.clone(), coin(1000, "uluna"))),
-        (sample_delegation(validator2.address.clone(), coin(1500, "uluna"))),
-    ];
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_burned, Uint128::new(10));
 
-    let validators: [Validator; 2] = [(validator.clone()), (validator2.clone())];
-    set_delegation_query(&mut deps.querier, &delegations, &validators);
+    do_unbond(deps.as_mut(), bob, mock_env(), token_info, Uint128::new(5));
 
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&addr1, &Uint128::new(2500))])]);
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_burned, Uint128::new(15));
+}
 
-    // send the first burn
-    let token_info = mock_info(&token_contract, &[]);
+/// Covers if the migration builds the config from the legacy config once and
+/// keeps the reward validator and fee collectors of a config that is already migrated.
+#[test]
+pub fn proper_migrate_config() {
+    let mut deps = dependencies(&[]);
 
-    let mut token_env = mock_env();
-    token_env.block.time = token_env.block.time.plus_seconds(40);
+    let token = deps.api.addr_canonicalize("token").unwrap();
+    let collector = deps.api.addr_canonicalize("collector").unwrap();
+    let rewards = deps.api.addr_canonicalize("rewards").unwrap();
+    let legacy_config = LegacyConfig {
+        token_contract: Some(token.clone()),
+        protocol_fee_collector: Some(collector.clone()),
+    };
+    deps.storage
+        .set(b"\x00\x06config", &to_vec(&legacy_config).unwrap());
 
-    let res = do_unbond(
-        deps.as_mut(),
-        addr2,
-        token_env,
-        token_info,
-        Uint128::new(2000),
-    );
-    assert_eq!(res.messages.len(), 3);
+    migrate_config(deps.as_mut().storage, Some(rewards.clone())).unwrap();
 
-    //check if the undelegate message is send two more than one validator.
-    if res.messages.len() > 2 {
-        match &res.messages[0].msg {
-            CosmosMsg::Staking(StakingMsg::Undelegate {
-                validator: val,
-                amount,
-            }) => {
-                if val == &validator.address {
-                    assert_eq!(amount.amount, Uint128::new(1000))
-                }
-                if val == &validator2.address {
-                    assert_eq!(amount.amount, Uint128::new(1500))
-                }
-            }
-            _ => panic!("Unexpected message: {:?}", &res.messages[1]),
-        }
+    let config = CONFIG.load(&deps.storage).unwrap();
+    assert_eq!(config.token_contract, Some(token));
+    assert_eq!(config.protocol_fee_collector, Some(collector));
+    assert_eq!(config.rewards_contract, Some(rewards.clone()));
+    assert_eq!(config.reward_validator, None);
 
-        match &res.messages[1].msg {
-            CosmosMsg::Staking(StakingMsg::Undelegate {
-                validator: val,
-                amount,
-            }) => {
-                if val == &validator.address {
-                    assert_eq!(amount.amount, Uint128::new(500))
-                }
-                if val == &validator2.address {
-                    assert_eq!(amount.amount, Uint128::new(1000))
-                }
-            }
-            _ => panic!("Unexpected message: {:?}", &res.messages[2]),
-        }
+    assert_eq!(config.protocol_fee_collectors, vec![]);
+
+    // a second migration keeps the configured reward validator and fee split
+    let split = vec![
+        (
+            deps.api.addr_canonicalize("collector1").unwrap(),
+            Decimal::percent(70),
+        ),
+        (
+            deps.api.addr_canonicalize("collector2").unwrap(),
+            Decimal::percent(30),
+        ),
+    ];
+    CONFIG
+        .update(deps.as_mut().storage, |mut config| -> StdResult<Config> {
+            config.reward_validator = Some(DEFAULT_VALIDATOR.to_string());
+            config.protocol_fee_collectors = split.clone();
+            Ok(config)
+        })
+        .unwrap();
+    migrate_config(deps.as_mut().storage, Some(rewards)).unwrap();
+
+    let config = CONFIG.load(&deps.storage).unwrap();
+    assert_eq!(config.reward_validator, Some(DEFAULT_VALIDATOR.to_string()));
+    assert_eq!(config.protocol_fee_collectors, split);
+}
+
+/// Covers if the migration backfills the burned total and open batch count from the
+/// stored histories on a state without them, and keeps them on a state that already has them.
+#[test]
+pub fn proper_migrate_state() {
+    let mut deps = dependencies(&[]);
+
+    // a state stored before the counters were added
+    let legacy_state = br#"{"exchange_rate":"1","second_exchange_rate":"1","total_bond_amount":"100","last_index_modification":0,"prev_hub_balance":"0","actual_unbonded_amount":"0","principle_balance_before_exchange_update":"0","last_unbonded_time":0,"last_processed_batch":0}"#;
+    deps.storage.set(b"\x00\x05state", legacy_state);
+
+    // one released batch, one batch still unbonding and the current batch
+    for (batch_id, released) in [(1u64, true), (2, false)] {
+        store_unbond_history(
+            &mut deps.storage,
+            batch_id,
+            UnbondHistory {
+                batch_id,
+                time: 0,
+                amount: Uint128::new(batch_id as u128 * 10),
+                applied_exchange_rate: Decimal::one(),
+                withdraw_rate: Decimal::one(),
+                released,
+            },
+        )
+        .unwrap();
     }
+    CURRENT_BATCH
+        .save(
+            &mut deps.storage,
+            &CurrentBatch {
+                id: 3,
+                requested_with_fee: Uint128::new(5),
+                requests_count: 1,
+            },
+        )
+        .unwrap();
+
+    migrate_state(deps.as_mut().storage).unwrap();
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_bond_amount, Uint128::new(100));
+    assert_eq!(state.total_burned, Uint128::new(35));
+    assert_eq!(state.total_rewards_compounded, Uint128::zero());
+    assert_eq!(state.open_batches_count, 1);
+
+    // a second migration keeps the counted values
+    STATE
+        .update(deps.as_mut().storage, |mut state| -> StdResult<State> {
+            state.total_burned = Uint128::new(10);
+            state.total_rewards_compounded = Uint128::new(20);
+            Ok(state)
+        })
+        .unwrap();
+    migrate_state(deps.as_mut().storage).unwrap();
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_burned, Uint128::new(10));
+    assert_eq!(state.total_rewards_compounded, Uint128::new(20));
 }
 
-/// Covers the effect of slashing of bond, unbond, and withdraw_unbonded
-/// update the exchange rate after and before slashing.
+/// Covers if the migration indexes the unbond requests stored before the batch index
 #[test]
-pub fn proper_slashing() {
+pub fn proper_migrate_batch_wait_list() {
+    let mut deps = dependencies(&[]);
+
+    // requests stored in the wait map only
+    for (addr, batch_id, amount) in [("bob", 1u64, 20u128), ("alice", 1, 10), ("alice", 2, 5)] {
+        let mut wait_map: Bucket<Uint128> = Bucket::multilevel(
+            deps.as_mut().storage,
+            &[PREFIX_WAIT_MAP, &to_vec(&addr.to_string()).unwrap()],
+        );
+        wait_map
+            .save(&to_vec(&batch_id).unwrap(), &Uint128::new(amount))
+            .unwrap();
+    }
+    assert!(read_batch_wait_list(&deps.storage, 1, None, None)
+        .unwrap()
+        .is_empty());
+
+    migrate_batch_wait_list(deps.as_mut().storage).unwrap();
+
+    assert_eq!(
+        read_batch_wait_list(&deps.storage, 1, None, None).unwrap(),
+        vec![
+            ("alice".to_string(), Uint128::new(10)),
+            ("bob".to_string(), Uint128::new(20)),
+        ]
+    );
+    assert_eq!(
+        read_batch_wait_list(&deps.storage, 2, None, None).unwrap(),
+        vec![("alice".to_string(), Uint128::new(5))]
+    );
+}
+
+/// Covers if the current batch is undelegated before the epoch period
+/// has passed once it reaches max_requests_per_batch.
+#[test]
+pub fn proper_max_requests_per_batch() {
     let mut deps = dependencies(&[]);
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
     set_validator_mock(&mut deps.querier);
 
-    let addr1 = "addr1000".to_string();
-
     let owner = "owner1".to_string();
     let token_contract = "token".to_string();
     init(
-        &mut deps,
+        deps.borrow_mut(),
         owner,
         token_contract.clone(),
         validator.address.clone(),
     );
 
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: Some(2),
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let creator_info = mock_info("owner1", &[]);
+    execute(deps.as_mut(), mock_env(), creator_info, update_prams).unwrap();
+
     // register_validator
     do_register_validator(deps.as_mut(), validator.clone());
 
-    //bond
+    let bob = "bob".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+
     do_bond(
         deps.as_mut(),
-        addr1.clone(),
-        Uint128::new(1000),
+        bob.clone(),
+        Uint128::new(100),
         validator.clone(),
     );
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
 
-    //this will set the balance of the user in token contract
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&addr1, &Uint128::new(1000u128))])]);
+    // the first request only joins the batch
+    let token_info = mock_info(&token_contract, &[]);
+    let res = do_unbond(
+        deps.as_mut(),
+        bob.clone(),
+        mock_env(),
+        token_info.clone(),
+        Uint128::new(10),
+    );
+    assert_eq!(res.messages.len(), 1);
 
-    // slashing
-    set_delegation(&mut deps.querier, validator.clone(), 900, "uluna");
+    let current_batch: CurrentBatchResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::CurrentBatch {}).unwrap()).unwrap();
+    assert_eq!(current_batch.id, 1);
+    assert_eq!(current_batch.requests_count, 1);
 
-    let info = mock_info(&addr1, &[]);
-    let report_slashing = CheckSlashing {};
-    let res = execute(deps.as_mut(), mock_env(), info, report_slashing).unwrap();
-    assert_eq!(0, res.messages.len());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
 
-    let ex_rate = QueryMsg::State {};
-    let query_exchange_rate: StateResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), ex_rate).unwrap()).unwrap();
-    assert_eq!(query_exchange_rate.exchange_rate.to_string(), "0.9");
+    // the second request fills the batch, so it is undelegated within the same epoch
+    let res = do_unbond(deps.as_mut(), bob, mock_env(), token_info, Uint128::new(10));
+    assert_eq!(res.messages.len(), 2);
 
-    //bond again to see the update exchange rate
-    let second_bond = ExecuteMsg::Bond {
-        validator: validator.address.clone(),
-    };
+    let current_batch: CurrentBatchResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::CurrentBatch {}).unwrap()).unwrap();
+    assert_eq!(current_batch.id, 2);
+    assert_eq!(current_batch.requests_count, 0);
+    assert_eq!(current_batch.requested_with_fee, Uint128::zero());
+}
 
-    let info = mock_info(&addr1, &[coin(1000, "uluna")]);
+/// Covers if the owner can undelegate the current batch before the epoch period has passed.
+#[test]
+pub fn proper_force_batch_rollover() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
 
-    let res = execute(deps.as_mut(), mock_env(), info.clone(), second_bond).unwrap();
-    assert_eq!(2, res.messages.len());
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    init(
+        deps.borrow_mut(),
+        owner,
+        token_contract.clone(),
+        validator.address.clone(),
+    );
 
-    // expected exchange rate must be more than 0.9
-    let expected_er = Decimal::from_ratio(Uint128::new(1900), Uint128::new(2111));
-    let ex_rate = QueryMsg::State {};
-    let query_exchange_rate: StateResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), ex_rate).unwrap()).unwrap();
-    assert_eq!(query_exchange_rate.exchange_rate, expected_er);
+    do_register_validator(deps.as_mut(), validator.clone());
 
-    let delegate = &res.messages[0].msg;
-    match delegate {
-        CosmosMsg::Staking(StakingMsg::Delegate { validator, amount }) => {
-            assert_eq!(validator.as_str(), DEFAULT_VALIDATOR.to_string());
-            assert_eq!(amount, &coin(1000, "uluna"));
-        }
-        _ => panic!("Unexpected message: {:?}", delegate),
-    }
+    let bob = "bob".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
 
-    let message = &res.messages[1].msg;
-    match message {
-        CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr,
-            msg,
-            funds: _,
-        }) => {
-            assert_eq!(contract_addr, &token_contract);
-            assert_eq!(
-                msg,
-                &to_binary(&Mint {
-                    recipient: info.sender.to_string(),
-                    amount: Uint128::new(1111)
-                })
-                .unwrap()
-            );
-        }
-        _ => panic!("Unexpected message: {:?}", message),
-    }
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator.clone(), 100, "uluna");
 
-    set_delegation(&mut deps.querier, validator.clone(), 100900, "uluna");
+    // an empty batch can not be rolled over
+    let creator_info = mock_info("owner1", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        creator_info.clone(),
+        ExecuteMsg::ForceBatchRollover {},
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::EmptyBatch {});
 
-    //update user balance
+    // both requests only join the batch within the epoch
+    let token_info = mock_info(&token_contract, &[]);
+    let res = do_unbond(
+        deps.as_mut(),
+        bob.clone(),
+        mock_env(),
+        token_info.clone(),
+        Uint128::new(10),
+    );
+    assert_eq!(res.messages.len(), 1);
     deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&addr1, &Uint128::new(2111u128))])]);
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
 
-    let info = mock_info(&addr1, &[]);
-    let mut env = mock_env();
-    let _res = execute_unbond(
+    let res = do_unbond(
         deps.as_mut(),
-        env.clone(),
-        info.clone(),
-        Uint128::new(500),
-        addr1.clone(),
-    )
-    .unwrap();
-
+        bob.clone(),
+        mock_env(),
+        token_info,
+        Uint128::new(10),
+    );
+    assert_eq!(res.messages.len(), 1);
     deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&addr1, &Uint128::new(1611u128))])]);
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(80u128))])]);
 
-    env.block.time = env.block.time.plus_seconds(31);
+    // only the owner can force the rollover
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[]),
+        ExecuteMsg::ForceBatchRollover {},
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::NotAdmin {});
 
-    let res = execute_unbond(
+    let res = execute(
         deps.as_mut(),
-        env.clone(),
-        info.clone(),
-        Uint128::new(500),
-        addr1.clone(),
+        mock_env(),
+        creator_info,
+        ExecuteMsg::ForceBatchRollover {},
     )
     .unwrap();
-    let msgs: SubMsg = SubMsg::new(CosmosMsg::Staking(StakingMsg::Undelegate {
-        validator: validator.address,
-        amount: coin(900, "uluna"),
-    }));
-    assert_eq!(res.messages[0], msgs);
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Staking(StakingMsg::Undelegate {
+            validator: validator.address,
+            amount: coin(20, "uluna"),
+        }))]
+    );
 
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&addr1, &Uint128::new(1111u128))])]);
+    let current_batch: CurrentBatchResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::CurrentBatch {}).unwrap()).unwrap();
+    assert_eq!(current_batch.id, 2);
+    assert_eq!(current_batch.requests_count, 0);
+    assert_eq!(current_batch.requested_with_fee, Uint128::zero());
 
-    deps.querier.with_native_balances(&[(
-        MOCK_CONTRACT_ADDR.to_string(),
-        Coin {
-            denom: "uluna".to_string(),
-            amount: Uint128::new(900),
-        },
-    )]);
+    let history = read_unbond_history(&deps.storage, 1).unwrap();
+    assert_eq!(history.amount, Uint128::new(20));
+    assert!(!history.released);
+}
 
-    let ex_rate = QueryMsg::State {};
-    let query_exchange_rate: StateResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), ex_rate).unwrap()).unwrap();
-    assert_eq!(query_exchange_rate.exchange_rate, expected_er);
+/// Unbond 10 of the 100 token bonded by bob, with a cw20 send or directly with an allowance.
+fn unbond_through(direct: bool) -> (Response, CurrentBatchResponse, Uint128) {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
 
-    env.block.time = env.block.time.plus_seconds(90);
-    //check withdrawUnbonded message
-    let withdraw_unbond_msg = ExecuteMsg::WithdrawUnbonded {};
-    let wdraw_unbonded_res = execute(deps.as_mut(), env, info, withdraw_unbond_msg).unwrap();
-    assert_eq!(wdraw_unbonded_res.messages.len(), 1);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
 
-    let ex_rate = QueryMsg::State {};
-    let query_exchange_rate: StateResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), ex_rate).unwrap()).unwrap();
-    assert_eq!(query_exchange_rate.exchange_rate, expected_er);
+    let bob = "bob".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
 
-    let sent_message = &wdraw_unbonded_res.messages[0].msg;
-    match sent_message {
-        CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
-            assert_eq!(to_address, &addr1);
-            assert_eq!(amount[0].amount, Uint128::new(900))
-        }
+    let res = if direct {
+        let unbond = ExecuteMsg::Unbond {
+            amount: Uint128::new(10),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(&bob, &[]), unbond).unwrap()
+    } else {
+        do_unbond(
+            deps.as_mut(),
+            bob.clone(),
+            mock_env(),
+            mock_info("token", &[]),
+            Uint128::new(10),
+        )
+    };
 
-        _ => panic!("Unexpected message: {:?}", sent_message),
-    }
+    let current_batch: CurrentBatchResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::CurrentBatch {}).unwrap()).unwrap();
+    let wait_list = read_unbond_wait_list(&deps.storage, 1, bob).unwrap();
+    (res, current_batch, wait_list)
 }
 
-/// Covers if the withdraw_rate function is updated before and after withdraw_unbonded,
-/// the finished amount is accurate, user requests are removed from the waitlist, and
-/// the BankMsg::Send is sent.
+/// Covers if unbonding directly with an allowance pulls the token with TransferFrom
+/// and leaves the same batch state as the cw20 receive path.
 #[test]
-pub fn proper_withdraw_unbonded() {
-    let mut deps = dependencies(&[]);
+pub fn proper_unbond_direct() {
+    let (receive_res, receive_batch, receive_wait_list) = unbond_through(false);
+    let (direct_res, direct_batch, direct_wait_list) = unbond_through(true);
 
-    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
-    set_validator_mock(&mut deps.querier);
+    assert_eq!(
+        direct_res.messages[0],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: "bob".to_string(),
+                recipient: MOCK_CONTRACT_ADDR.to_string(),
+                amount: Uint128::new(10),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))
+    );
+    // the burn follows the transfer
+    assert_eq!(direct_res.messages[1..], receive_res.messages[..]);
 
-    let owner = "owner1".to_string();
-    let token_contract = "token".to_string();
+    assert_eq!(direct_batch, receive_batch);
+    assert_eq!(direct_batch.requested_with_fee, Uint128::new(10));
+    assert_eq!(direct_wait_list, receive_wait_list);
+    assert_eq!(direct_wait_list, Uint128::new(10));
+}
 
-    init(&mut deps, owner, token_contract, validator.address.clone());
+/// Covers if a direct unbond request more than the sender's balance is rejected.
+#[test]
+pub fn proper_unbond_direct_exceeds_balance() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
 
-    // register_validator
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
     do_register_validator(deps.as_mut(), validator.clone());
 
     let bob = "bob".to_string();
-    let bond_msg = ExecuteMsg::Bond {
-        validator: validator.address.clone(),
-    };
-
-    let info = mock_info(&bob, &[coin(100, "uluna")]);
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(10),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator, 10, "uluna");
 
     //set bob's balance to 10 in token contract
     deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(10u128))])]);
 
-    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg).unwrap();
+    let unbond = ExecuteMsg::Unbond {
+        amount: Uint128::new(11),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(&bob, &[]), unbond).unwrap_err();
+    assert_eq!(res, HubError::UnbondExceedsBalance {});
+    assert_eq!(res.to_string(), "unbond amount exceeds balance");
+
+    // the whole balance can be unbonded
+    let unbond = ExecuteMsg::Unbond {
+        amount: Uint128::new(10),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(&bob, &[]), unbond).unwrap();
     assert_eq!(2, res.messages.len());
+}
 
-    let delegate = &res.messages[0].msg;
-    match delegate {
-        CosmosMsg::Staking(StakingMsg::Delegate { validator, amount }) => {
-            assert_eq!(validator.as_str(), DEFAULT_VALIDATOR.to_string());
-            assert_eq!(amount, &coin(100, "uluna"));
-        }
-        _ => panic!("Unexpected message: {:?}", delegate),
-    }
+/// Covers if a soft-deregistered validator rejects new bonds and keeps its delegation,
+/// and accepts bonds again once it is registered again.
+#[test]
+pub fn proper_soft_deregister_validator() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
 
-    set_delegation(&mut deps.querier, validator, 100, "uluna");
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
 
-    let res = execute_unbond(
+    let bob = "bob".to_string();
+    do_bond(
         deps.as_mut(),
-        mock_env(),
-        info,
-        Uint128::new(10),
         bob.clone(),
-    )
-    .unwrap();
-    assert_eq!(1, res.messages.len());
-
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
-
-    deps.querier.with_native_balances(&[(
-        MOCK_CONTRACT_ADDR.to_string(),
-        Coin {
-            denom: "uluna".to_string(),
-            amount: Uint128::new(0),
-        },
-    )]);
+        Uint128::new(100),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator.clone(), 100, "uluna");
 
-    let info = mock_info(&bob, &[]);
-    let mut env = mock_env();
-    //set the block time 30 seconds from now.
-    env.block.time = env.block.time.plus_seconds(31);
+    let soft_deregister = ExecuteMsg::SoftDeregisterValidator {
+        validator: validator.address.clone(),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[]),
+        soft_deregister.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::NotAdmin {});
 
-    let wdraw_unbonded_msg = ExecuteMsg::WithdrawUnbonded {};
-    let wdraw_unbonded_res = execute(
+    // nothing is redelegated
+    let res = execute(
         deps.as_mut(),
-        env.clone(),
-        info.clone(),
-        wdraw_unbonded_msg.clone(),
-    );
+        mock_env(),
+        mock_info("owner1", &[]),
+        soft_deregister,
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
 
+    let bond = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+    let info = mock_info(&bob, &[coin(10, "uluna")]);
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond.clone()).unwrap_err();
+    assert_eq!(res, HubError::ValidatorNoNewBonds {});
+
+    // the validator stays whitelisted with its delegation
+    let whitelist: WhitelistedValidatorsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::WhitelistedValidators {
+                start_after: None,
+                limit: None,
+                reverse: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(whitelist.validators.contains(&validator.address));
+
+    let validator_info: ValidatorInfoResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::ValidatorInfo {
+                validator: validator.address.clone(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(validator_info.current_delegation, Uint128::new(100));
+    assert_eq!(validator_info.validator_status, ValidatorStatus::NoNewBonds);
+
+    do_register_validator(deps.as_mut(), validator);
+    let res = execute(deps.as_mut(), mock_env(), info, bond).unwrap();
+    assert_eq!(res.messages.len(), 2);
+}
+
+/// Covers if the pick_validator function sends different Undelegate messages
+/// to different validators, when a validator does not have enough delegation.
+#[test]
+pub fn proper_pick_validator() {
+    let mut deps = dependencies(&[]);
+
+    let addr1 = "addr1000".to_string();
+    let addr2 = "addr2000".to_string();
+    let addr3 = "addr3000".to_string();
+
+    // create 3 validators
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    let validator3 = sample_validator(DEFAULT_VALIDATOR3.to_string());
+
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        deps.borrow_mut(),
+        owner,
+        token_contract.clone(),
+        validator.address.clone(),
+    );
+
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_register_validator(deps.as_mut(), validator2.clone());
+    do_register_validator(deps.as_mut(), validator3.clone());
+
+    // bond to a validator
+    do_bond(
+        deps.as_mut(),
+        addr1.clone(),
+        Uint128::new(10),
+        validator.clone(),
+    );
+    do_bond(
+        deps.as_mut(),
+        addr2.clone(),
+        Uint128::new(300),
+        validator2.clone(),
+    );
+    do_bond(
+        deps.as_mut(),
+        addr3.clone(),
+        Uint128::new(200),
+        validator3.clone(),
+    );
+
+    // give validators different delegation amount
+    let delegations: [FullDelegation; 3] = [
+        (sample_delegation(validator.address.clone(), coin(10, "uluna"))),
+        (sample_delegation(validator2.address.clone(), coin(300, "uluna"))),
+        (sample_delegation(validator3.address.clone(), coin(200, "uluna"))),
+    ];
+
+    let validators: [Validator; 3] = [
+        (validator.clone()),
+        (validator2.clone()),
+        (validator3.clone()),
+    ];
+    set_delegation_query(&mut deps.querier, &delegations, &validators);
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[
+            (&addr3, &Uint128::new(200)),
+            (&addr2, &Uint128::new(300)),
+            (&addr1, &Uint128::new(10)),
+        ],
+    )]);
+
+    // send the first burn
+    let token_info = mock_info(&token_contract, &[]);
+    let mut token_env = mock_env();
+
+    let res = do_unbond(
+        deps.as_mut(),
+        addr2.clone(),
+        token_env.clone(),
+        token_info.clone(),
+        Uint128::new(50),
+    );
+    assert_eq!(res.messages.len(), 1);
+
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[
+            (&addr3, &Uint128::new(200)),
+            (&addr2, &Uint128::new(250)),
+            (&addr1, &Uint128::new(10)),
+        ],
+    )]);
+
+    token_env.block.time = token_env.block.time.plus_seconds(40);
+
+    // send the second burn
+    let res = do_unbond(
+        deps.as_mut(),
+        addr2.clone(),
+        token_env,
+        token_info,
+        Uint128::new(100),
+    );
+    assert!(res.messages.len() >= 2);
+
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[
+            (&addr3, &Uint128::new(200)),
+            (&addr2, &Uint128::new(150)),
+            (&addr1, &Uint128::new(10)),
+        ],
+    )]);
+
+    //check if the undelegate message is send two more than one validator.
+    if res.messages.len() > 2 {
+        match &res.messages[0].msg {
+            CosmosMsg::Staking(StakingMsg::Undelegate {
+                validator: val,
+                amount,
+            }) => {
+                if val == &validator.address {
+                    assert_eq!(amount.amount, Uint128::new(10))
+                }
+                if val == &validator2.address {
+                    assert_eq!(amount.amount, Uint128::new(150))
+                }
+                if val == &validator3.address {
+                    assert_eq!(amount.amount, Uint128::new(150))
+                }
+            }
+            _ => panic!("Unexpected message: {:?}", &res.messages[1]),
+        }
+
+        match &res.messages[1].msg {
+            CosmosMsg::Staking(StakingMsg::Undelegate {
+                validator: val,
+                amount,
+            }) => {
+                if val == &validator2.address {
+                    assert_eq!(amount.amount, Uint128::new(140))
+                }
+                if val == &validator3.address {
+                    assert_eq!(amount.amount, Uint128::new(140))
+                }
+            }
+            _ => panic!("Unexpected message: {:?}", &res.messages[2]),
+        }
+    } else {
+        match &res.messages[1].msg {
+            CosmosMsg::Staking(StakingMsg::Undelegate {
+                validator: val,
+                amount,
+            }) => {
+                if val == &validator2.address {
+                    assert_eq!(amount.amount, Uint128::new(150))
+                }
+                if val == &validator3.address {
+                    assert_eq!(amount.amount, Uint128::new(150))
+                }
+            }
+            _ => panic!("Unexpected message: {:?}", &res.messages[1]),
+        }
+    }
+}
+
+/// Covers if the pick_validator function sends different Undelegate messages
+/// if the delegations of the user are distributed to several validators
+/// and if the user wants to unbond amount that none of validators has.
+#[test]
+pub fn proper_pick_validator_respect_distributed_delegation() {
+    let mut deps = dependencies(&[]);
+
+    let addr1 = "addr1000".to_string();
+
+    // create 3 validators
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    let validator3 = sample_validator(DEFAULT_VALIDATOR3.to_string());
+
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        deps.borrow_mut(),
+        owner,
+        token_contract.clone(),
+        validator.address.clone(),
+    );
+
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_register_validator(deps.as_mut(), validator2.clone());
+    do_register_validator(deps.as_mut(), validator3);
+
+    // bond to a validator
+    do_bond(
+        deps.as_mut(),
+        addr1.clone(),
+        Uint128::new(1000),
+        validator.clone(),
+    );
+    do_bond(
+        deps.as_mut(),
+        addr1.clone(),
+        Uint128::new(1500),
+        validator2.clone(),
+    );
+
+    // give validators different delegation amount
+    let delegations: [FullDelegation; 2] = [
+        (sample_delegation(validator.address.clone(), coin(1000, "uluna"))),
+        (sample_delegation(validator2.address.clone(), coin(1500, "uluna"))),
+    ];
+
+    let validators: [Validator; 2] = [(validator.clone()), (validator2.clone())];
+    set_delegation_query(&mut deps.querier, &delegations, &validators);
+
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&addr1, &Uint128::new(2500))])]);
+
+    // send the first burn
+    let token_info = mock_info(&token_contract, &[]);
+
+    let mut token_env = mock_env();
+    token_env.block.time = token_env.block.time.plus_seconds(40);
+
+    let res = do_unbond(
+        deps.as_mut(),
+        addr1,
+        token_env,
+        token_info,
+        Uint128::new(2000),
+    );
+    assert_eq!(res.messages.len(), 3);
+
+    //check if the undelegate message is send two more than one validator.
+    if res.messages.len() > 2 {
+        match &res.messages[0].msg {
+            CosmosMsg::Staking(StakingMsg::Undelegate {
+                validator: val,
+                amount,
+            }) => {
+                if val == &validator.address {
+                    assert_eq!(amount.amount, Uint128::new(1000))
+                }
+                if val == &validator2.address {
+                    assert_eq!(amount.amount, Uint128::new(1500))
+                }
+            }
+            _ => panic!("Unexpected message: {:?}", &res.messages[1]),
+        }
+
+        match &res.messages[1].msg {
+            CosmosMsg::Staking(StakingMsg::Undelegate {
+                validator: val,
+                amount,
+            }) => {
+                if val == &validator.address {
+                    assert_eq!(amount.amount, Uint128::new(500))
+                }
+                if val == &validator2.address {
+                    assert_eq!(amount.amount, Uint128::new(1000))
+                }
+            }
+            _ => panic!("Unexpected message: {:?}", &res.messages[2]),
+        }
+    }
+}
+
+/// Covers if the pick_validator function avoids undelegations smaller than
+/// min_undelegate_per_validator when another validator can cover the claim.
+#[test]
+pub fn proper_pick_validator_respect_min_undelegation() {
+    let mut deps = dependencies(&[]);
+
+    let addr1 = "addr1000".to_string();
+
+    // create 3 validators
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    let validator3 = sample_validator(DEFAULT_VALIDATOR3.to_string());
+
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        deps.borrow_mut(),
+        owner.clone(),
+        token_contract.clone(),
+        validator.address.clone(),
+    );
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: Some(Uint128::new(50)),
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info, update_prams).unwrap();
+
+    // two of the validators only hold dust compared to the threshold
+    let delegations: [FullDelegation; 3] = [
+        (sample_delegation(validator.address.clone(), coin(10, "uluna"))),
+        (sample_delegation(validator2.address.clone(), coin(20, "uluna"))),
+        (sample_delegation(validator3.address.clone(), coin(999970, "uluna"))),
+    ];
+
+    let validators: [Validator; 3] = [validator, validator2, validator3.clone()];
+    set_delegation_query(&mut deps.querier, &delegations, &validators);
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&addr1, &INITIAL_DEPOSIT_AMOUNT)])]);
+
+    let token_info = mock_info(&token_contract, &[]);
+    let mut token_env = mock_env();
+    token_env.block.time = token_env.block.time.plus_seconds(31);
+
+    let res = do_unbond(
+        deps.as_mut(),
+        addr1,
+        token_env,
+        token_info,
+        Uint128::new(100),
+    );
+
+    // the whole claim is undelegated from the only validator that can cover it
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Staking(StakingMsg::Undelegate {
+            validator: validator3.address,
+            amount: coin(100, "uluna"),
+        }))
+    );
+}
+
+/// Covers the effect of slashing of bond, unbond, and withdraw_unbonded
+/// update the exchange rate after and before slashing.
+#[test]
+pub fn proper_slashing() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let addr1 = "addr1000".to_string();
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    init(
+        &mut deps,
+        owner,
+        token_contract.clone(),
+        validator.address.clone(),
+    );
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    //bond
+    do_bond(
+        deps.as_mut(),
+        addr1.clone(),
+        Uint128::new(1000),
+        validator.clone(),
+    );
+
+    //this will set the balance of the user in token contract
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&addr1, &Uint128::new(1000u128))])]);
+
+    // slashing
+    set_delegation(&mut deps.querier, validator.clone(), 900, "uluna");
+
+    let info = mock_info(&addr1, &[]);
+    let report_slashing = CheckSlashing {};
+    let res = execute(deps.as_mut(), mock_env(), info, report_slashing).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let ex_rate = QueryMsg::State {};
+    let query_exchange_rate: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), ex_rate).unwrap()).unwrap();
+    assert_eq!(query_exchange_rate.exchange_rate.to_string(), "0.9");
+
+    //bond again to see the update exchange rate
+    let second_bond = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+
+    let info = mock_info(&addr1, &[coin(1000, "uluna")]);
+
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), second_bond).unwrap();
+    assert_eq!(2, res.messages.len());
+
+    // expected exchange rate must be more than 0.9
+    let expected_er = Decimal::from_ratio(Uint128::new(1900), Uint128::new(2111));
+    let ex_rate = QueryMsg::State {};
+    let query_exchange_rate: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), ex_rate).unwrap()).unwrap();
+    assert_eq!(query_exchange_rate.exchange_rate, expected_er);
+
+    let delegate = &res.messages[0].msg;
+    match delegate {
+        CosmosMsg::Staking(StakingMsg::Delegate { validator, amount }) => {
+            assert_eq!(validator.as_str(), DEFAULT_VALIDATOR.to_string());
+            assert_eq!(amount, &coin(1000, "uluna"));
+        }
+        _ => panic!("Unexpected message: {:?}", delegate),
+    }
+
+    let message = &res.messages[1].msg;
+    match message {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            msg,
+            funds: _,
+        }) => {
+            assert_eq!(contract_addr, &token_contract);
+            assert_eq!(
+                msg,
+                &to_binary(&Mint {
+                    recipient: info.sender.to_string(),
+                    amount: Uint128::new(1111)
+                })
+                .unwrap()
+            );
+        }
+        _ => panic!("Unexpected message: {:?}", message),
+    }
+
+    set_delegation(&mut deps.querier, validator.clone(), 100900, "uluna");
+
+    //update user balance
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&addr1, &Uint128::new(2111u128))])]);
+
+    let info = mock_info(&addr1, &[]);
+    let mut env = mock_env();
+    let _res = execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(500),
+        addr1.clone(),
+    )
+    .unwrap();
+
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&addr1, &Uint128::new(1611u128))])]);
+
+    env.block.time = env.block.time.plus_seconds(31);
+
+    let res = execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(500),
+        addr1.clone(),
+    )
+    .unwrap();
+    let msgs: SubMsg = SubMsg::new(CosmosMsg::Staking(StakingMsg::Undelegate {
+        validator: validator.address,
+        amount: coin(900, "uluna"),
+    }));
+    assert_eq!(res.messages[0], msgs);
+
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&addr1, &Uint128::new(1111u128))])]);
+
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(900),
+        },
+    )]);
+
+    let ex_rate = QueryMsg::State {};
+    let query_exchange_rate: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), ex_rate).unwrap()).unwrap();
+    assert_eq!(query_exchange_rate.exchange_rate, expected_er);
+
+    env.block.time = env.block.time.plus_seconds(90);
+    //check withdrawUnbonded message
+    let withdraw_unbond_msg = ExecuteMsg::WithdrawUnbonded { recipient: None };
+    let wdraw_unbonded_res = execute(deps.as_mut(), env, info, withdraw_unbond_msg).unwrap();
+    assert_eq!(wdraw_unbonded_res.messages.len(), 1);
+
+    let ex_rate = QueryMsg::State {};
+    let query_exchange_rate: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), ex_rate).unwrap()).unwrap();
+    assert_eq!(query_exchange_rate.exchange_rate, expected_er);
+
+    let sent_message = &wdraw_unbonded_res.messages[0].msg;
+    match sent_message {
+        CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+            assert_eq!(to_address, &addr1);
+            assert_eq!(amount[0].amount, Uint128::new(900))
+        }
+
+        _ => panic!("Unexpected message: {:?}", sent_message),
+    }
+}
+
+/// Covers if the slashing check raises the exchange rate on a positive
+/// delegation drift only when track_positive_drift is set.
+#[test]
+pub fn proper_track_positive_drift() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let addr1 = "addr1000".to_string();
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    init(&mut deps, owner, token_contract, validator.address.clone());
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    //bond
+    do_bond(
+        deps.as_mut(),
+        addr1.clone(),
+        Uint128::new(1000),
+        validator.clone(),
+    );
+
+    //this will set the balance of the user in token contract
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&addr1, &Uint128::new(1000u128))])]);
+
+    // a donation is ignored by default
+    set_delegation(&mut deps.querier, validator.clone(), 1100, "uluna");
+
+    let info = mock_info(&addr1, &[]);
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), CheckSlashing {}).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.exchange_rate.to_string(), "1");
+    assert_eq!(state.total_bond_amount, Uint128::new(1000));
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: Some(true),
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let creator_info = mock_info("owner1", &[]);
+    execute(deps.as_mut(), mock_env(), creator_info, update_prams).unwrap();
+
+    // drift up
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), CheckSlashing {}).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.exchange_rate.to_string(), "1.1");
+    assert_eq!(state.total_bond_amount, Uint128::new(1100));
+
+    // slash down
+    set_delegation(&mut deps.querier, validator, 990, "uluna");
+
+    let res = execute(deps.as_mut(), mock_env(), info, CheckSlashing {}).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.exchange_rate.to_string(), "0.99");
+    assert_eq!(state.total_bond_amount, Uint128::new(990));
+}
+
+/// Covers if the withdraw_rate function is updated before and after withdraw_unbonded,
+/// the finished amount is accurate, user requests are removed from the waitlist, and
+/// the BankMsg::Send is sent.
+#[test]
+pub fn proper_withdraw_unbonded() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(&mut deps, owner, token_contract, validator.address.clone());
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let bond_msg = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+
+    let info = mock_info(&bob, &[coin(100, "uluna")]);
+
+    //set bob's balance to 10 in token contract
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg).unwrap();
+    assert_eq!(2, res.messages.len());
+
+    let delegate = &res.messages[0].msg;
+    match delegate {
+        CosmosMsg::Staking(StakingMsg::Delegate { validator, amount }) => {
+            assert_eq!(validator.as_str(), DEFAULT_VALIDATOR.to_string());
+            assert_eq!(amount, &coin(100, "uluna"));
+        }
+        _ => panic!("Unexpected message: {:?}", delegate),
+    }
+
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
+
+    let res = execute_unbond(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    assert_eq!(1, res.messages.len());
+
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
+
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(0),
+        },
+    )]);
+
+    let info = mock_info(&bob, &[]);
+    let mut env = mock_env();
+    //set the block time 30 seconds from now.
+    env.block.time = env.block.time.plus_seconds(31);
+
+    let wdraw_unbonded_msg = ExecuteMsg::WithdrawUnbonded { recipient: None };
+    let wdraw_unbonded_res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        wdraw_unbonded_msg.clone(),
+    );
+
+    // trigger undelegation message
+    assert!(wdraw_unbonded_res.is_err());
+    assert_eq!(
+        wdraw_unbonded_res.unwrap_err(),
+        HubError::NothingToWithdraw {
+            denom: "uluna".to_string(),
+        }
+    );
+
+    let res = execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(80u128))])]);
+
+    //this query should be zero since the undelegated period is not passed
+    let withdrawable = WithdrawableUnbonded {
+        address: bob.clone(),
+    };
+    let query_with = query(deps.as_ref(), mock_env(), withdrawable).unwrap();
+    let res: WithdrawableUnbondedResponse = from_binary(&query_with).unwrap();
+    assert_eq!(res.withdrawable, Uint128::new(0));
+
+    env.block.time = env.block.time.plus_seconds(91);
+
+    // fabricate balance of the hub contract
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(20),
+        },
+    )]);
+    //first query AllUnbondedRequests
+    let all_unbonded = UnbondRequests {
+        address: bob.clone(),
+    };
+    let query_unbonded = query(deps.as_ref(), mock_env(), all_unbonded).unwrap();
+    let res: UnbondRequestsResponse = from_binary(&query_unbonded).unwrap();
+    assert_eq!(res.requests.len(), 1);
+    //the amount should be 10
+    assert_eq!(&res.address, &bob);
+    assert_eq!(res.requests[0].1, Uint128::new(20));
+    assert_eq!(res.requests[0].0, 1);
+
+    let all_batches = AllHistory {
+        start_from: None,
+        limit: None,
+    };
+    let res: AllHistoryResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), all_batches).unwrap()).unwrap();
+    assert_eq!(res.history[0].amount, Uint128::new(20));
+    assert_eq!(res.history[0].batch_id, 1);
+
+    //check with query
+    let withdrawable = WithdrawableUnbonded {
+        address: bob.clone(),
+    };
+    let query_with = query(deps.as_ref(), env.clone(), withdrawable).unwrap();
+    let res: WithdrawableUnbondedResponse = from_binary(&query_with).unwrap();
+    assert_eq!(res.withdrawable, Uint128::new(20));
+
+    let success_res = execute(deps.as_mut(), env, info, wdraw_unbonded_msg).unwrap();
+
+    assert_eq!(success_res.messages.len(), 1);
+
+    let sent_message = &success_res.messages[0].msg;
+    match sent_message {
+        CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+            assert_eq!(to_address, &bob);
+            assert_eq!(amount[0].amount, Uint128::new(20))
+        }
+
+        _ => panic!("Unexpected message: {:?}", sent_message),
+    }
+
+    //it should be removed
+    let withdrawable = WithdrawableUnbonded {
+        address: bob.clone(),
+    };
+    let query_with: WithdrawableUnbondedResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), withdrawable).unwrap()).unwrap();
+    assert_eq!(query_with.withdrawable, Uint128::new(0));
+
+    let waitlist = UnbondRequests {
+        address: bob.clone(),
+    };
+    let query_unbond: UnbondRequestsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), waitlist).unwrap()).unwrap();
+    assert_eq!(
+        query_unbond,
+        UnbondRequestsResponse {
+            address: bob,
+            requests: vec![]
+        }
+    );
+
+    // because of one that we add for each batch
+    let state = QueryMsg::State {};
+    let state_query: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), state).unwrap()).unwrap();
+    assert_eq!(state_query.prev_hub_balance, Uint128::new(0));
+    assert_eq!(state_query.exchange_rate, Decimal::one());
+}
+
+/// Covers if matured batches are released with their withdraw rates without sending funds,
+/// the limit only releases the first batches, and withdraw_unbonded reads the stored rates.
+#[test]
+pub fn proper_process_matured_batches() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(&mut deps, owner, token_contract, validator.address.clone());
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
+
+    let info = mock_info(&bob, &[]);
+    let mut env = mock_env();
+
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
+
+    // close the first batch
+    env.block.time = env.block.time.plus_seconds(31);
+    let res = execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(80u128))])]);
+
+    // close the second batch
+    env.block.time = env.block.time.plus_seconds(31);
+    let res = execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(70u128))])]);
+
+    // both batches are matured and their funds have arrived
+    env.block.time = env.block.time.plus_seconds(3);
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(30),
+        },
+    )]);
+
+    // release only the first batch
+    let keeper_info = mock_info("keeper", &[]);
+    let process = ExecuteMsg::ProcessMaturedBatches { limit: Some(1) };
+    let res = execute(deps.as_mut(), env.clone(), keeper_info.clone(), process).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let all_batches = AllHistory {
+        start_from: None,
+        limit: None,
+    };
+    let res: AllHistoryResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), all_batches.clone()).unwrap()).unwrap();
+    assert_eq!(res.history.len(), 2);
+    assert!(res.history[0].released);
+    assert_eq!(res.history[0].withdraw_rate, Decimal::one());
+    assert!(!res.history[1].released);
+
+    // release the rest
+    let process = ExecuteMsg::ProcessMaturedBatches { limit: None };
+    let res = execute(deps.as_mut(), env.clone(), keeper_info, process).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let res: AllHistoryResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), all_batches).unwrap()).unwrap();
+    assert!(res.history[0].released);
+    assert_eq!(res.history[0].withdraw_rate, Decimal::one());
+    assert!(res.history[1].released);
+    assert_eq!(res.history[1].withdraw_rate, Decimal::one());
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.prev_hub_balance, Uint128::new(30));
+    assert_eq!(state.last_processed_batch, 2);
+
+    // withdraw reads the already released batches
+    let res = execute(
+        deps.as_mut(),
+        env,
+        info,
+        ExecuteMsg::WithdrawUnbonded { recipient: None },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: bob,
+            amount: vec![Coin::new(30u128, "uluna")],
+        }))
+    );
+}
+
+/// Covers if the open batches count rises when a batch is closed
+/// and falls when the batch is released, also with a partial release.
+#[test]
+pub fn proper_open_batches_count() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(&mut deps, owner, token_contract, validator.address.clone());
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
+
+    let info = mock_info(&bob, &[]);
+    let mut env = mock_env();
+
+    // the first request does not close the batch
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.open_batches_count, 0);
+
+    // close the first batch
+    env.block.time = env.block.time.plus_seconds(31);
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(80u128))])]);
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.open_batches_count, 1);
+
+    // close the second batch
+    env.block.time = env.block.time.plus_seconds(31);
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(70u128))])]);
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.open_batches_count, 2);
+
+    // both batches are matured and their funds have arrived
+    env.block.time = env.block.time.plus_seconds(3);
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(30),
+        },
+    )]);
+
+    // release only the first batch
+    let keeper_info = mock_info("keeper", &[]);
+    let process = ExecuteMsg::ProcessMaturedBatches { limit: Some(1) };
+    execute(deps.as_mut(), env.clone(), keeper_info, process).unwrap();
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.open_batches_count, 1);
+
+    // withdraw releases the rest
+    execute(
+        deps.as_mut(),
+        env,
+        info,
+        ExecuteMsg::WithdrawUnbonded { recipient: None },
+    )
+    .unwrap();
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.open_batches_count, 0);
+}
+
+/// Covers if the maturity of each unreleased batch is its unbond time plus the unbonding period.
+#[test]
+pub fn proper_batch_maturities() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(&mut deps, owner, token_contract, validator.address.clone());
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
+
+    let info = mock_info(&bob, &[]);
+    let mut env = mock_env();
+
+    let maturities: BatchMaturitiesResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::BatchMaturities {}).unwrap())
+            .unwrap();
+    assert!(maturities.batches.is_empty());
+
+    // close the first batch
+    env.block.time = env.block.time.plus_seconds(31);
+    let first_batch_time = env.block.time.seconds();
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
+
+    // close the second batch
+    env.block.time = env.block.time.plus_seconds(31);
+    let second_batch_time = env.block.time.seconds();
+    execute_unbond(deps.as_mut(), env.clone(), info, Uint128::new(10), bob).unwrap();
+
+    let maturities: BatchMaturitiesResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::BatchMaturities {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        maturities.batches,
+        vec![
+            BatchMaturity {
+                batch_id: 1,
+                matures_at: first_batch_time + 2,
+            },
+            BatchMaturity {
+                batch_id: 2,
+                matures_at: second_batch_time + 2,
+            },
+        ]
+    );
+
+    // the released batch is not reported anymore
+    env.block.time = env.block.time.plus_seconds(3);
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(20),
+        },
+    )]);
+    let process = ExecuteMsg::ProcessMaturedBatches { limit: Some(1) };
+    execute(deps.as_mut(), env, mock_info("keeper", &[]), process).unwrap();
+
+    let maturities: BatchMaturitiesResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::BatchMaturities {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        maturities.batches,
+        vec![BatchMaturity {
+            batch_id: 2,
+            matures_at: second_batch_time + 2,
+        }]
+    );
+}
+
+/// Covers if a matured batch is reported as stuck until its unbonded
+/// funds arrive in the contract balance.
+#[test]
+pub fn proper_stuck_batches() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(&mut deps, owner, token_contract, validator.address.clone());
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
+
+    // close the first batch
+    let info = mock_info(&bob, &[]);
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(31);
+    let batch_time = env.block.time.seconds();
+    execute_unbond(deps.as_mut(), env.clone(), info, Uint128::new(10), bob).unwrap();
+
+    // the batch is not stuck before it matures
+    let stuck: StuckBatchesResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::StuckBatches {}).unwrap())
+            .unwrap();
+    assert!(stuck.batches.is_empty());
+
+    // matured but unfunded
+    env.block.time = env.block.time.plus_seconds(3);
+    let stuck: StuckBatchesResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::StuckBatches {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        stuck.batches,
+        vec![StuckBatch {
+            batch_id: 1,
+            matures_at: batch_time + 2,
+            expected_amount: Uint128::new(10),
+        }]
+    );
+
+    // the unbonded funds arrive
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(10),
+        },
+    )]);
+    let stuck: StuckBatchesResponse =
+        from_binary(&query(deps.as_ref(), env, QueryMsg::StuckBatches {}).unwrap()).unwrap();
+    assert!(stuck.batches.is_empty());
+}
+
+/// Covers if the lifetime bond and unbond totals are tracked per user,
+/// and bonds made with BondFor are credited to the recipient.
+#[test]
+pub fn proper_user_stats() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    init(
+        deps.borrow_mut(),
+        owner,
+        token_contract.clone(),
+        validator.address.clone(),
+    );
+
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let alice = "alice".to_string();
+
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(10),
+        validator.clone(),
+    );
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(20),
+        validator.clone(),
+    );
+
+    let bond_for = ExecuteMsg::BondFor {
+        validator: validator.address.clone(),
+        recipient: bob.clone(),
+        min_mint: None,
+    };
+    let info = mock_info(&alice, &[coin(5, "uluna")]);
+    execute(deps.as_mut(), mock_env(), info, bond_for).unwrap();
+
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(35u128))])]);
+    set_delegation(&mut deps.querier, validator, 35, "uluna");
+
+    let token_info = mock_info(&token_contract, &[]);
+    do_unbond(
+        deps.as_mut(),
+        bob.clone(),
+        mock_env(),
+        token_info,
+        Uint128::new(10),
+    );
+
+    let res: UserStatsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::UserStats {
+                address: bob.clone(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        UserStatsResponse {
+            address: bob,
+            total_bonded: Uint128::new(35),
+            total_unbonded: Uint128::new(10),
+            bond_count: 3,
+            unbond_count: 1,
+        }
+    );
+
+    // the sender of BondFor is not credited
+    let res: UserStatsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::UserStats {
+                address: alice.clone(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        UserStatsResponse {
+            address: alice,
+            total_bonded: Uint128::zero(),
+            total_unbonded: Uint128::zero(),
+            bond_count: 0,
+            unbond_count: 0,
+        }
+    );
+}
+
+/// Covers if the active batches report only the unreleased batches
+/// and flag the ones whose unbonding period has elapsed.
+#[test]
+pub fn proper_active_batches() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address,
+    );
+
+    let now = mock_env().block.time.seconds();
+    let histories = vec![
+        (1, now - 10, true),
+        (2, now - 2, false),
+        (3, now - 1, false),
+    ];
+    for (batch_id, time, released) in histories {
+        store_unbond_history(
+            &mut deps.storage,
+            batch_id,
+            UnbondHistory {
+                batch_id,
+                time,
+                amount: Uint128::new(batch_id as u128 * 10),
+                applied_exchange_rate: Decimal::one(),
+                withdraw_rate: Decimal::one(),
+                released,
+            },
+        )
+        .unwrap();
+    }
+
+    let res: ActiveBatchesResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::ActiveBatches {}).unwrap())
+            .unwrap();
+    // the unbonding period is 2 seconds
+    assert_eq!(
+        res.batches,
+        vec![
+            ActiveBatch {
+                id: 2,
+                requested_with_fee: Uint128::new(20),
+                creation_time: now - 2,
+                matured: true,
+            },
+            ActiveBatch {
+                id: 3,
+                requested_with_fee: Uint128::new(30),
+                creation_time: now - 1,
+                matured: false,
+            },
+        ]
+    );
+
+    // the batches up to the last processed one are released and skipped
+    let mut history = read_unbond_history(&deps.storage, 2).unwrap();
+    history.released = true;
+    store_unbond_history(&mut deps.storage, 2, history).unwrap();
+    STATE
+        .update(&mut deps.storage, |mut state| -> StdResult<State> {
+            state.last_processed_batch = 2;
+            Ok(state)
+        })
+        .unwrap();
+    let res: ActiveBatchesResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::ActiveBatches {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        res.batches,
+        vec![ActiveBatch {
+            id: 3,
+            requested_with_fee: Uint128::new(30),
+            creation_time: now - 1,
+            matured: false,
+        }]
+    );
+}
+
+/// Covers if the reconciliation reports a negative drift when the delegations
+/// fall below the stored bonded amount before the slashing is checked.
+#[test]
+pub fn proper_bond_reconciliation() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_bond(
+        deps.as_mut(),
+        "bob".to_string(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+
+    // the initial deposit and the bond are stored as bonded
+    set_delegation(&mut deps.querier, validator, 1000090, "uluna");
+
+    let res: BondReconciliationResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::BondReconciliation {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        res,
+        BondReconciliationResponse {
+            state_total_bond: Uint128::new(1000100),
+            chain_total_delegated: Uint128::new(1000090),
+            drift: SignedInt(Uint128::new(10), true),
+        }
+    );
+}
+
+/// Covers if compounding returns an error instead of panicking when the
+/// bonded amount would overflow or when there is no supply.
+#[test]
+pub fn proper_update_exchange_rate_overflow() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    set_delegation(
+        &mut deps.querier,
+        validator,
+        INITIAL_DEPOSIT_AMOUNT.u128(),
+        "uluna",
+    );
+
+    // no token has been issued
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[])]);
+    let info = mock_info("rewards_contract", &[Coin::new(100, "uluna")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap_err();
+    assert_eq!(
+        res,
+        HubError::Std(StdError::generic_err(
+            "Rewards can not be compounded without any supply"
+        ))
+    );
+
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &INITIAL_DEPOSIT_AMOUNT)],
+    )]);
+    STATE
+        .update(&mut deps.storage, |mut state| -> StdResult<State> {
+            state.total_bond_amount = Uint128::MAX - Uint128::new(50);
+            Ok(state)
+        })
+        .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap_err();
+    assert!(matches!(res, HubError::Std(StdError::Overflow { .. })));
+}
+
+/// Covers if the wait list of a batch lists every requester with the requested amount,
+/// page by page.
+#[test]
+pub fn proper_batch_wait_list() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let alice = "alice".to_string();
+    let bob = "bob".to_string();
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[
+            (&alice, &Uint128::new(100u128)),
+            (&bob, &Uint128::new(100u128)),
+        ],
+    )]);
+    do_bond(
+        deps.as_mut(),
+        alice.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator, 200, "uluna");
+
+    let token_info = mock_info("token", &[]);
+    do_unbond(
+        deps.as_mut(),
+        alice.clone(),
+        mock_env(),
+        token_info.clone(),
+        Uint128::new(10),
+    );
+    do_unbond(
+        deps.as_mut(),
+        bob.clone(),
+        mock_env(),
+        token_info,
+        Uint128::new(20),
+    );
+
+    let wait_list = QueryMsg::BatchWaitList {
+        batch_id: 1,
+        start_after: None,
+        limit: None,
+    };
+    let res: BatchWaitListResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), wait_list).unwrap()).unwrap();
+    assert_eq!(res.batch_id, 1);
+    assert_eq!(res.requests.len(), 2);
+    assert!(res.requests.contains(&(alice, Uint128::new(10))));
+    assert!(res.requests.contains(&(bob, Uint128::new(20))));
+
+    // the second page starts after the first requester
+    let first_page = QueryMsg::BatchWaitList {
+        batch_id: 1,
+        start_after: None,
+        limit: Some(1),
+    };
+    let first: BatchWaitListResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), first_page).unwrap()).unwrap();
+    let second_page = QueryMsg::BatchWaitList {
+        batch_id: 1,
+        start_after: Some(first.requests[0].0.clone()),
+        limit: Some(1),
+    };
+    let second: BatchWaitListResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), second_page).unwrap()).unwrap();
+    assert_eq!([first.requests, second.requests].concat(), res.requests);
+
+    // nobody has unbonded into the next batch
+    let next_batch = QueryMsg::BatchWaitList {
+        batch_id: 2,
+        start_after: None,
+        limit: None,
+    };
+    let res: BatchWaitListResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), next_batch).unwrap()).unwrap();
+    assert!(res.requests.is_empty());
+}
+
+/// Covers if the compound simulation lists the delegated validators
+/// and sums their accumulated rewards in the underlying coin.
+#[test]
+pub fn proper_simulate_compound() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(&mut deps, owner, token_contract, validator.address.clone());
+
+    let mut delegation = sample_delegation(validator.address.clone(), coin(1000, "uluna"));
+    delegation.accumulated_rewards = vec![coin(150, "uluna"), coin(20, "uusd")];
+    let mut delegation2 = sample_delegation(validator2.address.clone(), coin(500, "uluna"));
+    delegation2.accumulated_rewards = vec![coin(50, "uluna")];
+    set_delegation_query(
+        &mut deps.querier,
+        &[delegation, delegation2],
+        &[validator.clone(), validator2.clone()],
+    );
+
+    let res: SimulateCompoundResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::SimulateCompound {}).unwrap())
+            .unwrap();
+    assert_eq!(res.validators, vec![validator.address, validator2.address]);
+    assert_eq!(res.claimable_rewards, Uint128::new(200));
+}
+
+/// Covers if the idle balance reports the underlying balance held by the hub.
+#[test]
+pub fn proper_idle_balance() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(&mut deps, owner, token_contract, validator.address);
+
+    let idle: IdleBalanceResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::IdleBalance {}).unwrap()).unwrap();
+    assert_eq!(idle.amount, Uint128::zero());
+
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(250),
+        },
+    )]);
+
+    let idle: IdleBalanceResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::IdleBalance {}).unwrap()).unwrap();
+    assert_eq!(idle.amount, Uint128::new(250));
+}
+
+/// Covers if the release time of a request is its batch time plus the unbonding period,
+/// and the open batch is estimated to close at the end of the epoch.
+#[test]
+pub fn proper_unbond_timeline() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(&mut deps, owner, token_contract, validator.address.clone());
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
+
+    // unbond into the first batch, which is closed right away
+    let info = mock_info(&bob, &[]);
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(31);
+    let batch_time = env.block.time.seconds();
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
+
+    // unbond into the open second batch
+    execute_unbond(deps.as_mut(), env, info, Uint128::new(5), bob.clone()).unwrap();
+
+    let timeline: UnbondTimelineResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::UnbondTimeline {
+                address: bob.clone(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(timeline.address, bob);
+    assert_eq!(
+        timeline.requests,
+        vec![
+            UnbondTimelineEntry {
+                batch_id: 1,
+                requested_amount: Uint128::new(10),
+                estimated_release_time: batch_time + 2,
+            },
+            UnbondTimelineEntry {
+                batch_id: 2,
+                requested_amount: Uint128::new(5),
+                estimated_release_time: batch_time + 30 + 2,
+            },
+        ]
+    );
+}
+
+/// Covers slashing during the unbonded period and its effect on the finished amount.
+#[test]
+pub fn proper_withdraw_unbonded_respect_slashing() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let bond_amount = Uint128::new(10000);
+    let unbond_amount = Uint128::new(500);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(&mut deps, owner, token_contract, validator.address.clone());
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let bond_msg = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+
+    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
+
+    //set bob's balance to 10 in token contract
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg).unwrap();
+    assert_eq!(2, res.messages.len());
+
+    let delegate = &res.messages[0].msg;
+    match delegate {
+        CosmosMsg::Staking(StakingMsg::Delegate { validator, amount }) => {
+            assert_eq!(validator.as_str(), DEFAULT_VALIDATOR.to_string());
+            assert_eq!(amount, &coin(bond_amount.u128(), "uluna"));
+        }
+        _ => panic!("Unexpected message: {:?}", delegate),
+    }
+
+    set_delegation(&mut deps.querier, validator, bond_amount.u128(), "uluna");
+
+    let res = execute_unbond(deps.as_mut(), mock_env(), info, unbond_amount, bob.clone()).unwrap();
+    assert_eq!(1, res.messages.len());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9500))])]);
+
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(0),
+        },
+    )]);
+
+    let info = mock_info(&bob, &[]);
+    let mut env = mock_env();
+    //set the block time 30 seconds from now.
+
+    env.block.time = env.block.time.plus_seconds(31);
+
+    let wdraw_unbonded_msg = ExecuteMsg::WithdrawUnbonded { recipient: None };
+    let wdraw_unbonded_res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        wdraw_unbonded_msg.clone(),
+    );
+    assert!(wdraw_unbonded_res.is_err());
+    assert_eq!(
+        wdraw_unbonded_res.unwrap_err(),
+        HubError::NothingToWithdraw {
+            denom: "uluna".to_string(),
+        }
+    );
+
+    // trigger undelegation message
+    let res = execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        unbond_amount,
+        bob.clone(),
+    )
+    .unwrap();
+    assert_eq!(2, res.messages.len());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9000))])]);
+
+    //this query should be zero since the undelegated period is not passed
+    let withdrawable = WithdrawableUnbonded {
+        address: bob.clone(),
+    };
+    let query_with = query(deps.as_ref(), mock_env(), withdrawable).unwrap();
+    let res: WithdrawableUnbondedResponse = from_binary(&query_with).unwrap();
+    assert_eq!(res.withdrawable, Uint128::new(0));
+
+    env.block.time = env.block.time.plus_seconds(91);
+
+    // fabricate balance of the hub contract
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(900),
+        },
+    )]);
+
+    //first query AllUnbondedRequests
+    let all_unbonded = UnbondRequests {
+        address: bob.clone(),
+    };
+    let query_unbonded = query(deps.as_ref(), mock_env(), all_unbonded).unwrap();
+    let res: UnbondRequestsResponse = from_binary(&query_unbonded).unwrap();
+    assert_eq!(res.requests.len(), 1);
+    //the amount should be 10
+    assert_eq!(&res.address, &bob);
+    assert_eq!(res.requests[0].1, Uint128::new(1000));
+    assert_eq!(res.requests[0].0, 1);
+
+    //check with query
+    //this query does not reflect the actual withdrawable
+    let withdrawable = WithdrawableUnbonded {
+        address: bob.clone(),
+    };
+    let query_with = query(deps.as_ref(), env.clone(), withdrawable).unwrap();
+    let res: WithdrawableUnbondedResponse = from_binary(&query_with).unwrap();
+    assert_eq!(res.withdrawable, Uint128::new(1000));
+
+    let success_res = execute(deps.as_mut(), env, info, wdraw_unbonded_msg).unwrap();
+
+    assert_eq!(success_res.messages.len(), 1);
+
+    let sent_message = &success_res.messages[0].msg;
+    match sent_message {
+        CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+            assert_eq!(to_address, &bob);
+            assert_eq!(amount[0].amount, Uint128::new(899))
+        }
+
+        _ => panic!("Unexpected message: {:?}", sent_message),
+    }
+
+    // there should not be any result
+    let withdrawable = WithdrawableUnbonded { address: bob };
+    let query_with: WithdrawableUnbondedResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), withdrawable).unwrap()).unwrap();
+    assert_eq!(query_with.withdrawable, Uint128::new(0));
+}
+
+/// Covers withdraw_unbonded/inactivity in the system while there are slashing events.
+#[test]
+pub fn proper_withdraw_unbonded_respect_inactivity_slashing() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let bond_amount = Uint128::new(10000);
+    let unbond_amount = Uint128::new(500);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(&mut deps, owner, token_contract, validator.address.clone());
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let bond_msg = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+
+    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
+
+    //set bob's balance to 10 in token contract
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg).unwrap();
+    assert_eq!(2, res.messages.len());
+
+    let delegate = &res.messages[0].msg;
+    match delegate {
+        CosmosMsg::Staking(StakingMsg::Delegate { validator, amount }) => {
+            assert_eq!(validator.as_str(), DEFAULT_VALIDATOR.to_string());
+            assert_eq!(amount, &coin(bond_amount.u128(), "uluna"));
+        }
+        _ => panic!("Unexpected message: {:?}", delegate),
+    }
+
+    set_delegation(&mut deps.querier, validator, bond_amount.u128(), "uluna");
+
+    let res = execute_unbond(deps.as_mut(), mock_env(), info, unbond_amount, bob.clone()).unwrap();
+    assert_eq!(1, res.messages.len());
+
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9500))])]);
+
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(0),
+        },
+    )]);
+
+    let info = mock_info(&bob, &[]);
+    let mut env = mock_env();
+    //set the block time 30 seconds from now.
+
+    let current_batch = QueryMsg::CurrentBatch {};
+    let query_batch: CurrentBatchResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), current_batch).unwrap()).unwrap();
+    assert_eq!(query_batch.id, 1);
+    assert_eq!(query_batch.requested_with_fee, unbond_amount);
+
+    env.block.time = env.block.time.plus_seconds(1000);
+    let wdraw_unbonded_msg = ExecuteMsg::WithdrawUnbonded { recipient: None };
+    let wdraw_unbonded_res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        wdraw_unbonded_msg.clone(),
+    );
+    assert!(wdraw_unbonded_res.is_err());
+    assert_eq!(
+        wdraw_unbonded_res.unwrap_err(),
+        HubError::NothingToWithdraw {
+            denom: "uluna".to_string(),
+        }
+    );
+
+    // trigger undelegation message
+    let res = execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        unbond_amount,
+        bob.clone(),
+    )
+    .unwrap();
+    assert_eq!(2, res.messages.len());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9000))])]);
+
+    let current_batch = QueryMsg::CurrentBatch {};
+    let query_batch: CurrentBatchResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), current_batch).unwrap()).unwrap();
+    assert_eq!(query_batch.id, 2);
+    assert_eq!(query_batch.requested_with_fee, Uint128::zero());
+
+    let all_batches = AllHistory {
+        start_from: None,
+        limit: None,
+    };
+    let res: AllHistoryResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), all_batches).unwrap()).unwrap();
+    assert_eq!(res.history[0].amount, Uint128::new(1000));
+    assert_eq!(res.history[0].withdraw_rate.to_string(), "1");
+    assert!(!res.history[0].released);
+    assert_eq!(res.history[0].batch_id, 1);
+
+    //this query should be zero since the undelegated period is not passed
+    let withdrawable = WithdrawableUnbonded {
+        address: bob.clone(),
+    };
+    let query_with = query(deps.as_ref(), mock_env(), withdrawable).unwrap();
+    let res: WithdrawableUnbondedResponse = from_binary(&query_with).unwrap();
+    assert_eq!(res.withdrawable, Uint128::zero());
+
+    env.block.time = env.block.time.plus_seconds(1091);
+
+    // fabricate balance of the hub contract
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(900),
+        },
+    )]);
+    //first query AllUnbondedRequests
+    let all_unbonded = UnbondRequests {
+        address: bob.clone(),
+    };
+    let query_unbonded = query(deps.as_ref(), env.clone(), all_unbonded).unwrap();
+    let res: UnbondRequestsResponse = from_binary(&query_unbonded).unwrap();
+    assert_eq!(res.requests.len(), 1);
+    //the amount should be 10
+    assert_eq!(&res.address, &bob);
+    assert_eq!(res.requests[0].1, Uint128::new(1000));
+    assert_eq!(res.requests[0].0, 1);
+
+    //check with query
+    //this query does not reflect the actual withdrawable
+    let withdrawable = WithdrawableUnbonded {
+        address: bob.clone(),
+    };
+    let query_with = query(deps.as_ref(), env.clone(), withdrawable).unwrap();
+    let res: WithdrawableUnbondedResponse = from_binary(&query_with).unwrap();
+    assert_eq!(res.withdrawable, Uint128::new(1000));
+
+    let success_res = execute(deps.as_mut(), env.clone(), info, wdraw_unbonded_msg).unwrap();
+
+    assert_eq!(success_res.messages.len(), 1);
+
+    let sent_message = &success_res.messages[0].msg;
+    match sent_message {
+        CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+            assert_eq!(to_address, &bob);
+            assert_eq!(amount[0].amount, Uint128::new(899))
+        }
+
+        _ => panic!("Unexpected message: {:?}", sent_message),
+    }
+
+    // there should not be any result
+    let withdrawable = WithdrawableUnbonded { address: bob };
+    let query_with: WithdrawableUnbondedResponse =
+        from_binary(&query(deps.as_ref(), env, withdrawable).unwrap()).unwrap();
+    assert_eq!(query_with.withdrawable, Uint128::new(0));
+
+    let all_batches = AllHistory {
+        start_from: None,
+        limit: None,
+    };
+    let res: AllHistoryResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), all_batches).unwrap()).unwrap();
+    assert_eq!(res.history[0].amount, Uint128::new(1000));
+    assert_eq!(res.history[0].applied_exchange_rate.to_string(), "1");
+    assert_eq!(res.history[0].withdraw_rate.to_string(), "0.899");
+    assert!(res.history[0].released);
+    assert_eq!(res.history[0].batch_id, 1);
+}
+
+/// Covers if the signed integer works properly,
+/// the exception when a user sends rogue coin.
+#[test]
+pub fn proper_withdraw_unbond_with_dummies() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let bond_amount = Uint128::new(10000);
+    let unbond_amount = Uint128::new(500);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(&mut deps, owner, token_contract, validator.address.clone());
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let bond_msg = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+
+    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
+
+    //set bob's balance to 10 in token contract
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg).unwrap();
+    assert_eq!(2, res.messages.len());
+
+    set_delegation(
+        &mut deps.querier,
+        validator.clone(),
+        bond_amount.u128(),
+        "uluna",
+    );
+
+    let res = execute_unbond(deps.as_mut(), mock_env(), info, unbond_amount, bob.clone()).unwrap();
+    assert_eq!(1, res.messages.len());
+
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9500))])]);
+
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(0),
+        },
+    )]);
+
+    let mut env = mock_env();
+    let info = mock_info(&bob, &[]);
+    //set the block time 30 seconds from now.
+
+    env.block.time = env.block.time.plus_seconds(31);
     // trigger undelegation message
-    assert!(wdraw_unbonded_res.is_err());
+    let res = execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        unbond_amount,
+        bob.clone(),
+    )
+    .unwrap();
+    assert_eq!(2, res.messages.len());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9000))])]);
+
+    // slashing
+    set_delegation(
+        &mut deps.querier,
+        validator,
+        bond_amount.u128() - 2000,
+        "uluna",
+    );
+
+    let res = execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        unbond_amount,
+        bob.clone(),
+    )
+    .unwrap();
+    assert_eq!(1, res.messages.len());
+
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(8500))])]);
+
+    env.block.time = env.block.time.plus_seconds(31);
+
+    let res = execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        unbond_amount,
+        bob.clone(),
+    )
+    .unwrap();
+    assert_eq!(2, res.messages.len());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(8000))])]);
+
+    // fabricate balance of the hub contract
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(2200),
+        },
+    )]);
+
+    env.block.time = env.block.time.plus_seconds(120);
+    let wdraw_unbonded_msg = ExecuteMsg::WithdrawUnbonded { recipient: None };
+    let success_res = execute(deps.as_mut(), env, info, wdraw_unbonded_msg).unwrap();
+
+    assert_eq!(success_res.messages.len(), 1);
+
+    let all_batches = AllHistory {
+        start_from: None,
+        limit: None,
+    };
+    let res: AllHistoryResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), all_batches).unwrap()).unwrap();
+    assert_eq!(res.history[0].amount, Uint128::new(1000));
+    assert_eq!(res.history[0].withdraw_rate.to_string(), "1.164");
+    assert!(res.history[0].released);
+    assert_eq!(res.history[0].batch_id, 1);
+    assert_eq!(res.history[1].amount, Uint128::new(1000));
+    assert_eq!(res.history[1].withdraw_rate.to_string(), "1.033");
+    assert!(res.history[1].released);
+    assert_eq!(res.history[1].batch_id, 2);
+
+    let expected = (res.history[0].withdraw_rate * res.history[0].amount)
+        + res.history[1].withdraw_rate * res.history[1].amount;
+    let sent_message = &success_res.messages[0].msg;
+    match sent_message {
+        CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+            assert_eq!(to_address, &bob);
+            assert_eq!(amount[0].amount, expected)
+        }
+
+        _ => panic!("Unexpected message: {:?}", sent_message),
+    }
+
+    // there should not be any result
+    let withdrawable = WithdrawableUnbonded { address: bob };
+    let query_with: WithdrawableUnbondedResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), withdrawable).unwrap()).unwrap();
+    assert_eq!(query_with.withdrawable, Uint128::new(0));
+}
+
+/// Covers if the state/parameters storage is updated to the given value,
+/// who sends the message, and if
+/// RewardUpdateDenom message is sent to the reward contract
+#[test]
+pub fn test_update_params() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    //test with no swap denom.
+    let update_prams = UpdateParams {
+        epoch_period: Some(20),
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(&mut deps, owner, token_contract, validator.address);
+
+    let invalid_info = mock_info("invalid", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        invalid_info,
+        update_prams.clone(),
+    );
+    assert_eq!(res.unwrap_err(), HubError::NotAdmin {});
+    let creator_info = mock_info("owner1", &[]);
+    let res = execute(deps.as_mut(), mock_env(), creator_info, update_prams).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let params: Parameters =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Parameters {}).unwrap()).unwrap();
+    assert_eq!(params.epoch_period, 20);
+    assert_eq!(params.underlying_coin_denom, "uluna");
+    assert_eq!(params.unbonding_period, 2);
+    assert_eq!(params.peg_recovery_fee, Decimal::zero());
+    assert_eq!(params.er_threshold, Decimal::one());
+
+    //test with some swap_denom.
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: Some(3),
+        peg_recovery_fee: Some(Decimal::one()),
+        er_threshold: Some(Decimal::zero()),
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+
+    //the result must be 1
+    let creator_info = mock_info("owner1", &[]);
+    let res = execute(deps.as_mut(), mock_env(), creator_info, update_prams).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let params: Parameters =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Parameters {}).unwrap()).unwrap();
+    assert_eq!(params.epoch_period, 20);
+    assert_eq!(params.underlying_coin_denom, "uluna");
+    assert_eq!(params.unbonding_period, 3);
+    assert_eq!(params.peg_recovery_fee, Decimal::one());
+    assert_eq!(params.er_threshold, Decimal::zero());
+}
+
+/// Covers if the optional parameters can be unset again after being set
+#[test]
+pub fn proper_unset_params() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        "token".to_string(),
+        validator.address,
+    );
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: Some(Decimal::percent(200)),
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: Some(5),
+        unbond_return_denom: Some("uusd".to_string()),
+        fee_free_until: Some(100),
+        max_validator_commission: Some(Decimal::percent(10)),
+        instant_redeem_fee: Some(Decimal::percent(1)),
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_prams).unwrap();
+
+    let unset = ExecuteMsg::UnsetParams {
+        params: vec![
+            OptionalParam::MaxExchangeRate,
+            OptionalParam::MaxWithdrawsPerUpdate,
+            OptionalParam::UnbondReturnDenom,
+            OptionalParam::FeeFreeUntil,
+            OptionalParam::MaxValidatorCommission,
+            OptionalParam::InstantRedeemFee,
+        ],
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("invalid", &[]),
+        unset.clone(),
+    );
+    assert_eq!(res.unwrap_err(), HubError::NotAdmin {});
+
+    // only the given parameters are unset
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::UnsetParams {
+            params: vec![OptionalParam::MaxExchangeRate],
+        },
+    )
+    .unwrap();
+    assert_eq!(res.attributes, vec![attr("action", "unset_params")]);
+    let params: Parameters =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Parameters {}).unwrap()).unwrap();
+    assert_eq!(params.max_exchange_rate, None);
+    assert_eq!(params.max_withdraws_per_update, Some(5));
+
+    execute(deps.as_mut(), mock_env(), owner_info, unset).unwrap();
+    let params: Parameters =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Parameters {}).unwrap()).unwrap();
+    assert_eq!(params.max_exchange_rate, None);
+    assert_eq!(params.max_withdraws_per_update, None);
+    assert_eq!(params.unbond_return_denom, None);
+    assert_eq!(params.fee_free_until, None);
+    assert_eq!(params.max_validator_commission, None);
+    assert_eq!(params.instant_redeem_fee, None);
+}
+
+/// Bond `bond_amount` while the delegations back the supply of 1000000 with `delegated`
+/// and return the minted amount, the peg recovery fee is 0.1%.
+fn bond_below_threshold(delegated: u128, bond_amount: u128, round_up_peg_fee: bool) -> Uint128 {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: Some(Decimal::permille(1)),
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: Some(round_up_peg_fee),
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner1", &[]),
+        update_prams,
+    )
+    .unwrap();
+
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(1000000u128))])]);
+    set_delegation(&mut deps.querier, validator.clone(), delegated, "uluna");
+
+    let bond_msg = ExecuteMsg::Bond {
+        validator: validator.address,
+    };
+    let info = mock_info(&bob, &[coin(bond_amount, "uluna")]);
+    let res = execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
+
+    match &res.messages[1].msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+            match from_binary::<Cw20ExecuteMsg>(msg).unwrap() {
+                Mint { amount, .. } => amount,
+                other => panic!("Unexpected message: {:?}", other),
+            }
+        }
+        other => panic!("Unexpected message: {:?}", other),
+    }
+}
+
+/// Covers the peg recovery fee of a bond just below the threshold, when the
+/// maximum fee caps the required fee and when the required fee is lower,
+/// with both rounding directions of the maximum fee.
+#[test]
+pub fn proper_peg_fee_rounding() {
+    // exchange rate 0.999, 1000 / 0.999 mints 1001 before the fee,
+    // the required fee is 1001, the maximum fee 1.001 is rounded to 1 or 2
+    assert_eq!(
+        bond_below_threshold(999000, 1000, false),
+        Uint128::new(1000)
+    );
+    assert_eq!(bond_below_threshold(999000, 1000, true), Uint128::new(999));
+
+    // exchange rate 0.999999, 100000 / 0.999999 mints 100000 before the fee,
+    // the maximum fee is exactly 100, the required fee is 1
+    assert_eq!(
+        bond_below_threshold(999999, 100000, false),
+        Uint128::new(99999)
+    );
+    assert_eq!(
+        bond_below_threshold(999999, 100000, true),
+        Uint128::new(99999)
+    );
+}
+
+/// Covers if peg recovery is applied (in "bond", "unbond",
+/// and "withdraw_unbonded" messages) in case of a slashing event
+#[test]
+pub fn proper_recovery_fee() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: Some(Decimal::from_ratio(Uint128::new(1), Uint128::new(1000))),
+        er_threshold: Some(Decimal::from_ratio(Uint128::new(99), Uint128::new(100))),
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    let bond_amount = Uint128::new(1000000u128);
+    let unbond_amount = Uint128::new(100000u128);
+
+    init(
+        &mut deps,
+        owner,
+        token_contract.clone(),
+        validator.address.clone(),
+    );
+
+    let creator_info = mock_info("owner1", &[]);
+    let res = execute(deps.as_mut(), mock_env(), creator_info, update_prams).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let get_params = QueryMsg::Parameters {};
+    let parmas: Parameters =
+        from_binary(&query(deps.as_ref(), mock_env(), get_params).unwrap()).unwrap();
+    assert_eq!(parmas.epoch_period, 30);
+    assert_eq!(parmas.underlying_coin_denom, "uluna");
+    assert_eq!(parmas.unbonding_period, 2);
+    assert_eq!(parmas.peg_recovery_fee.to_string(), "0.001");
+    assert_eq!(parmas.er_threshold.to_string(), "0.99");
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let bond_msg = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+
+    //this will set the balance of the user in token contract
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+
+    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
+
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg).unwrap();
+    assert_eq!(2, res.messages.len());
+
+    set_delegation(&mut deps.querier, validator.clone(), 900000, "uluna");
+
+    let report_slashing = CheckSlashing {};
+    let res = execute(deps.as_mut(), mock_env(), info, report_slashing).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let ex_rate = QueryMsg::State {};
+    let query_exchange_rate: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), ex_rate).unwrap()).unwrap();
+    assert_eq!(query_exchange_rate.exchange_rate.to_string(), "0.9");
+
+    //Bond again to see the applied result
+    let bob = "bob".to_string();
+    let bond_msg = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+
+    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
+
+    let res = execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
+    let mint_amount = decimal_division(
+        bond_amount,
+        Decimal::from_ratio(Uint128::new(9), Uint128::new(10)),
+    );
+    let max_peg_fee = mint_amount * parmas.peg_recovery_fee;
+    let required_peg_fee = ((bond_amount + mint_amount + Uint128::zero())
+        .checked_sub(Uint128::new(900000) + bond_amount))
+    .unwrap();
+    let peg_fee = Uint128::min(max_peg_fee, required_peg_fee);
+    let mint_amount_with_fee = (mint_amount.checked_sub(peg_fee)).unwrap();
+
+    let mint_msg = &res.messages[1].msg;
+    match mint_msg {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: _,
+            msg,
+            funds: _,
+        }) => assert_eq!(
+            msg,
+            &to_binary(&Mint {
+                recipient: bob.clone(),
+                amount: mint_amount_with_fee
+            })
+            .unwrap()
+        ),
+        _ => panic!("Unexpected message: {:?}", mint_msg),
+    }
+
+    // check unbond message
+    let unbond = Unbond {};
+    let receive = Receive(Cw20ReceiveMsg {
+        sender: bob.clone(),
+        amount: unbond_amount,
+        msg: to_binary(&unbond).unwrap(),
+    });
+
+    let new_balance = bond_amount.checked_sub(unbond_amount).unwrap();
+
+    let mut token_env = mock_env();
+    let token_info = mock_info(&token_contract, &[]);
+    let res = execute(
+        deps.as_mut(),
+        token_env.clone(),
+        token_info.clone(),
+        receive,
+    )
+    .unwrap();
+    assert_eq!(1, res.messages.len());
+
+    //check current batch
+    let bonded_with_fee =
+        unbond_amount * Decimal::from_ratio(Uint128::new(999), Uint128::new(1000));
+    let current_batch = QueryMsg::CurrentBatch {};
+    let query_batch: CurrentBatchResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), current_batch).unwrap()).unwrap();
+    assert_eq!(query_batch.id, 1);
+    assert_eq!(query_batch.requested_with_fee, bonded_with_fee);
+
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &new_balance)])]);
+
+    token_env.block.time = token_env.block.time.plus_seconds(60);
+
+    let second_unbond = Unbond {};
+    let receive = Receive(Cw20ReceiveMsg {
+        sender: bob.clone(),
+        amount: unbond_amount,
+        msg: to_binary(&second_unbond).unwrap(),
+    });
+    let res = execute(
+        deps.as_mut(),
+        token_env.clone(),
+        token_info.clone(),
+        receive,
+    )
+    .unwrap();
+    assert_eq!(2, res.messages.len());
+
+    let ex_rate = QueryMsg::State {};
+    let query_exchange_rate: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), ex_rate).unwrap()).unwrap();
+    let new_exchange = query_exchange_rate.exchange_rate;
+
+    let expected = bonded_with_fee + bonded_with_fee;
+    let undelegate_message = &res.messages[0].msg;
+    match undelegate_message {
+        CosmosMsg::Staking(StakingMsg::Undelegate {
+            validator: val,
+            amount,
+        }) => {
+            assert_eq!(&validator.address, val);
+            assert_eq!(amount.amount, expected * new_exchange);
+        }
+        _ => panic!("Unexpected message: {:?}", mint_msg),
+    }
+
+    //got slashed during unbonding
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(161870),
+        },
+    )]);
+
+    token_env.block.time = token_env.block.time.plus_seconds(90);
+    //check withdrawUnbonded message
+    let withdraw_unbond_msg = ExecuteMsg::WithdrawUnbonded { recipient: None };
+    let wdraw_unbonded_res = execute(
+        deps.as_mut(),
+        token_env,
+        mock_info(&bob, &[]),
+        withdraw_unbond_msg,
+    )
+    .unwrap();
+    assert_eq!(wdraw_unbonded_res.messages.len(), 1);
+
+    let sent_message = &wdraw_unbonded_res.messages[0].msg;
+    let expected = ((expected
+        * new_exchange
+        * Decimal::from_ratio(Uint128::new(161870), expected * new_exchange))
+    .checked_sub(Uint128::new(1)))
+    .unwrap();
+    match sent_message {
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: _,
+            amount,
+        }) => {
+            assert_eq!(amount[0].amount, expected);
+        }
+
+        _ => panic!("Unexpected message: {:?}", sent_message),
+    }
+
+    let all_batches = AllHistory {
+        start_from: None,
+        limit: None,
+    };
+    let res: AllHistoryResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), all_batches).unwrap()).unwrap();
+    // amount should be 99 + 99 since we store the requested amount with peg fee applied.
+    assert_eq!(res.history[0].amount, bonded_with_fee + bonded_with_fee);
+    assert_eq!(res.history[0].applied_exchange_rate, new_exchange);
+    assert_eq!(
+        res.history[0].withdraw_rate,
+        Decimal::from_ratio(Uint128::new(161869), bonded_with_fee + bonded_with_fee)
+    );
+    assert!(res.history[0].released);
+    assert_eq!(res.history[0].batch_id, 1);
+}
+
+/// Covers if the storage affected by update_config are updated properly
+#[test]
+pub fn proper_update_config() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let new_owner = "new_owner".to_string();
+    let invalid_owner = "invalid_owner".to_string();
+    let token_contract = "token".to_string();
+    let protocol_fee_collector = "fee_collector".to_string();
+
+    init(&mut deps, owner.clone(), token_contract, validator.address);
+
+    let admin = Admin {};
+    let query_admin: AdminResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), admin).unwrap()).unwrap();
+    //make sure the other configs are still the same.
+    assert_eq!(query_admin.admin.unwrap(), owner);
+
+    // only the owner can call this message
+    let update_admin = UpdateAdmin {
+        admin: new_owner.clone(),
+    };
+
+    let info = mock_info(&invalid_owner, &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, update_admin);
+    assert_eq!(res.unwrap_err(), HubError::NotAdmin {});
+
+    // change the owner
+    let update_admin = UpdateAdmin {
+        admin: new_owner.clone(),
+    };
+
+    let info = mock_info(&owner, &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, update_admin).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    // let config = CONFIG.load(&deps.storage).unwrap();
+    let new_owner_raw = new_owner.clone();
+    let admin = ADMIN.get(deps.as_ref()).unwrap().unwrap();
+    assert_eq!(new_owner_raw, admin);
+
+    // new owner can send the owner related messages
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+
+    let new_owner_info = mock_info(&new_owner, &[]);
+    let res = execute(deps.as_mut(), mock_env(), new_owner_info, update_prams).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    //previous owner cannot send this message
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+
+    let new_owner_info = mock_info(&owner, &[]);
+    let res = execute(deps.as_mut(), mock_env(), new_owner_info, update_prams);
+    assert_eq!(res.unwrap_err(), HubError::NotAdmin {});
+
+    let update_config = UpdateConfig {
+        token_contract: Some("new token".to_string()),
+        protocol_fee_collector: None,
+        reward_validator: None,
+        rewards_contract: None,
+    };
+    //cannot register the new token
+    let new_owner_info = mock_info(&new_owner, &[]);
+    let res = execute(deps.as_mut(), mock_env(), new_owner_info, update_config).unwrap_err();
+    assert_eq!(res, HubError::TokenAlreadyRegistered {});
+
+    let config = QueryMsg::Config {};
+    let config_query: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), config).unwrap()).unwrap();
+    assert_eq!(config_query.token_contract.unwrap(), "token".to_string());
+
+    let admin = Admin {};
+    let query_admin: AdminResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), admin).unwrap()).unwrap();
+    //make sure the other configs are still the same.
+    assert_eq!(query_admin.admin.unwrap(), new_owner);
+
+    let update_config = UpdateConfig {
+        token_contract: None,
+        protocol_fee_collector: Some(protocol_fee_collector),
+        reward_validator: None,
+        rewards_contract: None,
+    };
+    let new_owner_info = mock_info(&new_owner, &[]);
+    let res = execute(deps.as_mut(), mock_env(), new_owner_info, update_config).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let config = QueryMsg::Config {};
+    let config_query: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), config).unwrap()).unwrap();
+    assert_eq!(
+        config_query.protocol_fee_collector.unwrap(),
+        "fee_collector".to_string()
+    );
+
+    let admin = Admin {};
+    let query_admin: AdminResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), admin).unwrap()).unwrap();
+    //make sure the other configs are still the same.
+    assert_eq!(query_admin.admin.unwrap(), new_owner);
+}
+
+#[test]
+pub fn proper_protocol_fee() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: Some(Decimal::from_ratio(Uint128::new(1), Uint128::new(1000))),
+        er_threshold: Some(Decimal::from_ratio(Uint128::new(99), Uint128::new(100))),
+        protocol_fee: Some(Decimal::from_ratio(Uint128::new(1), Uint128::new(100))),
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    let protocol_fee_collector = "fee_collector".to_string();
+
+    let bond_amount = Uint128::new(1000000u128);
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+
+    let creator_info = mock_info("owner1", &[]);
+    let res = execute(deps.as_mut(), mock_env(), creator_info, update_prams).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let get_params = QueryMsg::Parameters {};
+    let parmas: Parameters =
+        from_binary(&query(deps.as_ref(), mock_env(), get_params).unwrap()).unwrap();
+    assert_eq!(parmas.epoch_period, 30);
+    assert_eq!(parmas.underlying_coin_denom, "uluna");
+    assert_eq!(parmas.unbonding_period, 2);
+    assert_eq!(parmas.peg_recovery_fee.to_string(), "0.001");
+    assert_eq!(parmas.er_threshold.to_string(), "0.99");
+    assert_eq!(parmas.protocol_fee.to_string(), "0.01");
+
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let bond_msg = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+
+    //this will set the balance of the user in token contract
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+
+    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
+
+    let res = execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
+    assert_eq!(2, res.messages.len());
+
+    // set balance before executing the exchange rate update
+    set_delegation(
+        &mut deps.querier,
+        validator.clone(),
+        bond_amount.u128(),
+        "uluna",
+    );
+
+    let reward_msg = ExecuteMsg::UpdateGlobalIndex {};
+
+    let info = mock_info(&owner, &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, reward_msg).unwrap();
+    assert_eq!(2, res.messages.len());
+
+    let last_index_query = QueryMsg::State {};
+    let last_modification: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), last_index_query).unwrap()).unwrap();
+    assert_eq!(
+        &last_modification.last_index_modification,
+        &mock_env().block.time.seconds()
+    );
+
+    let withdraw = &res.messages[0].msg;
+    match withdraw {
+        CosmosMsg::Distribution(DistributionMsg::WithdrawDelegatorReward { validator: val }) => {
+            assert_eq!(val, &validator.address);
+        }
+        _ => panic!("Unexpected message: {:?}", withdraw),
+    }
+
+    let swap = &res.messages[1].msg;
+    match swap {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            msg: _,
+            funds: _,
+        }) => {
+            assert_eq!(contract_addr, "rewards_contract");
+        }
+        _ => panic!("Unexpected message: {:?}", swap),
+    }
+
+    // need to set the protocol fee collector address
+    let register_msg = UpdateConfig {
+        token_contract: None,
+        protocol_fee_collector: Some(protocol_fee_collector.clone()),
+        reward_validator: None,
+        rewards_contract: None,
+    };
+
+    let owner_info = mock_info("owner1", &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info, register_msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let query_msg = QueryMsg::Config {};
+
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap();
+
+    assert_eq!(
+        config.protocol_fee_collector.unwrap(),
+        protocol_fee_collector
+    );
+
+    // set balance before executing the exchange rate update
+    let new_balance = Uint128::new(1100);
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: new_balance,
+        },
+    )]);
+
+    let update_exchange_rate = ExecuteMsg::UpdateExchangeRate {};
+
+    let info = mock_info("rewards_contract", &[Coin::new(100, "uluna")]);
+    let res = execute(deps.as_mut(), mock_env(), info, update_exchange_rate).unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "fee_collector".to_string(),
+            amount: vec![Coin::new(1u128, "uluna")],
+        })),
+    );
+}
+
+/// Covers if the protocol fee of a fractional amount is rounded down
+/// by default and to the nearest unit with round_protocol_fee.
+#[test]
+pub fn proper_round_protocol_fee() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &INITIAL_DEPOSIT_AMOUNT)],
+    )]);
+    set_delegation(
+        &mut deps.querier,
+        validator.clone(),
+        INITIAL_DEPOSIT_AMOUNT.u128(),
+        "uluna",
+    );
+
+    let update_config = UpdateConfig {
+        token_contract: None,
+        protocol_fee_collector: Some("fee_collector".to_string()),
+        reward_validator: None,
+        rewards_contract: None,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_config).unwrap();
+
+    // a 10% fee of 15 is 1.5
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: Some(Decimal::percent(10)),
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_prams).unwrap();
+
+    for (round_protocol_fee, fee) in [(false, 1u128), (true, 2u128)] {
+        let update_prams = UpdateParams {
+            epoch_period: None,
+            unbonding_period: None,
+            peg_recovery_fee: None,
+            er_threshold: None,
+            protocol_fee: None,
+            min_undelegate_per_validator: None,
+            max_validators: None,
+            max_exchange_rate: None,
+            max_requests_per_batch: None,
+            track_positive_drift: None,
+            round_protocol_fee: Some(round_protocol_fee),
+            accrue_protocol_fee: None,
+            round_up_peg_fee: None,
+            min_compound_interval: None,
+            restake_dust_threshold: None,
+            min_protocol_fee_transfer: None,
+            max_withdraws_per_update: None,
+            unbond_return_denom: None,
+            fee_free_until: None,
+            max_validator_commission: None,
+            instant_redeem_fee: None,
+            max_open_unbond_batches_per_user: None,
+            unbond_cooloff: None,
+            liquidity_reserve_ratio: None,
+        };
+        execute(deps.as_mut(), mock_env(), owner_info.clone(), update_prams).unwrap();
+
+        let info = mock_info("rewards_contract", &[Coin::new(15, "uluna")]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::UpdateExchangeRate {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "fee_collector".to_string(),
+                    amount: vec![Coin::new(fee, "uluna")],
+                })),
+                SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+                    validator: validator.address.clone(),
+                    amount: coin(15 - fee, "uluna"),
+                })),
+            ]
+        );
+    }
+}
+
+/// Covers if the protocol fee accrues on the hub across compounds
+/// and a single claim by the collector drains it.
+#[test]
+pub fn proper_accrue_protocol_fee() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &INITIAL_DEPOSIT_AMOUNT)],
+    )]);
+    set_delegation(
+        &mut deps.querier,
+        validator.clone(),
+        INITIAL_DEPOSIT_AMOUNT.u128(),
+        "uluna",
+    );
+
+    let update_config = UpdateConfig {
+        token_contract: None,
+        protocol_fee_collector: Some("fee_collector".to_string()),
+        reward_validator: None,
+        rewards_contract: None,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_config).unwrap();
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: Some(Decimal::percent(10)),
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: Some(true),
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    execute(deps.as_mut(), mock_env(), owner_info, update_prams).unwrap();
+
+    // the fee is kept on the hub
+    for _ in 0..2 {
+        let info = mock_info("rewards_contract", &[Coin::new(100, "uluna")]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::UpdateExchangeRate {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+                validator: validator.address.clone(),
+                amount: coin(90, "uluna"),
+            }))]
+        );
+    }
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.prev_hub_balance, Uint128::new(20));
+    assert_eq!(state.total_rewards_compounded, Uint128::new(180));
+
+    // only the collector can claim
+    let invalid_info = mock_info("invalid", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        invalid_info,
+        ExecuteMsg::ClaimProtocolFee {},
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::Unauthorized {});
+
+    let collector_info = mock_info("fee_collector", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        collector_info.clone(),
+        ExecuteMsg::ClaimProtocolFee {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "fee_collector".to_string(),
+            amount: vec![Coin::new(20, "uluna")],
+        }))]
+    );
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.prev_hub_balance, Uint128::zero());
+
+    // nothing is left to claim
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        collector_info,
+        ExecuteMsg::ClaimProtocolFee {},
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::NoAccruedFee {});
+}
+/// Covers if protocol fees below min_protocol_fee_transfer accrue on the hub
+/// and are sent together once the accrued fee crosses the minimum.
+#[test]
+pub fn proper_min_protocol_fee_transfer() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &INITIAL_DEPOSIT_AMOUNT)],
+    )]);
+    set_delegation(
+        &mut deps.querier,
+        validator.clone(),
+        INITIAL_DEPOSIT_AMOUNT.u128(),
+        "uluna",
+    );
+
+    let update_config = UpdateConfig {
+        token_contract: None,
+        protocol_fee_collector: Some("fee_collector".to_string()),
+        reward_validator: None,
+        rewards_contract: None,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_config).unwrap();
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: Some(Decimal::percent(10)),
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: Some(Uint128::new(25)),
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    execute(deps.as_mut(), mock_env(), owner_info, update_prams).unwrap();
+
+    // fees of 10 stay below the minimum twice
+    for _ in 0..2 {
+        let info = mock_info("rewards_contract", &[Coin::new(100, "uluna")]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::UpdateExchangeRate {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+                validator: validator.address.clone(),
+                amount: coin(90, "uluna"),
+            }))]
+        );
+    }
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.prev_hub_balance, Uint128::new(20));
+
+    // the third fee crosses the minimum and the whole accrued fee is sent
+    let info = mock_info("rewards_contract", &[Coin::new(100, "uluna")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "fee_collector".to_string(),
+                amount: vec![Coin::new(30, "uluna")],
+            })),
+            SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+                validator: validator.address,
+                amount: coin(90, "uluna"),
+            })),
+        ]
+    );
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.prev_hub_balance, Uint128::zero());
+
+    // nothing is left to claim
+    let collector_info = mock_info("fee_collector", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        collector_info,
+        ExecuteMsg::ClaimProtocolFee {},
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::NoAccruedFee {});
+}
+
+#[test]
+pub fn proper_set_fee_collectors() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(&mut deps, owner.clone(), token_contract, validator.address);
+
+    let collectors = vec![
+        ("collector1".to_string(), Decimal::percent(70)),
+        ("collector2".to_string(), Decimal::percent(30)),
+    ];
+
+    // only the admin can set the fee collectors
+    let set_collectors = ExecuteMsg::SetFeeCollectors {
+        collectors: collectors.clone(),
+    };
+    let invalid_info = mock_info("invalid", &[]);
+    let res = execute(deps.as_mut(), mock_env(), invalid_info, set_collectors).unwrap_err();
+    assert_eq!(res, HubError::NotAdmin {});
+
+    let set_collectors = ExecuteMsg::SetFeeCollectors {
+        collectors: collectors.clone(),
+    };
+    let owner_info = mock_info(&owner, &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info, set_collectors).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.protocol_fee_collectors, collectors);
+
+    // the weights must sum to one
+    let set_collectors = ExecuteMsg::SetFeeCollectors {
+        collectors: vec![
+            ("collector1".to_string(), Decimal::percent(50)),
+            ("collector3".to_string(), Decimal::percent(40)),
+        ],
+    };
+    let owner_info = mock_info(&owner, &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info, set_collectors).unwrap_err();
+    assert_eq!(
+        res,
+        HubError::Std(StdError::generic_err(
+            "The weights of the fee collectors must sum to one"
+        ))
+    );
+
+    // the previous set must be kept
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.protocol_fee_collectors, collectors);
+}
+
+/// Covers if the protocol fee of a compound is split by weight
+/// between the fee collectors.
+#[test]
+pub fn proper_protocol_fee_split() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    let bob = "bob".to_string();
+    let bond_amount = Uint128::new(1000000u128);
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: Some(Decimal::percent(10)),
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info, update_prams).unwrap();
+
+    let set_collectors = ExecuteMsg::SetFeeCollectors {
+        collectors: vec![
+            ("collector1".to_string(), Decimal::percent(70)),
+            ("collector2".to_string(), Decimal::percent(30)),
+        ],
+    };
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info, set_collectors).unwrap();
+
+    do_bond(deps.as_mut(), bob.clone(), bond_amount, validator.clone());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+    set_delegation(
+        &mut deps.querier,
+        validator.clone(),
+        bond_amount.u128(),
+        "uluna",
+    );
+
+    // a fee of 100 out of 1000 rewards is split 70/30
+    let info = mock_info("rewards_contract", &[Coin::new(1000, "uluna")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "collector1".to_string(),
+            amount: vec![Coin::new(70u128, "uluna")],
+        }))
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "collector2".to_string(),
+            amount: vec![Coin::new(30u128, "uluna")],
+        }))
+    );
+    assert_eq!(
+        res.messages[2],
+        SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: validator.address,
+            amount: Coin::new(900u128, "uluna"),
+        }))
+    );
+}
+
+/// Covers if UpdateGlobalIndex is rejected within min_compound_interval
+/// of the last index modification.
+#[test]
+pub fn proper_min_compound_interval() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator);
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: Some(10),
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info, update_prams).unwrap();
+
+    // the interval is counted from the instantiation
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(10);
+    let info = mock_info("keeper", &[]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+
+    env.block.time = env.block.time.plus_seconds(5);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::CompoundTooSoon {});
+
+    env.block.time = env.block.time.plus_seconds(5);
+    execute(deps.as_mut(), env, info, ExecuteMsg::UpdateGlobalIndex {}).unwrap();
+}
+
+/// Covers if the idle balance above the dust threshold is delegated
+/// to the least staked validator and backs the existing supply.
+#[test]
+pub fn proper_restake_idle() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_register_validator(deps.as_mut(), validator2.clone());
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: Some(Uint128::new(100)),
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info, update_prams).unwrap();
+
+    set_delegation_query(
+        &mut deps.querier,
+        &[
+            sample_delegation(validator.address.clone(), coin(600000, "uluna")),
+            sample_delegation(validator2.address.clone(), coin(400000, "uluna")),
+        ],
+        &[validator, validator2.clone()],
+    );
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(1500),
+        },
+    )]);
+
+    let info = mock_info("keeper", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::RestakeIdle {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: validator2.address,
+            amount: coin(1400, "uluna"),
+        }))]
+    );
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_bond_amount, Uint128::new(1001400));
+    assert_eq!(state.prev_hub_balance, Uint128::new(100));
+
+    // only the dust buffer is left
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(100),
+        },
+    )]);
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::RestakeIdle {}).unwrap_err();
+    assert_eq!(
+        res,
+        HubError::Std(StdError::generic_err(
+            "No idle balance above the dust threshold"
+        ))
+    );
+}
+
+/// Covers if the rewards contract can be changed, moving the withdraw address
+/// and the permission of UpdateExchangeRate to the new contract.
+#[test]
+pub fn proper_update_rewards_contract() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    let bob = "bob".to_string();
+    let bond_amount = Uint128::new(1000000u128);
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_bond(deps.as_mut(), bob.clone(), bond_amount, validator.clone());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+    set_delegation(
+        &mut deps.querier,
+        validator.clone(),
+        bond_amount.u128(),
+        "uluna",
+    );
+
+    let update_config = UpdateConfig {
+        token_contract: None,
+        protocol_fee_collector: None,
+        reward_validator: None,
+        rewards_contract: Some("new_rewards".to_string()),
+    };
+    let owner_info = mock_info(&owner, &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info, update_config).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Distribution(
+            DistributionMsg::SetWithdrawAddress {
+                address: "new_rewards".to_string(),
+            }
+        ))]
+    );
+
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.rewards_contract, Some("new_rewards".to_string()));
+
+    // the previous rewards contract is no longer authorized
+    let info = mock_info("rewards_contract", &[Coin::new(1000, "uluna")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::Unauthorized {});
+
+    let info = mock_info("new_rewards", &[Coin::new(1000, "uluna")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: validator.address,
+            amount: coin(1000, "uluna"),
+        }))]
+    );
+}
+
+/// Covers if the admin can recover a cw20 token sent to the hub
+/// but not the registered basset token.
+#[test]
+pub fn proper_recover_token() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(&mut deps, owner.clone(), token_contract, validator.address);
+
+    // only the admin can recover tokens
+    let recover = ExecuteMsg::RecoverToken {
+        token_addr: "airdrop".to_string(),
+        amount: Uint128::new(100),
+        recipient: "treasury".to_string(),
+    };
+    let invalid_info = mock_info("invalid", &[]);
+    let res = execute(deps.as_mut(), mock_env(), invalid_info, recover.clone()).unwrap_err();
+    assert_eq!(res, HubError::NotAdmin {});
+
+    let owner_info = mock_info(&owner, &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), recover).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "airdrop".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "treasury".to_string(),
+                amount: Uint128::new(100),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))]
+    );
+
+    // the basset token can not be recovered
+    let recover = ExecuteMsg::RecoverToken {
+        token_addr: "token".to_string(),
+        amount: Uint128::new(100),
+        recipient: "treasury".to_string(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), owner_info, recover).unwrap_err();
+    assert_eq!(
+        res,
+        HubError::Std(StdError::generic_err(
+            "The basset token can not be recovered"
+        ))
+    );
+}
+
+/// Covers if UpdateGlobalIndex withdraws from a rotating window of delegations
+#[test]
+pub fn proper_max_withdraws_per_update() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        deps.borrow_mut(),
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+
+    let validators: Vec<Validator> = (1..=5)
+        .map(|i| sample_validator(format!("validator{}", i)))
+        .collect();
+    let delegations: Vec<FullDelegation> = validators
+        .iter()
+        .map(|v| sample_delegation(v.address.clone(), coin(10, "uluna")))
+        .collect();
+    set_delegation_query(&mut deps.querier, &delegations, &validators);
+
+    let update_prams = |max_withdraws_per_update| UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        update_prams(Some(0)),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        HubError::Std(StdError::generic_err(
+            "The max withdraws per update must be greater than zero"
+        ))
+    );
+    execute(deps.as_mut(), mock_env(), owner_info, update_prams(Some(2))).unwrap();
+
+    let expected = [
+        (["validator1", "validator2"], 2u64),
+        (["validator3", "validator4"], 4u64),
+        (["validator5", "validator1"], 1u64),
+    ];
+    for (withdrawn, cursor) in expected.iter() {
+        let info = mock_info("addr0001", &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::UpdateGlobalIndex {},
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 3);
+        for (i, val) in withdrawn.iter().enumerate() {
+            assert_eq!(
+                res.messages[i].msg,
+                CosmosMsg::Distribution(DistributionMsg::WithdrawDelegatorReward {
+                    validator: val.to_string(),
+                })
+            );
+        }
+        assert_eq!(WITHDRAW_CURSOR.load(&deps.storage).unwrap(), *cursor);
+    }
+}
+
+/// Covers if the execute errors can be matched by their typed variants
+#[test]
+pub fn proper_typed_errors() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        deps.borrow_mut(),
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+
+    let bob_info = mock_info("bob", &[coin(10, "uluna")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        bob_info.clone(),
+        ExecuteMsg::Pause {},
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::NotAdmin {});
+
+    let bond = ExecuteMsg::Bond {
+        validator: "invalid".to_string(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), bob_info, bond).unwrap_err();
+    assert_eq!(res, HubError::ValidatorNotWhitelisted {});
+
+    let bond = ExecuteMsg::Bond {
+        validator: validator.address,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        bond.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        res,
+        HubError::NoFundsToBond {
+            denom: "uluna".to_string()
+        }
+    );
+    assert_eq!(res.to_string(), "No uluna assets are provided to bond");
+
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info, ExecuteMsg::Pause {}).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[coin(10, "uluna")]),
+        bond,
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::Paused {});
+}
+
+/// Covers if the peg fee preview matches the fee charged by the following bond and unbond
+#[test]
+pub fn proper_peg_fee_preview() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract.clone(),
+        validator.address.clone(),
+    );
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: Some(Decimal::from_ratio(Uint128::new(1), Uint128::new(1000))),
+        er_threshold: Some(Decimal::from_ratio(Uint128::new(99), Uint128::new(100))),
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        update_prams,
+    )
+    .unwrap();
+
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let bond_amount = Uint128::new(1000000);
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+    do_bond(deps.as_mut(), bob.clone(), bond_amount, validator.clone());
+
+    // a slashing moves the exchange rate below the threshold
+    set_delegation(&mut deps.querier, validator.clone(), 900000, "uluna");
+    let info = mock_info(&bob, &[]);
+    execute(deps.as_mut(), mock_env(), info, CheckSlashing {}).unwrap();
+
+    let preview = QueryMsg::PegFeePreview {
+        action: BondOrUnbond::Bond,
+        amount: bond_amount,
+    };
+    let res: PegFeePreviewResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), preview).unwrap()).unwrap();
+    assert!(!res.fee.is_zero());
+
+    let bond_msg = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
+    let bond_res = execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
+    let mint_amount = decimal_division(bond_amount, Decimal::percent(90));
+    assert_eq!(
+        bond_res.messages[1].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token_contract.clone(),
+            msg: to_binary(&Mint {
+                recipient: bob.clone(),
+                amount: mint_amount - res.fee,
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+
+    let new_balance = bond_amount + mint_amount - res.fee;
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &new_balance)])]);
+    set_delegation(&mut deps.querier, validator, 1900000, "uluna");
+
+    let unbond_amount = Uint128::new(100000);
+    let preview = QueryMsg::PegFeePreview {
+        action: BondOrUnbond::Unbond,
+        amount: unbond_amount,
+    };
+    let res: PegFeePreviewResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), preview).unwrap()).unwrap();
+    assert!(!res.fee.is_zero());
+
+    let receive = Receive(Cw20ReceiveMsg {
+        sender: bob,
+        amount: unbond_amount,
+        msg: to_binary(&Unbond {}).unwrap(),
+    });
+    let token_info = mock_info(&token_contract, &[]);
+    execute(deps.as_mut(), mock_env(), token_info, receive).unwrap();
+
+    let current_batch: CurrentBatchResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::CurrentBatch {}).unwrap()).unwrap();
+    assert_eq!(current_batch.requested_with_fee, unbond_amount - res.fee);
+}
+
+/// Covers if a single batch history is returned by its id
+#[test]
+pub fn proper_batch_history() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address,
+    );
+
+    let now = mock_env().block.time.seconds();
+    for batch_id in 1..=3 {
+        store_unbond_history(
+            &mut deps.storage,
+            batch_id,
+            UnbondHistory {
+                batch_id,
+                time: now - 10 + batch_id,
+                amount: Uint128::new(batch_id as u128 * 10),
+                applied_exchange_rate: Decimal::one(),
+                withdraw_rate: Decimal::one(),
+                released: false,
+            },
+        )
+        .unwrap();
+    }
+
+    let batch_history = QueryMsg::BatchHistory { batch_id: 2 };
+    let res: UnbondHistory =
+        from_binary(&query(deps.as_ref(), mock_env(), batch_history).unwrap()).unwrap();
+    assert_eq!(
+        res,
+        UnbondHistory {
+            batch_id: 2,
+            time: now - 8,
+            amount: Uint128::new(20),
+            applied_exchange_rate: Decimal::one(),
+            withdraw_rate: Decimal::one(),
+            released: false,
+        }
+    );
+
+    let batch_history = QueryMsg::BatchHistory { batch_id: 4 };
+    let res = query(deps.as_ref(), mock_env(), batch_history).unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err("Burn requests not found for the specified time period")
+    );
+}
+
+/// Covers if the unbonded funds are withdrawn in the return denom override
+/// while the bond is made in the underlying coin
+#[test]
+pub fn proper_unbond_return_denom() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: Some("uwluna".to_string()),
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        update_prams,
+    )
+    .unwrap();
+
+    let bob = "bob".to_string();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+
+    // the bond is still delegated in the underlying coin
+    let bond = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+    let info = mock_info(&bob, &[coin(100, "uluna")]);
+    let res = execute(deps.as_mut(), mock_env(), info, bond).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: validator.address.clone(),
+            amount: coin(100, "uluna"),
+        })
+    );
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
+
+    let info = mock_info(&bob, &[]);
+    let mut env = mock_env();
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
+
+    // close the batch
+    env.block.time = env.block.time.plus_seconds(31);
+    let res = execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+
+    // the batch is matured and its funds have arrived
+    env.block.time = env.block.time.plus_seconds(3);
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(20),
+        },
+    )]);
+
+    let res = execute(
+        deps.as_mut(),
+        env,
+        info,
+        ExecuteMsg::WithdrawUnbonded { recipient: None },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: bob,
+            amount: vec![Coin::new(20u128, "uwluna")],
+        }))
+    );
+}
+
+/// Covers if the bond delegates before it mints, without a reply that could
+/// keep the mint of a failed delegation
+#[test]
+pub fn proper_bond_message_order() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let bond = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+    let info = mock_info(&bob, &[coin(100, "uluna")]);
+    let res = execute(deps.as_mut(), mock_env(), info, bond).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+                validator: validator.address,
+                amount: coin(100, "uluna"),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "token".to_string(),
+                msg: to_binary(&Mint {
+                    recipient: bob,
+                    amount: Uint128::new(100),
+                })
+                .unwrap(),
+                funds: vec![],
+            })),
+        ]
+    );
+    assert!(res
+        .messages
+        .iter()
+        .all(|msg| msg.reply_on == ReplyOn::Never));
+}
+
+/// Covers if no protocol fee is taken from a compound before fee_free_until
+/// and the configured fee is taken after it
+#[test]
+pub fn proper_fee_free_until() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    let bob = "bob".to_string();
+    let bond_amount = Uint128::new(1000000u128);
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let cutoff = mock_env().block.time.seconds() + 100;
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: Some(Decimal::percent(10)),
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: Some(cutoff),
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_prams).unwrap();
+
+    let update_config = UpdateConfig {
+        token_contract: None,
+        protocol_fee_collector: Some("fee_collector".to_string()),
+        reward_validator: None,
+        rewards_contract: None,
+    };
+    execute(deps.as_mut(), mock_env(), owner_info, update_config).unwrap();
+
+    do_bond(deps.as_mut(), bob.clone(), bond_amount, validator.clone());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+    set_delegation(
+        &mut deps.querier,
+        validator.clone(),
+        bond_amount.u128(),
+        "uluna",
+    );
+
+    // before the cutoff the whole reward is compounded
+    let info = mock_info("rewards_contract", &[Coin::new(1000, "uluna")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: validator.address.clone(),
+            amount: Coin::new(1000u128, "uluna"),
+        }))]
+    );
+    assert_eq!(res.attributes[2].value, "0");
+
+    // from the cutoff the configured fee applies
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::UpdateExchangeRate {}).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "fee_collector".to_string(),
+                amount: vec![Coin::new(100u128, "uluna")],
+            })),
+            SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+                validator: validator.address,
+                amount: Coin::new(900u128, "uluna"),
+            })),
+        ]
+    );
+    assert_eq!(res.attributes[2].value, "100");
+}
+
+/// Covers if UpdateExchangeRate rejects rewards sent along with another denom
+#[test]
+pub fn proper_update_exchange_rate_extra_denom() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let bond_amount = Uint128::new(1000000u128);
+    do_bond(deps.as_mut(), bob.clone(), bond_amount, validator.clone());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+    set_delegation(&mut deps.querier, validator, bond_amount.u128(), "uluna");
+
+    let info = mock_info(
+        "rewards_contract",
+        &[Coin::new(1000, "uluna"), Coin::new(10, "ukrw")],
+    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::MultipleCoins {});
+
+    let info = mock_info("rewards_contract", &[Coin::new(1000, "uluna")]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap();
+}
+
+/// Covers if a validator with a commission above max_validator_commission can not be registered,
+/// neither directly nor as a deregister replacement
+#[test]
+pub fn proper_max_validator_commission() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let expensive_validator = Validator {
+        address: DEFAULT_VALIDATOR2.to_string(),
+        commission: Decimal::percent(20),
+        max_commission: Decimal::percent(30),
+        max_change_rate: Decimal::percent(1),
+    };
+    deps.querier.update_staking(
+        "uluna",
+        &[validator.clone(), expensive_validator.clone()],
+        &[],
+    );
+
+    let owner = "owner1".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: Some(Decimal::percent(10)),
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_prams).unwrap();
+
+    // a 3% commission is accepted
+    let register_msg = ExecuteMsg::RegisterValidator {
+        validator: validator.address.clone(),
+    };
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), register_msg).unwrap();
+
+    // a 20% commission is refused
+    let register_msg = ExecuteMsg::RegisterValidator {
+        validator: expensive_validator.address.clone(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), register_msg).unwrap_err();
+    assert_eq!(
+        res,
+        HubError::ValidatorCommissionTooHigh {
+            commission: Decimal::percent(20),
+            max_commission: Decimal::percent(10),
+        }
+    );
+
+    // nor can it come in as the replacement of a deregistered validator
+    let deregister_msg = ExecuteMsg::DeregisterValidator {
+        validator: validator.address,
+        replacement: Some(expensive_validator.address),
+    };
+    let res = execute(deps.as_mut(), mock_env(), owner_info, deregister_msg).unwrap_err();
+    assert_eq!(
+        res,
+        HubError::ValidatorCommissionTooHigh {
+            commission: Decimal::percent(20),
+            max_commission: Decimal::percent(10),
+        }
+    );
+}
+
+/// Covers if the cumulative rewards and protocol fees count every compound
+#[test]
+pub fn proper_cumulative_compound_counters() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let bob = "bob".to_string();
+    let bond_amount = Uint128::new(1000000u128);
+
+    init(
+        &mut deps,
+        owner.clone(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: Some(Decimal::percent(10)),
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: Some(true),
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        update_prams,
+    )
+    .unwrap();
+
+    do_bond(deps.as_mut(), bob.clone(), bond_amount, validator.clone());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+    set_delegation(&mut deps.querier, validator, bond_amount.u128(), "uluna");
+
+    for rewards in [1000u128, 500u128] {
+        let info = mock_info("rewards_contract", &[Coin::new(rewards, "uluna")]);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::UpdateExchangeRate {},
+        )
+        .unwrap();
+    }
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.cumulative_rewards_compounded, Uint128::new(1500));
+    assert_eq!(state.cumulative_protocol_fees, Uint128::new(150));
+    assert_eq!(state.total_rewards_compounded, Uint128::new(1350));
+}
+
+/// Covers if the owner can move the unbond requests of an address to another,
+/// merging the batches both addresses requested in
+#[test]
+pub fn proper_reassign_unbond_requests() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract.clone(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let alice = "alice".to_string();
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    do_bond(
+        deps.as_mut(),
+        alice.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator, 200, "uluna");
+    deps.querier.with_token_balances(&[(
+        &token_contract,
+        &[
+            (&bob, &Uint128::new(100u128)),
+            (&alice, &Uint128::new(100u128)),
+        ],
+    )]);
+
+    // bob requests in the first and second batch, alice only in the second
+    let token_info = mock_info(&token_contract, &[]);
+    do_unbond(
+        deps.as_mut(),
+        bob.clone(),
+        mock_env(),
+        token_info.clone(),
+        Uint128::new(10),
+    );
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        ExecuteMsg::ForceBatchRollover {},
+    )
+    .unwrap();
+    do_unbond(
+        deps.as_mut(),
+        bob.clone(),
+        mock_env(),
+        token_info.clone(),
+        Uint128::new(20),
+    );
+    do_unbond(
+        deps.as_mut(),
+        alice.clone(),
+        mock_env(),
+        token_info,
+        Uint128::new(5),
+    );
+
+    let reassign = ExecuteMsg::ReassignUnbondRequests {
+        from: bob.clone(),
+        to: alice.clone(),
+    };
+
+    // only the owner can reassign
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&bob, &[]),
+        reassign.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::NotAdmin {});
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        reassign.clone(),
+    )
+    .unwrap();
+    assert_eq!(res.attributes[3].value, "2");
+
+    let bob_requests: UnbondRequestsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::UnbondRequests { address: bob },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(bob_requests.requests.is_empty());
+
+    let alice_requests: UnbondRequestsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::UnbondRequests { address: alice },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        alice_requests.requests,
+        vec![(1u64, Uint128::new(10)), (2u64, Uint128::new(25))]
+    );
+
+    // nothing is left to move
+    let res = execute(deps.as_mut(), mock_env(), mock_info(&owner, &[]), reassign).unwrap_err();
+    assert_eq!(
+        res,
+        HubError::Std(StdError::generic_err("No unbond requests to reassign"))
+    );
+}
+
+/// Covers if a slash detected by CheckSlashing is recorded in the slash history
+#[test]
+pub fn proper_slash_history() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let addr1 = "addr1000".to_string();
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_bond(
+        deps.as_mut(),
+        addr1.clone(),
+        Uint128::new(1000),
+        validator.clone(),
+    );
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&addr1, &Uint128::new(1000u128))])]);
+
+    // no slash is recorded while the delegation matches
+    set_delegation(&mut deps.querier, validator.clone(), 1000, "uluna");
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&addr1, &[]),
+        CheckSlashing {},
+    )
+    .unwrap();
+    let history: SlashHistoryResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::SlashHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(history.slashes.is_empty());
+
+    set_delegation(&mut deps.querier, validator, 900, "uluna");
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&addr1, &[]),
+        CheckSlashing {},
+    )
+    .unwrap();
+
+    let history: SlashHistoryResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::SlashHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        history.slashes,
+        vec![SlashEvent {
+            id: 1,
+            time: mock_env().block.time.seconds(),
+            old_bond_amount: Uint128::new(1000),
+            new_bond_amount: Uint128::new(900),
+            exchange_rate: Decimal::from_ratio(9u128, 10u128),
+        }]
+    );
+
+    let history: SlashHistoryResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::SlashHistory {
+                start_after: Some(1),
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(history.slashes.is_empty());
+}
+
+/// Covers if an instant redeem pays the idle balance minus the fee and burns the basset,
+/// and is rejected while disabled or when the idle balance does not cover the payout
+#[test]
+pub fn proper_instant_redeem() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    let bob = "bob".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract.clone(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(1000),
+        validator.clone(),
+    );
+    deps.querier
+        .with_token_balances(&[(&token_contract, &[(&bob, &Uint128::new(1000u128))])]);
+    set_delegation(&mut deps.querier, validator, 1000, "uluna");
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(500),
+        },
+    )]);
+
+    let redeem = |amount: u128| {
+        Receive(Cw20ReceiveMsg {
+            sender: bob.clone(),
+            amount: Uint128::new(amount),
+            msg: to_binary(&InstantRedeem {}).unwrap(),
+        })
+    };
+    let token_info = mock_info(&token_contract, &[]);
+
+    // disabled until the fee is set
+    let res = execute(deps.as_mut(), mock_env(), token_info.clone(), redeem(100)).unwrap_err();
+    assert_eq!(
+        res,
+        HubError::Std(StdError::generic_err("Instant redeem is disabled"))
+    );
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: Some(Decimal::percent(1)),
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        update_prams,
+    )
+    .unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), token_info.clone(), redeem(100)).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: token_contract.clone(),
+                msg: to_binary(&Cw20ExecuteMsg::Burn {
+                    amount: Uint128::new(100)
+                })
+                .unwrap(),
+                funds: vec![],
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: bob.clone(),
+                amount: vec![coin(99, "uluna")],
+            })),
+        ]
+    );
+
+    // the delegations are untouched, so the remaining supply keeps the whole bonded share
+    // of the burned basset and the exchange rate rises by the payout, not only the fee
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.exchange_rate, Decimal::from_ratio(1000u128, 900u128));
+    assert_eq!(state.total_bond_amount, Uint128::new(1000));
+
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(401),
+        },
+    )]);
+    deps.querier
+        .with_token_balances(&[(&token_contract, &[(&bob, &Uint128::new(900u128))])]);
+
+    // 500 basset is worth more than the 401 idle uluna
+    let res = execute(deps.as_mut(), mock_env(), token_info, redeem(500)).unwrap_err();
+    assert_eq!(
+        res,
+        HubError::Std(StdError::generic_err(
+            "Not enough idle liquidity to redeem instantly, unbond instead"
+        ))
+    );
+
+    // the idle balance left after the payout is restaked on top of it
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("keeper", &[]),
+        ExecuteMsg::RestakeIdle {},
+    )
+    .unwrap();
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_bond_amount, Uint128::new(1401));
+    assert_eq!(state.exchange_rate, Decimal::from_ratio(1401u128, 900u128));
+}
+
+/// Covers if the simulated undelegations match the ones sent when the batch is undelegated
+#[test]
+pub fn proper_simulate_undelegate() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    let validator3 = sample_validator(DEFAULT_VALIDATOR3.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    let bob = "bob".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract.clone(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_register_validator(deps.as_mut(), validator2.clone());
+    do_register_validator(deps.as_mut(), validator3.clone());
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(1000),
+        validator.clone(),
+    );
+    deps.querier
+        .with_token_balances(&[(&token_contract, &[(&bob, &Uint128::new(1000u128))])]);
+    set_delegation_query(
+        &mut deps.querier,
+        &[
+            sample_delegation(validator.address.clone(), coin(600, "uluna")),
+            sample_delegation(validator2.address.clone(), coin(300, "uluna")),
+            sample_delegation(validator3.address.clone(), coin(100, "uluna")),
+        ],
+        &[validator, validator2, validator3],
+    );
+
+    do_unbond(
+        deps.as_mut(),
+        bob.clone(),
+        mock_env(),
+        mock_info(&token_contract, &[]),
+        Uint128::new(700),
+    );
+    deps.querier
+        .with_token_balances(&[(&token_contract, &[(&bob, &Uint128::new(300u128))])]);
+
+    let simulated: SimulateUndelegateResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::SimulateUndelegate {
+                amount: Uint128::new(700),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let total = simulated
+        .undelegations
+        .iter()
+        .fold(Uint128::zero(), |total, (_, amount)| total + *amount);
+    assert_eq!(total, Uint128::new(700));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        ExecuteMsg::ForceBatchRollover {},
+    )
+    .unwrap();
+    let expected: Vec<SubMsg> = simulated
+        .undelegations
+        .into_iter()
+        .map(|(validator, amount)| {
+            SubMsg::new(CosmosMsg::Staking(StakingMsg::Undelegate {
+                validator,
+                amount: coin(amount.u128(), "uluna"),
+            }))
+        })
+        .collect();
+    assert_eq!(res.messages, expected);
+}
+
+/// Covers if compounding is rejected while the exchange rate is frozen,
+/// and the matured batches can still be withdrawn
+#[test]
+pub fn proper_freeze_exchange_rate() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
+
+    let info = mock_info(&bob, &[]);
+    let mut env = mock_env();
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
+
+    // close the batch
+    env.block.time = env.block.time.plus_seconds(31);
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(80u128))])]);
+
+    // only the owner can freeze
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::FreezeExchangeRate {},
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::NotAdmin {});
+
+    let owner_info = mock_info(&owner, &[]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        ExecuteMsg::FreezeExchangeRate {},
+    )
+    .unwrap();
+
+    let rewards_info = mock_info("rewards_contract", &[Coin::new(100, "uluna")]);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        rewards_info.clone(),
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::RateFrozen {});
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::RateFrozen {});
+
+    // the batch is matured and its funds have arrived
+    env.block.time = env.block.time.plus_seconds(3);
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(20),
+        },
+    )]);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info,
+        ExecuteMsg::WithdrawUnbonded { recipient: None },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: bob,
+            amount: vec![Coin::new(20u128, "uluna")],
+        }))
+    );
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info,
+        ExecuteMsg::UnfreezeExchangeRate {},
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env,
+        rewards_info,
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap();
+}
+
+/// Covers if an unbond opening one more unreleased batch than
+/// max_open_unbond_batches_per_user is rejected
+#[test]
+pub fn proper_max_open_unbond_batches_per_user() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract.clone(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    deps.querier
+        .with_token_balances(&[(&token_contract, &[(&bob, &Uint128::new(100u128))])]);
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: Some(2),
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_prams).unwrap();
+
+    let unbond = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: bob.clone(),
+        amount: Uint128::new(10),
+        msg: to_binary(&Unbond {}).unwrap(),
+    });
+    let token_info = mock_info(&token_contract, &[]);
+
+    // the first batch, then the second one twice
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        token_info.clone(),
+        unbond.clone(),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::ForceBatchRollover {},
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        token_info.clone(),
+        unbond.clone(),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        token_info.clone(),
+        unbond.clone(),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info,
+        ExecuteMsg::ForceBatchRollover {},
+    )
+    .unwrap();
+
+    // a third unreleased batch is over the limit
+    let res = execute(deps.as_mut(), mock_env(), token_info, unbond).unwrap_err();
+    assert_eq!(res, HubError::TooManyOpenBatches { max: 2 });
+    assert_eq!(
+        res.to_string(),
+        "Requests are open in 2 unreleased batches, withdraw the matured batches first"
+    );
+}
+
+/// Covers if RegisterValidators whitelists every validator of the list,
+/// and none of them when one is not a validator
+#[test]
+pub fn proper_register_validators() {
+    let mut deps = dependencies(&[]);
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        "token".to_string(),
+        DEFAULT_VALIDATOR.to_string(),
+    );
+
+    let whitelisted = |deps: &OwnedDeps<MockStorage, MockApi, WasmMockQuerier>| {
+        let res: WhitelistedValidatorsResponse = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::WhitelistedValidators {
+                    start_after: None,
+                    limit: None,
+                    reverse: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        res.validators
+    };
+
+    // only the owner can register
+    let msg = ExecuteMsg::RegisterValidators {
+        validators: vec![DEFAULT_VALIDATOR2.to_string()],
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg).unwrap_err();
+    assert_eq!(res, HubError::NotAdmin {});
+
+    let owner_info = mock_info(&owner, &[]);
+    let msg = ExecuteMsg::RegisterValidators {
+        validators: vec![DEFAULT_VALIDATOR2.to_string(), "invalid".to_string()],
+    };
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap_err();
+    assert_eq!(
+        res,
+        HubError::Std(StdError::generic_err(
+            "The specified address is not a validator"
+        ))
+    );
+    assert!(!whitelisted(&deps).contains(&DEFAULT_VALIDATOR2.to_string()));
+
+    let msg = ExecuteMsg::RegisterValidators {
+        validators: vec![
+            DEFAULT_VALIDATOR.to_string(),
+            DEFAULT_VALIDATOR2.to_string(),
+            DEFAULT_VALIDATOR3.to_string(),
+        ],
+    };
+    let res = execute(deps.as_mut(), mock_env(), owner_info, msg).unwrap();
+    assert_eq!(res.attributes.len(), 4);
+    assert_eq!(res.attributes[3].value, DEFAULT_VALIDATOR3);
+    assert_eq!(
+        whitelisted(&deps),
+        vec![
+            DEFAULT_VALIDATOR.to_string(),
+            DEFAULT_VALIDATOR2.to_string(),
+            DEFAULT_VALIDATOR3.to_string(),
+        ]
+    );
+}
+
+/// Covers if an unbond within unbond_cooloff of the last bond is rejected, and allowed after it,
+/// while a bond by a third party for the user does not restart the cool-off
+#[test]
+pub fn proper_unbond_cooloff() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: Some(100),
+        liquidity_reserve_ratio: None,
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        update_prams,
+    )
+    .unwrap();
+
+    let bob = "bob".to_string();
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+    set_delegation(&mut deps.querier, validator.clone(), 100, "uluna");
+
+    let bond_time = mock_env().block.time.seconds();
+    let info = mock_info(&bob, &[]);
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(50);
+    let res = execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        res,
+        HubError::UnbondCoolOff {
+            until: bond_time + 100
+        }
+    );
+
+    env.block.time = env.block.time.plus_seconds(50);
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+
+    // a bond by a third party for bob does not delay the unbond of bob
+    let bond_for = ExecuteMsg::BondFor {
+        validator: validator.address,
+        recipient: bob.clone(),
+        min_mint: None,
+    };
+    let alice_info = mock_info("alice", &[coin(10, "uluna")]);
+    execute(deps.as_mut(), env.clone(), alice_info, bond_for).unwrap();
+    execute_unbond(deps.as_mut(), env, info, Uint128::new(10), bob).unwrap();
+}
+
+/// Covers if DelegationCount reports one delegation per bonded validator
+#[test]
+pub fn proper_delegation_count() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_register_validator(deps.as_mut(), validator2.clone());
+
+    let bob = "bob".to_string();
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    do_bond(deps.as_mut(), bob, Uint128::new(50), validator2.clone());
+    set_delegation_query(
+        &mut deps.querier,
+        &[
+            sample_delegation(validator.address.clone(), coin(100, "uluna")),
+            sample_delegation(validator2.address.clone(), coin(50, "uluna")),
+        ],
+        &[validator, validator2],
+    );
+
+    let res: DelegationCountResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::DelegationCount {}).unwrap())
+            .unwrap();
+    assert_eq!(res.count, 2);
+}
+
+/// Covers if the matured funds are sent to the recipient given to WithdrawUnbonded
+#[test]
+pub fn proper_withdraw_unbonded_recipient() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
+
+    let info = mock_info(&bob, &[]);
+    let mut env = mock_env();
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
+
+    // close the batch
+    env.block.time = env.block.time.plus_seconds(31);
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+
+    // the batch is matured and its funds have arrived
+    env.block.time = env.block.time.plus_seconds(3);
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(20),
+        },
+    )]);
+
+    let msg = ExecuteMsg::WithdrawUnbonded {
+        recipient: Some("custody".to_string()),
+    };
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "custody".to_string(),
+            amount: vec![Coin::new(20u128, "uluna")],
+        }))]
+    );
+
+    // the consumed requests were bob's
+    let requests: UnbondRequestsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::UnbondRequests { address: bob },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(requests.requests.is_empty());
+}
+
+/// Covers if UpdateGlobalIndex reports the withdrawn validators and the balance before the withdrawals
+#[test]
+pub fn proper_update_global_index_attributes() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_register_validator(deps.as_mut(), validator2.clone());
+
+    let bob = "bob".to_string();
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    do_bond(deps.as_mut(), bob, Uint128::new(50), validator2.clone());
+    set_delegation_query(
+        &mut deps.querier,
+        &[
+            sample_delegation(validator.address.clone(), coin(100, "uluna")),
+            sample_delegation(validator2.address.clone(), coin(50, "uluna")),
+        ],
+        &[validator, validator2],
+    );
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(25),
+        },
+    )]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("keeper", &[]),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "update_global_index"),
+            attr("validators_withdrawn", "2"),
+            attr("principle_balance_before", "25"),
+            attr("keeper_reward", "0"),
+        ]
+    );
+}
+
+/// Covers if a compound keeps liquidity_reserve_ratio of the rewards idle
+/// and the reserve is reported against its target
+#[test]
+pub fn proper_liquidity_reserve_ratio() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: Some(Decimal::percent(10)),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        update_prams,
+    )
+    .unwrap();
+
+    let bob = "bob".to_string();
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(1000),
+        validator.clone(),
+    );
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(1000u128))])]);
+    set_delegation(&mut deps.querier, validator.clone(), 1000, "uluna");
+
+    let info = mock_info("rewards_contract", &[Coin::new(100, "uluna")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap();
     assert_eq!(
-        wdraw_unbonded_res.unwrap_err(),
-        StdError::generic_err("No withdrawable uluna assets are available yet")
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: validator.address,
+            amount: coin(90, "uluna"),
+        }))]
     );
 
-    let res = execute_unbond(
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_bond_amount, Uint128::new(1090));
+
+    // the reserved rewards are held on the hub
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(10),
+        },
+    )]);
+    let reserve: LiquidityReserveResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::LiquidityReserve {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        reserve,
+        LiquidityReserveResponse {
+            reserve: Uint128::new(10),
+            target: Uint128::new(109),
+        }
+    );
+}
+
+/// Covers if the liquidity reserve is not paid to a batch released after the rewards are reserved
+#[test]
+pub fn proper_liquidity_reserve_not_released() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    init(
+        &mut deps,
+        owner.clone(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: Some(Decimal::percent(10)),
+    };
+    execute(
         deps.as_mut(),
-        env.clone(),
-        info.clone(),
-        Uint128::new(10),
-        bob.clone(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        update_prams,
     )
     .unwrap();
-    assert_eq!(res.messages.len(), 2);
+
+    let bob = "bob".to_string();
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(1000),
+        validator.clone(),
+    );
     deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(80u128))])]);
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(1000u128))])]);
+    set_delegation(&mut deps.querier, validator.clone(), 1000, "uluna");
 
-    //this query should be zero since the undelegated period is not passed
-    let withdrawable = WithdrawableUnbonded {
-        address: bob.clone(),
-    };
-    let query_with = query(deps.as_ref(), mock_env(), withdrawable).unwrap();
-    let res: WithdrawableUnbondedResponse = from_binary(&query_with).unwrap();
-    assert_eq!(res.withdrawable, Uint128::new(0));
+    // 10 of the rewards are reserved on the hub
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewards_contract", &[Coin::new(100, "uluna")]),
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap();
+    set_delegation(&mut deps.querier, validator, 1090, "uluna");
 
-    env.block.time = env.block.time.plus_seconds(91);
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.prev_hub_balance, Uint128::new(10));
+    assert_eq!(state.liquidity_reserve, Uint128::new(10));
 
-    // fabricate balance of the hub contract
+    // bob's request closes the batch, 109 is undelegated at the rate of 1.09
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(31);
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&bob, &[]),
+        Uint128::new(100),
+        bob,
+    )
+    .unwrap();
+
+    // the batch is matured and its funds have arrived next to the reserve
+    env.block.time = env.block.time.plus_seconds(3);
     deps.querier.with_native_balances(&[(
         MOCK_CONTRACT_ADDR.to_string(),
         Coin {
             denom: "uluna".to_string(),
-            amount: Uint128::new(20),
+            amount: Uint128::new(119),
         },
     )]);
-    //first query AllUnbondedRequests
-    let all_unbonded = UnbondRequests {
-        address: bob.clone(),
-    };
-    let query_unbonded = query(deps.as_ref(), mock_env(), all_unbonded).unwrap();
-    let res: UnbondRequestsResponse = from_binary(&query_unbonded).unwrap();
-    assert_eq!(res.requests.len(), 1);
-    //the amount should be 10
-    assert_eq!(&res.address, &bob);
-    assert_eq!(res.requests[0].1, Uint128::new(20));
-    assert_eq!(res.requests[0].0, 1);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("keeper", &[]),
+        ExecuteMsg::ProcessMaturedBatches { limit: None },
+    )
+    .unwrap();
 
-    let all_batches = AllHistory {
-        start_from: None,
-        limit: None,
-    };
-    let res: AllHistoryResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), all_batches).unwrap()).unwrap();
-    assert_eq!(res.history[0].amount, Uint128::new(20));
-    assert_eq!(res.history[0].batch_id, 1);
+    let history: UnbondHistory = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::BatchHistory { batch_id: 1 },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(history.released);
+    assert_eq!(history.withdraw_rate, Decimal::from_ratio(109u128, 100u128));
 
-    //check with query
-    let withdrawable = WithdrawableUnbonded {
-        address: bob.clone(),
-    };
-    let query_with = query(deps.as_ref(), env.clone(), withdrawable).unwrap();
-    let res: WithdrawableUnbondedResponse = from_binary(&query_with).unwrap();
-    assert_eq!(res.withdrawable, Uint128::new(20));
+    let reserve: LiquidityReserveResponse =
+        from_binary(&query(deps.as_ref(), env, QueryMsg::LiquidityReserve {}).unwrap()).unwrap();
+    assert_eq!(reserve.reserve, Uint128::new(10));
+}
 
-    let success_res = execute(deps.as_mut(), env, info, wdraw_unbonded_msg).unwrap();
+/// Covers that the unbond batch accounting returns an error on overflow instead of panicking
+#[test]
+pub fn proper_unbond_checked_arithmetic() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
 
-    assert_eq!(success_res.messages.len(), 1);
+    let owner = "owner1".to_string();
+    init(
+        &mut deps,
+        owner,
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
 
-    let sent_message = &success_res.messages[0].msg;
-    match sent_message {
-        CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
-            assert_eq!(to_address, &bob);
-            assert_eq!(amount[0].amount, Uint128::new(20))
-        }
+    let bob = "bob".to_string();
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator, 100, "uluna");
 
-        _ => panic!("Unexpected message: {:?}", sent_message),
-    }
+    // a supply close to the limit still accumulates the requests
+    let near_max = Uint128::MAX - Uint128::new(100);
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &near_max)])]);
+    let info = mock_info(&bob, &[]);
+    execute_unbond(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    execute_unbond(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        Uint128::new(20),
+        bob.clone(),
+    )
+    .unwrap();
 
-    //it should be removed
-    let withdrawable = WithdrawableUnbonded {
-        address: bob.clone(),
-    };
-    let query_with: WithdrawableUnbondedResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), withdrawable).unwrap()).unwrap();
-    assert_eq!(query_with.withdrawable, Uint128::new(0));
+    let query_batch: CurrentBatchResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::CurrentBatch {}).unwrap()).unwrap();
+    assert_eq!(query_batch.requested_with_fee, Uint128::new(30));
 
-    let waitlist = UnbondRequests {
-        address: bob.clone(),
-    };
-    let query_unbond: UnbondRequestsResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), waitlist).unwrap()).unwrap();
+    // the supply plus the requested amount no longer fits in 128 bits
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::MAX)])]);
+    let res = execute_unbond(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(res, HubError::Std(StdError::Overflow { .. })));
+
+    let query_batch: CurrentBatchResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::CurrentBatch {}).unwrap()).unwrap();
+    assert_eq!(query_batch.requested_with_fee, Uint128::new(30));
+}
+
+/// Covers if the requesters of a released batch are listed for the keepers
+#[test]
+pub fn proper_withdrawable_addresses() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bob = "bob".to_string();
+    let alice = "alice".to_string();
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    do_bond(
+        deps.as_mut(),
+        alice.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[
+            (&bob, &Uint128::new(100u128)),
+            (&alice, &Uint128::new(100u128)),
+        ],
+    )]);
+    set_delegation(&mut deps.querier, validator, 200, "uluna");
+
+    let mut env = mock_env();
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&bob, &[]),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[
+            (&bob, &Uint128::new(90u128)),
+            (&alice, &Uint128::new(100u128)),
+        ],
+    )]);
+
+    // alice's request closes the batch
+    env.block.time = env.block.time.plus_seconds(31);
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&alice, &[]),
+        Uint128::new(20),
+        alice.clone(),
+    )
+    .unwrap();
+
+    // nothing is listed before the batch is released
+    let res: WithdrawableAddressesResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::WithdrawableAddresses {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(res.addresses.is_empty());
+
+    // the batch is matured and its funds have arrived
+    env.block.time = env.block.time.plus_seconds(3);
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(30),
+        },
+    )]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("keeper", &[]),
+        ExecuteMsg::ProcessMaturedBatches { limit: None },
+    )
+    .unwrap();
+
+    let res: WithdrawableAddressesResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::WithdrawableAddresses {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
     assert_eq!(
-        query_unbond,
-        UnbondRequestsResponse {
-            address: bob,
-            requests: vec![]
-        }
+        res.addresses,
+        vec![
+            (bob.clone(), Uint128::new(10)),
+            (alice.clone(), Uint128::new(20))
+        ]
     );
 
-    // because of one that we add for each batch
-    let state = QueryMsg::State {};
-    let state_query: StateResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), state).unwrap()).unwrap();
-    assert_eq!(state_query.prev_hub_balance, Uint128::new(0));
-    assert_eq!(state_query.exchange_rate, Decimal::one());
+    // the page continues after the last listed address
+    let res: WithdrawableAddressesResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::WithdrawableAddresses {
+                start_after: None,
+                limit: Some(1),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.addresses, vec![(bob.clone(), Uint128::new(10))]);
+    assert_eq!(res.last_requester, Some(bob.clone()));
+    let res: WithdrawableAddressesResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::WithdrawableAddresses {
+                start_after: Some(bob),
+                limit: Some(1),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.addresses, vec![(alice, Uint128::new(20))]);
+    assert_eq!(res.last_requester, None);
 }
 
-/// Covers slashing during the unbonded period and its effect on the finished amount.
+/// Covers if a blocklisted validator can not be registered or used as a replacement
+/// until it is unblocklisted
 #[test]
-pub fn proper_withdraw_unbonded_respect_slashing() {
+pub fn proper_validator_blocklist() {
     let mut deps = dependencies(&[]);
-
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
     set_validator_mock(&mut deps.querier);
 
-    let bond_amount = Uint128::new(10000);
-    let unbond_amount = Uint128::new(500);
-
     let owner = "owner1".to_string();
-    let token_contract = "token".to_string();
-
-    init(&mut deps, owner, token_contract, validator.address.clone());
-
-    // register_validator
+    init(
+        &mut deps,
+        owner.clone(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
     do_register_validator(deps.as_mut(), validator.clone());
 
-    let bob = "bob".to_string();
-    let bond_msg = ExecuteMsg::Bond {
-        validator: validator.address.clone(),
+    // only the owner manages the blocklist
+    let msg = ExecuteMsg::BlocklistValidator {
+        validator: validator2.address.clone(),
     };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        msg.clone(),
+    );
+    assert_eq!(res.unwrap_err(), HubError::NotAdmin {});
 
-    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
-
-    //set bob's balance to 10 in token contract
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
-
-    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg).unwrap();
-    assert_eq!(2, res.messages.len());
+    let res = execute(deps.as_mut(), mock_env(), mock_info(&owner, &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "blocklist_validator"),
+            attr("validator", validator2.address.clone()),
+        ]
+    );
 
-    let delegate = &res.messages[0].msg;
-    match delegate {
-        CosmosMsg::Staking(StakingMsg::Delegate { validator, amount }) => {
-            assert_eq!(validator.as_str(), DEFAULT_VALIDATOR.to_string());
-            assert_eq!(amount, &coin(bond_amount.u128(), "uluna"));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        ExecuteMsg::RegisterValidator {
+            validator: validator2.address.clone(),
+        },
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        HubError::ValidatorBlocklisted {
+            validator: validator2.address.clone(),
         }
-        _ => panic!("Unexpected message: {:?}", delegate),
-    }
-
-    set_delegation(&mut deps.querier, validator, bond_amount.u128(), "uluna");
+    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        ExecuteMsg::RegisterValidators {
+            validators: vec![validator2.address.clone()],
+        },
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        HubError::ValidatorBlocklisted {
+            validator: validator2.address.clone(),
+        }
+    );
+    // nor can it replace a deregistered validator
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        ExecuteMsg::DeregisterValidator {
+            validator: validator.address.clone(),
+            replacement: Some(validator2.address.clone()),
+        },
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        HubError::ValidatorBlocklisted {
+            validator: validator2.address.clone(),
+        }
+    );
+    assert!(!is_valid_validator(deps.as_ref().storage, validator2.address.clone()).unwrap());
 
-    let res = execute_unbond(deps.as_mut(), mock_env(), info, unbond_amount, bob.clone()).unwrap();
-    assert_eq!(1, res.messages.len());
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9500))])]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        ExecuteMsg::UnblocklistValidator {
+            validator: validator2.address.clone(),
+        },
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        ExecuteMsg::UnblocklistValidator {
+            validator: validator2.address.clone(),
+        },
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        HubError::Std(StdError::generic_err("Validator is not blocklisted"))
+    );
 
-    deps.querier.with_native_balances(&[(
-        MOCK_CONTRACT_ADDR.to_string(),
-        Coin {
-            denom: "uluna".to_string(),
-            amount: Uint128::new(0),
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        ExecuteMsg::RegisterValidator {
+            validator: validator2.address.clone(),
         },
-    )]);
+    )
+    .unwrap();
+    assert!(is_valid_validator(deps.as_ref().storage, validator2.address).unwrap());
+}
 
-    let info = mock_info(&bob, &[]);
-    let mut env = mock_env();
-    //set the block time 30 seconds from now.
+/// Covers if compounding without any delegation returns an error instead of panicking
+#[test]
+pub fn proper_compound_without_delegations() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
 
-    env.block.time = env.block.time.plus_seconds(31);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
 
-    let wdraw_unbonded_msg = ExecuteMsg::WithdrawUnbonded {};
-    let wdraw_unbonded_res = execute(
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &INITIAL_DEPOSIT_AMOUNT)],
+    )]);
+    let state_before: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+
+    // all the stake has been undelegated
+    let info = mock_info("rewards_contract", &[Coin::new(100, "uluna")]);
+    let res = execute(
         deps.as_mut(),
-        env.clone(),
+        mock_env(),
         info.clone(),
-        wdraw_unbonded_msg.clone(),
-    );
-    assert!(wdraw_unbonded_res.is_err());
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap_err();
     assert_eq!(
-        wdraw_unbonded_res.unwrap_err(),
-        StdError::generic_err("No withdrawable uluna assets are available yet")
+        res,
+        HubError::Std(StdError::generic_err(
+            "There are no delegations to compound the rewards into"
+        ))
     );
+    let state_after: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state_after, state_before);
 
-    // trigger undelegation message
-    let res = execute_unbond(
+    set_delegation(
+        &mut deps.querier,
+        validator.clone(),
+        INITIAL_DEPOSIT_AMOUNT.u128(),
+        "uluna",
+    );
+    let res = execute(
         deps.as_mut(),
-        env.clone(),
-        info.clone(),
-        unbond_amount,
-        bob.clone(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateExchangeRate {},
     )
     .unwrap();
-    assert_eq!(2, res.messages.len());
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9000))])]);
-
-    //this query should be zero since the undelegated period is not passed
-    let withdrawable = WithdrawableUnbonded {
-        address: bob.clone(),
-    };
-    let query_with = query(deps.as_ref(), mock_env(), withdrawable).unwrap();
-    let res: WithdrawableUnbondedResponse = from_binary(&query_with).unwrap();
-    assert_eq!(res.withdrawable, Uint128::new(0));
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: validator.address,
+            amount: coin(100, "uluna"),
+        }))]
+    );
+}
 
-    env.block.time = env.block.time.plus_seconds(91);
+/// Covers if the batch records the exchange rate of the state at rollover time
+#[test]
+pub fn proper_batch_exchange_rate() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
 
-    // fabricate balance of the hub contract
-    deps.querier.with_native_balances(&[(
-        MOCK_CONTRACT_ADDR.to_string(),
-        Coin {
-            denom: "uluna".to_string(),
-            amount: Uint128::new(900),
-        },
-    )]);
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
+    do_register_validator(deps.as_mut(), validator.clone());
 
-    //first query AllUnbondedRequests
-    let all_unbonded = UnbondRequests {
-        address: bob.clone(),
-    };
-    let query_unbonded = query(deps.as_ref(), mock_env(), all_unbonded).unwrap();
-    let res: UnbondRequestsResponse = from_binary(&query_unbonded).unwrap();
-    assert_eq!(res.requests.len(), 1);
-    //the amount should be 10
-    assert_eq!(&res.address, &bob);
-    assert_eq!(res.requests[0].1, Uint128::new(1000));
-    assert_eq!(res.requests[0].0, 1);
+    let bob = "bob".to_string();
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(100),
+        validator.clone(),
+    );
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(100u128))])]);
+    set_delegation(&mut deps.querier, validator.clone(), 100, "uluna");
 
-    //check with query
-    //this query does not reflect the actual withdrawable
-    let withdrawable = WithdrawableUnbonded {
-        address: bob.clone(),
-    };
-    let query_with = query(deps.as_ref(), env.clone(), withdrawable).unwrap();
-    let res: WithdrawableUnbondedResponse = from_binary(&query_with).unwrap();
-    assert_eq!(res.withdrawable, Uint128::new(1000));
+    // the compounded rewards move the exchange rate away from one
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewards_contract", &[Coin::new(10, "uluna")]),
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap();
+    set_delegation(&mut deps.querier, validator, 110, "uluna");
 
-    let success_res = execute(deps.as_mut(), env, info, wdraw_unbonded_msg).unwrap();
+    let info = mock_info(&bob, &[]);
+    let mut env = mock_env();
+    execute_unbond(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        Uint128::new(10),
+        bob.clone(),
+    )
+    .unwrap();
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(90u128))])]);
 
-    assert_eq!(success_res.messages.len(), 1);
+    // the open batch has no rate yet
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::BatchExchangeRate { batch_id: 1 },
+    );
+    assert!(res.is_err());
 
-    let sent_message = &success_res.messages[0].msg;
-    match sent_message {
-        CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
-            assert_eq!(to_address, &bob);
-            assert_eq!(amount[0].amount, Uint128::new(899))
-        }
+    // close the batch
+    env.block.time = env.block.time.plus_seconds(31);
+    execute_unbond(deps.as_mut(), env.clone(), info, Uint128::new(10), bob).unwrap();
 
-        _ => panic!("Unexpected message: {:?}", sent_message),
-    }
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.exchange_rate, Decimal::from_ratio(11u128, 10u128));
 
-    // there should not be any result
-    let withdrawable = WithdrawableUnbonded { address: bob };
-    let query_with: WithdrawableUnbondedResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), withdrawable).unwrap()).unwrap();
-    assert_eq!(query_with.withdrawable, Uint128::new(0));
+    let res: BatchExchangeRateResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::BatchExchangeRate { batch_id: 1 },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        BatchExchangeRateResponse {
+            batch_id: 1,
+            applied_exchange_rate: state.exchange_rate,
+        }
+    );
+    assert_eq!(
+        BATCH_EXCHANGE_RATE.load(&deps.storage, 1).unwrap(),
+        state.exchange_rate
+    );
 }
 
-/// Covers withdraw_unbonded/inactivity in the system while there are slashing events.
+/// Covers if the caller of UpdateGlobalIndex is paid the keeper reward out of the accrued fee
+/// and is throttled by the keeper interval
 #[test]
-pub fn proper_withdraw_unbonded_respect_inactivity_slashing() {
+pub fn proper_keeper_reward() {
     let mut deps = dependencies(&[]);
-
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
     set_validator_mock(&mut deps.querier);
 
-    let bond_amount = Uint128::new(10000);
-    let unbond_amount = Uint128::new(500);
-
     let owner = "owner1".to_string();
-    let token_contract = "token".to_string();
-
-    init(&mut deps, owner, token_contract, validator.address.clone());
-
-    // register_validator
+    init(
+        &mut deps,
+        owner.clone(),
+        "token".to_string(),
+        validator.address.clone(),
+    );
     do_register_validator(deps.as_mut(), validator.clone());
 
-    let bob = "bob".to_string();
-    let bond_msg = ExecuteMsg::Bond {
-        validator: validator.address.clone(),
+    let update_prams = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: Some(Decimal::percent(10)),
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: None,
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: Some(true),
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
     };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        update_prams,
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&owner, &[]),
+        ExecuteMsg::SetKeeperConfig {
+            reward: Uint128::new(6),
+            min_interval: 10,
+        },
+    )
+    .unwrap();
 
-    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
-
-    //set bob's balance to 10 in token contract
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
-
-    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg).unwrap();
-    assert_eq!(2, res.messages.len());
-
-    let delegate = &res.messages[0].msg;
-    match delegate {
-        CosmosMsg::Staking(StakingMsg::Delegate { validator, amount }) => {
-            assert_eq!(validator.as_str(), DEFAULT_VALIDATOR.to_string());
-            assert_eq!(amount, &coin(bond_amount.u128(), "uluna"));
-        }
-        _ => panic!("Unexpected message: {:?}", delegate),
-    }
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &INITIAL_DEPOSIT_AMOUNT)],
+    )]);
+    set_delegation(
+        &mut deps.querier,
+        validator,
+        INITIAL_DEPOSIT_AMOUNT.u128(),
+        "uluna",
+    );
 
-    set_delegation(&mut deps.querier, validator, bond_amount.u128(), "uluna");
+    // 10 of the compounded rewards are accrued as protocol fee
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewards_contract", &[Coin::new(100, "uluna")]),
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap();
 
-    let res = execute_unbond(deps.as_mut(), mock_env(), info, unbond_amount, bob.clone()).unwrap();
-    assert_eq!(1, res.messages.len());
+    let keeper = mock_info("keeper", &[]);
+    let mut env = mock_env();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        keeper.clone(),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::CompoundTooSoon {});
 
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9500))])]);
+    env.block.time = env.block.time.plus_seconds(10);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        keeper.clone(),
+        ExecuteMsg::UpdateGlobalIndex {},
+    )
+    .unwrap();
+    assert!(res.messages.contains(&SubMsg::new(BankMsg::Send {
+        to_address: "keeper".to_string(),
+        amount: coins(6, "uluna"),
+    })));
+    assert!(res.attributes.contains(&attr("keeper_reward", "6")));
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.prev_hub_balance, Uint128::new(4));
+
+    // only the rest of the accrued fee is paid
+    env.block.time = env.block.time.plus_seconds(10);
+    let res = execute(deps.as_mut(), env, keeper, ExecuteMsg::UpdateGlobalIndex {}).unwrap();
+    assert!(res.messages.contains(&SubMsg::new(BankMsg::Send {
+        to_address: "keeper".to_string(),
+        amount: coins(4, "uluna"),
+    })));
+    assert_eq!(
+        ACCRUED_FEE.load(deps.as_ref().storage).unwrap(),
+        Uint128::zero()
+    );
+}
 
-    deps.querier.with_native_balances(&[(
-        MOCK_CONTRACT_ADDR.to_string(),
-        Coin {
-            denom: "uluna".to_string(),
-            amount: Uint128::new(0),
+/// Covers if the hub accepts the exchange rate update the rewards contract sends
+/// while coins other than the underlying coin are allowed on the rewards contract.
+#[test]
+pub fn proper_update_exchange_rate_from_rewards_contract() {
+    let mut rewards_deps = mock_dependencies_with_balance(&[coin(500, "uluna"), coin(100, "uusd")]);
+    let rewards_owner = mock_info("owner1", &[]);
+    prism_rewards::contract::instantiate(
+        rewards_deps.as_mut(),
+        mock_env(),
+        rewards_owner.clone(),
+        RewardsInstantiateMsg {
+            hub_addr: MOCK_CONTRACT_ADDR.to_string(),
+            underlying_coin_denom: "uluna".to_string(),
+            min_process_amount: Uint128::zero(),
         },
-    )]);
-
-    let info = mock_info(&bob, &[]);
-    let mut env = mock_env();
-    //set the block time 30 seconds from now.
+    )
+    .unwrap();
+    prism_rewards::contract::execute(
+        rewards_deps.as_mut(),
+        mock_env(),
+        rewards_owner,
+        RewardsExecuteMsg::UpdateAllowedDenoms {
+            denoms: vec!["uluna".to_string(), "uusd".to_string()],
+        },
+    )
+    .unwrap();
+    let res = prism_rewards::contract::execute(
+        rewards_deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ProcessRewards {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    let (msg, funds) = match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            msg,
+            funds,
+        }) => {
+            assert_eq!(contract_addr, MOCK_CONTRACT_ADDR);
+            (msg.clone(), funds.clone())
+        }
+        _ => panic!("Unexpected message: {:?}", res.messages[0]),
+    };
+    assert_eq!(funds, vec![coin(500, "uluna")]);
 
-    let current_batch = QueryMsg::CurrentBatch {};
-    let query_batch: CurrentBatchResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), current_batch).unwrap()).unwrap();
-    assert_eq!(query_batch.id, 1);
-    assert_eq!(query_batch.requested_with_fee, unbond_amount);
+    // deliver the message to the hub as the rewards contract
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
 
-    env.block.time = env.block.time.plus_seconds(1000);
-    let wdraw_unbonded_msg = ExecuteMsg::WithdrawUnbonded {};
-    let wdraw_unbonded_res = execute(
-        deps.as_mut(),
-        env.clone(),
-        info.clone(),
-        wdraw_unbonded_msg.clone(),
+    init(
+        &mut deps,
+        "owner1".to_string(),
+        "token".to_string(),
+        validator.address.clone(),
     );
-    assert!(wdraw_unbonded_res.is_err());
-    assert_eq!(
-        wdraw_unbonded_res.unwrap_err(),
-        StdError::generic_err("No withdrawable uluna assets are available yet")
+    do_register_validator(deps.as_mut(), validator.clone());
+    set_delegation(
+        &mut deps.querier,
+        validator,
+        INITIAL_DEPOSIT_AMOUNT.u128(),
+        "uluna",
     );
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &INITIAL_DEPOSIT_AMOUNT)],
+    )]);
 
-    // trigger undelegation message
-    let res = execute_unbond(
+    execute(
         deps.as_mut(),
-        env.clone(),
-        info.clone(),
-        unbond_amount,
-        bob.clone(),
+        mock_env(),
+        mock_info("rewards_contract", &funds),
+        from_binary(&msg).unwrap(),
     )
     .unwrap();
-    assert_eq!(2, res.messages.len());
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9000))])]);
-
-    let current_batch = QueryMsg::CurrentBatch {};
-    let query_batch: CurrentBatchResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), current_batch).unwrap()).unwrap();
-    assert_eq!(query_batch.id, 2);
-    assert_eq!(query_batch.requested_with_fee, Uint128::zero());
+}
 
-    let all_batches = AllHistory {
-        start_from: None,
-        limit: None,
-    };
-    let res: AllHistoryResponse =
-        from_binary(&query(deps.as_ref(), env.clone(), all_batches).unwrap()).unwrap();
-    assert_eq!(res.history[0].amount, Uint128::new(1000));
-    assert_eq!(res.history[0].withdraw_rate.to_string(), "1");
-    assert!(!res.history[0].released);
-    assert_eq!(res.history[0].batch_id, 1);
+/// Covers if the keeper reward and interval are stored together
+/// and the interval is validated against the epoch period.
+#[test]
+pub fn proper_set_keeper_config() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
 
-    //this query should be zero since the undelegated period is not passed
-    let withdrawable = WithdrawableUnbonded {
-        address: bob.clone(),
-    };
-    let query_with = query(deps.as_ref(), mock_env(), withdrawable).unwrap();
-    let res: WithdrawableUnbondedResponse = from_binary(&query_with).unwrap();
-    assert_eq!(res.withdrawable, Uint128::zero());
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
 
-    env.block.time = env.block.time.plus_seconds(1091);
+    init(&mut deps, owner.clone(), token_contract, validator.address);
 
-    // fabricate balance of the hub contract
-    deps.querier.with_native_balances(&[(
-        MOCK_CONTRACT_ADDR.to_string(),
-        Coin {
-            denom: "uluna".to_string(),
-            amount: Uint128::new(900),
-        },
-    )]);
-    //first query AllUnbondedRequests
-    let all_unbonded = UnbondRequests {
-        address: bob.clone(),
-    };
-    let query_unbonded = query(deps.as_ref(), env.clone(), all_unbonded).unwrap();
-    let res: UnbondRequestsResponse = from_binary(&query_unbonded).unwrap();
-    assert_eq!(res.requests.len(), 1);
-    //the amount should be 10
-    assert_eq!(&res.address, &bob);
-    assert_eq!(res.requests[0].1, Uint128::new(1000));
-    assert_eq!(res.requests[0].0, 1);
+    let keeper_config: KeeperConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::KeeperConfig {}).unwrap()).unwrap();
+    assert_eq!(keeper_config.reward, Uint128::zero());
+    assert_eq!(keeper_config.min_interval, 0);
 
-    //check with query
-    //this query does not reflect the actual withdrawable
-    let withdrawable = WithdrawableUnbonded {
-        address: bob.clone(),
+    // only the admin can set the keeper config
+    let set_keeper_config = ExecuteMsg::SetKeeperConfig {
+        reward: Uint128::new(1000),
+        min_interval: 20,
     };
-    let query_with = query(deps.as_ref(), env.clone(), withdrawable).unwrap();
-    let res: WithdrawableUnbondedResponse = from_binary(&query_with).unwrap();
-    assert_eq!(res.withdrawable, Uint128::new(1000));
+    let invalid_info = mock_info("invalid", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        invalid_info,
+        set_keeper_config.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::NotAdmin {});
 
-    let success_res = execute(deps.as_mut(), env.clone(), info, wdraw_unbonded_msg).unwrap();
+    let owner_info = mock_info(&owner, &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info, set_keeper_config).unwrap();
+    assert_eq!(res.messages.len(), 0);
 
-    assert_eq!(success_res.messages.len(), 1);
+    let keeper_config: KeeperConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::KeeperConfig {}).unwrap()).unwrap();
+    assert_eq!(keeper_config.reward, Uint128::new(1000));
+    assert_eq!(keeper_config.min_interval, 20);
 
-    let sent_message = &success_res.messages[0].msg;
-    match sent_message {
-        CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
-            assert_eq!(to_address, &bob);
-            assert_eq!(amount[0].amount, Uint128::new(899))
-        }
+    // the interval is the compound interval of the parameters
+    let params: Parameters =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Parameters {}).unwrap()).unwrap();
+    assert_eq!(params.min_compound_interval, 20);
 
-        _ => panic!("Unexpected message: {:?}", sent_message),
-    }
+    // the interval can not be longer than an epoch
+    let set_keeper_config = ExecuteMsg::SetKeeperConfig {
+        reward: Uint128::new(10),
+        min_interval: 31,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info, set_keeper_config).unwrap_err();
+    assert_eq!(
+        res,
+        HubError::Std(StdError::generic_err(
+            "The keeper min interval can not be longer than the epoch period 30"
+        ))
+    );
 
-    // there should not be any result
-    let withdrawable = WithdrawableUnbonded { address: bob };
-    let query_with: WithdrawableUnbondedResponse =
-        from_binary(&query(deps.as_ref(), env, withdrawable).unwrap()).unwrap();
-    assert_eq!(query_with.withdrawable, Uint128::new(0));
+    // the previous config must be kept
+    let keeper_config: KeeperConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::KeeperConfig {}).unwrap()).unwrap();
+    assert_eq!(keeper_config.reward, Uint128::new(1000));
+    assert_eq!(keeper_config.min_interval, 20);
 
-    let all_batches = AllHistory {
-        start_from: None,
-        limit: None,
+    // a zero interval disables the throttle
+    let set_keeper_config = ExecuteMsg::SetKeeperConfig {
+        reward: Uint128::new(1000),
+        min_interval: 0,
     };
-    let res: AllHistoryResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), all_batches).unwrap()).unwrap();
-    assert_eq!(res.history[0].amount, Uint128::new(1000));
-    assert_eq!(res.history[0].applied_exchange_rate.to_string(), "1");
-    assert_eq!(res.history[0].withdraw_rate.to_string(), "0.899");
-    assert!(res.history[0].released);
-    assert_eq!(res.history[0].batch_id, 1);
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info, set_keeper_config).unwrap();
+
+    let keeper_config: KeeperConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::KeeperConfig {}).unwrap()).unwrap();
+    assert_eq!(keeper_config.min_interval, 0);
 }
 
-/// Covers if the signed integer works properly,
-/// the exception when a user sends rogue coin.
+/// Covers if the compound health reports a stall once the supply is
+/// too large for a one unit reward to move the exchange rate.
 #[test]
-pub fn proper_withdraw_unbond_with_dummies() {
+pub fn proper_compound_health() {
     let mut deps = dependencies(&[]);
 
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
     set_validator_mock(&mut deps.querier);
 
-    let bond_amount = Uint128::new(10000);
-    let unbond_amount = Uint128::new(500);
-
     let owner = "owner1".to_string();
     let token_contract = "token".to_string();
+    let bob = "bob".to_string();
 
-    init(&mut deps, owner, token_contract, validator.address.clone());
+    init(&mut deps, owner, token_contract, validator.address);
 
-    // register_validator
-    do_register_validator(deps.as_mut(), validator.clone());
+    deps.querier
+        .with_token_balances(&[(&"token".to_string(), &[(&bob, &INITIAL_DEPOSIT_AMOUNT)])]);
 
-    let bob = "bob".to_string();
-    let bond_msg = ExecuteMsg::Bond {
-        validator: validator.address.clone(),
-    };
+    let health: CompoundHealthResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::CompoundHealth {}).unwrap())
+            .unwrap();
+    assert!(!health.stalled);
+    assert_eq!(health.min_effective_reward, Uint128::new(1));
 
-    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
+    // a pool of 2 * 10^18 needs at least two units of reward
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&bob, &Uint128::new(2_000_000_000_000_000_000u128))],
+    )]);
 
-    //set bob's balance to 10 in token contract
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+    let health: CompoundHealthResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::CompoundHealth {}).unwrap())
+            .unwrap();
+    assert!(health.stalled);
+    assert_eq!(health.min_effective_reward, Uint128::new(2));
+}
 
-    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg).unwrap();
-    assert_eq!(2, res.messages.len());
+/// Covers if the staked value follows the exchange rate after a compound,
+/// and the query fails before the token contract is registered.
+#[test]
+pub fn proper_staked_value() {
+    let mut deps = dependencies(&[]);
 
-    set_delegation(
-        &mut deps.querier,
-        validator.clone(),
-        bond_amount.u128(),
-        "uluna",
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let bob = "bob".to_string();
+
+    let msg = InstantiateMsg {
+        epoch_period: 30,
+        underlying_coin_denom: "uluna".to_string(),
+        unbonding_period: 2,
+        peg_recovery_fee: Decimal::zero(),
+        er_threshold: Decimal::one(),
+        validator: validator.address.clone(),
+        protocol_fee: Default::default(),
+        rewards_contract: "rewards_contract".to_string(),
+        start_paused: None,
+    };
+    let owner_info = mock_info(&owner, &[coin(1000000, "uluna")]);
+    instantiate(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
+
+    let staked_value = QueryMsg::StakedValue {
+        address: bob.clone(),
+    };
+    let res = query(deps.as_ref(), mock_env(), staked_value.clone()).unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err("The token contract has not been registered")
     );
 
-    let res = execute_unbond(deps.as_mut(), mock_env(), info, unbond_amount, bob.clone()).unwrap();
-    assert_eq!(1, res.messages.len());
+    let register_msg = UpdateConfig {
+        token_contract: Some("token".to_string()),
+        protocol_fee_collector: None,
+        reward_validator: None,
+        rewards_contract: None,
+    };
+    execute(deps.as_mut(), mock_env(), owner_info, register_msg).unwrap();
 
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9500))])]);
+    // register_validator
+    do_register_validator(deps.as_mut(), validator.clone());
 
-    deps.querier.with_native_balances(&[(
-        MOCK_CONTRACT_ADDR.to_string(),
-        Coin {
-            denom: "uluna".to_string(),
-            amount: Uint128::new(0),
-        },
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[
+            (&MOCK_CONTRACT_ADDR.to_string(), &INITIAL_DEPOSIT_AMOUNT),
+            (&bob, &Uint128::new(1000)),
+        ],
     )]);
+    do_bond(
+        deps.as_mut(),
+        bob.clone(),
+        Uint128::new(1000),
+        validator.clone(),
+    );
+    set_delegation(&mut deps.querier, validator, 1001000, "uluna");
 
-    let mut env = mock_env();
-    let info = mock_info(&bob, &[]);
-    //set the block time 30 seconds from now.
+    let res: StakedValueResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), staked_value.clone()).unwrap()).unwrap();
+    assert_eq!(res.bluna_balance, Uint128::new(1000));
+    assert_eq!(res.underlying_value, Uint128::new(1000));
 
-    env.block.time = env.block.time.plus_seconds(31);
-    // trigger undelegation message
-    let res = execute_unbond(
+    // compound 1001 uluna on a supply of 1001000, the rate becomes 1.001
+    let info = mock_info("rewards_contract", &[Coin::new(1001, "uluna")]);
+    execute(
         deps.as_mut(),
-        env.clone(),
-        info.clone(),
-        unbond_amount,
-        bob.clone(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateExchangeRate {},
     )
     .unwrap();
-    assert_eq!(2, res.messages.len());
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(9000))])]);
 
-    // slashing
+    let res: StakedValueResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), staked_value).unwrap()).unwrap();
+    assert_eq!(res.bluna_balance, Uint128::new(1000));
+    assert_eq!(res.underlying_value, Uint128::new(1001));
+}
+
+/// Covers if compounding fails once the exchange rate would exceed max_exchange_rate.
+#[test]
+pub fn proper_max_exchange_rate() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &INITIAL_DEPOSIT_AMOUNT)],
+    )]);
     set_delegation(
         &mut deps.querier,
         validator,
-        bond_amount.u128() - 2000,
+        INITIAL_DEPOSIT_AMOUNT.u128(),
         "uluna",
     );
 
-    let res = execute_unbond(
+    let update_params = UpdateParams {
+        epoch_period: None,
+        unbonding_period: None,
+        peg_recovery_fee: None,
+        er_threshold: None,
+        protocol_fee: None,
+        min_undelegate_per_validator: None,
+        max_validators: None,
+        max_exchange_rate: Some(Decimal::percent(105)),
+        max_requests_per_batch: None,
+        track_positive_drift: None,
+        round_protocol_fee: None,
+        accrue_protocol_fee: None,
+        round_up_peg_fee: None,
+        min_compound_interval: None,
+        restake_dust_threshold: None,
+        min_protocol_fee_transfer: None,
+        max_withdraws_per_update: None,
+        unbond_return_denom: None,
+        fee_free_until: None,
+        max_validator_commission: None,
+        instant_redeem_fee: None,
+        max_open_unbond_batches_per_user: None,
+        unbond_cooloff: None,
+        liquidity_reserve_ratio: None,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    execute(deps.as_mut(), mock_env(), owner_info, update_params).unwrap();
+
+    // an inflated reward would push the exchange rate to 1.1
+    let info = mock_info("rewards_contract", &[Coin::new(100000, "uluna")]);
+    let res = execute(
         deps.as_mut(),
-        env.clone(),
-        info.clone(),
-        unbond_amount,
-        bob.clone(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateExchangeRate {},
     )
-    .unwrap();
-    assert_eq!(1, res.messages.len());
-
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(8500))])]);
+    .unwrap_err();
+    assert_eq!(
+        res,
+        HubError::Std(StdError::generic_err(
+            "The exchange rate 1.1 exceeds the maximum exchange rate 1.05"
+        ))
+    );
 
-    env.block.time = env.block.time.plus_seconds(31);
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.exchange_rate, Decimal::one());
 
-    let res = execute_unbond(
+    // compounding below the bound still works
+    let info = mock_info("rewards_contract", &[Coin::new(10000, "uluna")]);
+    execute(
         deps.as_mut(),
-        env.clone(),
-        info.clone(),
-        unbond_amount,
-        bob.clone(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateExchangeRate {},
     )
     .unwrap();
-    assert_eq!(2, res.messages.len());
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &Uint128::new(8000))])]);
 
-    // fabricate balance of the hub contract
-    deps.querier.with_native_balances(&[(
-        MOCK_CONTRACT_ADDR.to_string(),
-        Coin {
-            denom: "uluna".to_string(),
-            amount: Uint128::new(2200),
-        },
-    )]);
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.exchange_rate, Decimal::percent(101));
+}
 
-    env.block.time = env.block.time.plus_seconds(120);
-    let wdraw_unbonded_msg = ExecuteMsg::WithdrawUnbonded {};
-    let success_res = execute(deps.as_mut(), env, info, wdraw_unbonded_msg).unwrap();
+/// Covers if compounded rewards are delegated to the reward validator,
+/// and the reward validator is cleared when it is deregistered or unset.
+#[test]
+pub fn proper_reward_validator() {
+    let mut deps = dependencies(&[]);
 
-    assert_eq!(success_res.messages.len(), 1);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    let validator3 = sample_validator(DEFAULT_VALIDATOR3.to_string());
+    set_validator_mock(&mut deps.querier);
 
-    let all_batches = AllHistory {
-        start_from: None,
-        limit: None,
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
+
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
+
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_register_validator(deps.as_mut(), validator2.clone());
+
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &INITIAL_DEPOSIT_AMOUNT)],
+    )]);
+
+    // the reward validator must be whitelisted
+    let update_config = UpdateConfig {
+        token_contract: None,
+        protocol_fee_collector: None,
+        reward_validator: Some(validator3.address.clone()),
+        rewards_contract: None,
     };
-    let res: AllHistoryResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), all_batches).unwrap()).unwrap();
-    assert_eq!(res.history[0].amount, Uint128::new(1000));
-    assert_eq!(res.history[0].withdraw_rate.to_string(), "1.164");
-    assert!(res.history[0].released);
-    assert_eq!(res.history[0].batch_id, 1);
-    assert_eq!(res.history[1].amount, Uint128::new(1000));
-    assert_eq!(res.history[1].withdraw_rate.to_string(), "1.033");
-    assert!(res.history[1].released);
-    assert_eq!(res.history[1].batch_id, 2);
+    let owner_info = mock_info(&owner, &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), update_config).unwrap_err();
+    assert_eq!(
+        res,
+        HubError::Std(StdError::generic_err(
+            "The reward validator must be whitelisted"
+        ))
+    );
 
-    let expected = (res.history[0].withdraw_rate * res.history[0].amount)
-        + res.history[1].withdraw_rate * res.history[1].amount;
-    let sent_message = &success_res.messages[0].msg;
-    match sent_message {
-        CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
-            assert_eq!(to_address, &bob);
-            assert_eq!(amount[0].amount, expected)
-        }
+    let update_config = UpdateConfig {
+        token_contract: None,
+        protocol_fee_collector: None,
+        reward_validator: Some(validator2.address.clone()),
+        rewards_contract: None,
+    };
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_config).unwrap();
 
-        _ => panic!("Unexpected message: {:?}", sent_message),
-    }
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.reward_validator, Some(validator2.address.clone()));
 
-    // there should not be any result
-    let withdrawable = WithdrawableUnbonded { address: bob };
-    let query_with: WithdrawableUnbondedResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), withdrawable).unwrap()).unwrap();
-    assert_eq!(query_with.withdrawable, Uint128::new(0));
+    // only the first validator has a delegation
+    set_delegation(
+        &mut deps.querier,
+        validator.clone(),
+        INITIAL_DEPOSIT_AMOUNT.u128(),
+        "uluna",
+    );
+
+    let info = mock_info("rewards_contract", &[Coin::new(100, "uluna")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: validator2.address.clone(),
+            amount: coin(100, "uluna"),
+        }))]
+    );
+
+    let msg = ExecuteMsg::DeregisterValidator {
+        validator: validator2.address,
+        replacement: None,
+    };
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
+
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.reward_validator, None);
+
+    // the admin can unset the reward validator to compound into a random delegation
+    let update_config = UpdateConfig {
+        token_contract: None,
+        protocol_fee_collector: None,
+        reward_validator: Some(validator.address.clone()),
+        rewards_contract: None,
+    };
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), update_config).unwrap();
+    let unset = ExecuteMsg::UnsetParams {
+        params: vec![OptionalParam::RewardValidator],
+    };
+    execute(deps.as_mut(), mock_env(), owner_info, unset).unwrap();
+
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.reward_validator, None);
+
+    let info = mock_info("rewards_contract", &[Coin::new(100, "uluna")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::UpdateExchangeRate {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: validator.address,
+            amount: coin(100, "uluna"),
+        }))]
+    );
 }
 
-/// Covers if the state/parameters storage is updated to the given value,
-/// who sends the message, and if
-/// RewardUpdateDenom message is sent to the reward contract
+/// Covers if a validator that does not accept new bonds can not be the reward validator,
+/// nor receive compounded rewards or the delegation of a deregistered validator.
 #[test]
-pub fn test_update_params() {
+pub fn proper_no_new_bonds_delegation_targets() {
     let mut deps = dependencies(&[]);
 
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
+    let validator3 = sample_validator(DEFAULT_VALIDATOR3.to_string());
     set_validator_mock(&mut deps.querier);
 
-    //test with no swap denom.
-    let update_prams = UpdateParams {
-        epoch_period: Some(20),
-        unbonding_period: None,
-        peg_recovery_fee: None,
-        er_threshold: None,
-        protocol_fee: None,
-    };
     let owner = "owner1".to_string();
     let token_contract = "token".to_string();
 
-    init(&mut deps, owner, token_contract, validator.address);
+    init(
+        &mut deps,
+        owner.clone(),
+        token_contract,
+        validator.address.clone(),
+    );
 
-    let invalid_info = mock_info("invalid", &[]);
-    let res = execute(
+    do_register_validator(deps.as_mut(), validator.clone());
+    do_register_validator(deps.as_mut(), validator2.clone());
+
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &INITIAL_DEPOSIT_AMOUNT)],
+    )]);
+
+    let owner_info = mock_info(&owner, &[]);
+    execute(
         deps.as_mut(),
         mock_env(),
-        invalid_info,
-        update_prams.clone(),
-    );
-    assert_eq!(
-        res.unwrap_err(),
-        StdError::generic_err("Caller is not admin")
-    );
-    let creator_info = mock_info("owner1", &[]);
-    let res = execute(deps.as_mut(), mock_env(), creator_info, update_prams).unwrap();
-    assert_eq!(res.messages.len(), 0);
+        owner_info.clone(),
+        ExecuteMsg::SoftDeregisterValidator {
+            validator: validator2.address.clone(),
+        },
+    )
+    .unwrap();
 
-    let params: Parameters =
-        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Parameters {}).unwrap()).unwrap();
-    assert_eq!(params.epoch_period, 20);
-    assert_eq!(params.underlying_coin_denom, "uluna");
-    assert_eq!(params.unbonding_period, 2);
-    assert_eq!(params.peg_recovery_fee, Decimal::zero());
-    assert_eq!(params.er_threshold, Decimal::one());
+    // the reward validator must accept new bonds
+    let update_config = UpdateConfig {
+        token_contract: None,
+        protocol_fee_collector: None,
+        reward_validator: Some(validator2.address.clone()),
+        rewards_contract: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), update_config).unwrap_err();
+    assert_eq!(res, HubError::ValidatorNoNewBonds {});
 
-    //test with some swap_denom.
-    let update_prams = UpdateParams {
-        epoch_period: None,
-        unbonding_period: Some(3),
-        peg_recovery_fee: Some(Decimal::one()),
-        er_threshold: Some(Decimal::zero()),
-        protocol_fee: None,
+    // the random compounding skips the delegation of the second validator
+    let delegations: [FullDelegation; 2] = [
+        sample_delegation(validator.address.clone(), coin(500000, "uluna")),
+        sample_delegation(validator2.address.clone(), coin(500000, "uluna")),
+    ];
+    let validators: [Validator; 3] = [validator.clone(), validator2.clone(), validator3.clone()];
+    set_delegation_query(&mut deps.querier, &delegations, &validators);
+
+    for _ in 0..4 {
+        let info = mock_info("rewards_contract", &[Coin::new(100, "uluna")]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::UpdateExchangeRate {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+                validator: validator.address.clone(),
+                amount: coin(100, "uluna"),
+            }))]
+        );
+    }
+
+    // the delegation of a deregistered validator only goes to a validator accepting bonds
+    do_register_validator(deps.as_mut(), validator3.clone());
+    let msg = ExecuteMsg::DeregisterValidator {
+        validator: validator.address.clone(),
+        replacement: None,
     };
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Staking(StakingMsg::Redelegate {
+            src_validator: validator.address,
+            dst_validator: validator3.address.clone(),
+            amount: coin(500000, "uluna"),
+        })
+    );
 
-    //the result must be 1
-    let creator_info = mock_info("owner1", &[]);
-    let res = execute(deps.as_mut(), mock_env(), creator_info, update_prams).unwrap();
-    assert_eq!(res.messages.len(), 0);
+    let msg = ExecuteMsg::DeregisterValidator {
+        validator: validator3.address.clone(),
+        replacement: Some(validator2.address),
+    };
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap_err();
+    assert_eq!(res, HubError::ValidatorNoNewBonds {});
 
-    let params: Parameters =
-        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Parameters {}).unwrap()).unwrap();
-    assert_eq!(params.epoch_period, 20);
-    assert_eq!(params.underlying_coin_denom, "uluna");
-    assert_eq!(params.unbonding_period, 3);
-    assert_eq!(params.peg_recovery_fee, Decimal::one());
-    assert_eq!(params.er_threshold, Decimal::zero());
+    let msg = ExecuteMsg::DeregisterValidator {
+        validator: validator3.address,
+        replacement: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), owner_info, msg).unwrap_err();
+    assert_eq!(
+        res,
+        HubError::Std(StdError::generic_err("No validator accepts new bonds"))
+    );
 }
 
-/// Covers if peg recovery is applied (in "bond", "unbond",
-/// and "withdraw_unbonded" messages) in case of a slashing event
+/// Covers if consecutive compounds in the same block pick the validator
+/// with an advancing selection nonce.
 #[test]
-pub fn proper_recovery_fee() {
+pub fn proper_selection_nonce() {
     let mut deps = dependencies(&[]);
+
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
     set_validator_mock(&mut deps.querier);
 
-    let update_prams = UpdateParams {
-        epoch_period: None,
-        unbonding_period: None,
-        peg_recovery_fee: Some(Decimal::from_ratio(Uint128::new(1), Uint128::new(1000))),
-        er_threshold: Some(Decimal::from_ratio(Uint128::new(99), Uint128::new(100))),
-        protocol_fee: None,
-    };
     let owner = "owner1".to_string();
     let token_contract = "token".to_string();
 
-    let bond_amount = Uint128::new(1000000u128);
-    let unbond_amount = Uint128::new(100000u128);
-
-    init(
-        &mut deps,
-        owner,
-        token_contract.clone(),
-        validator.address.clone(),
-    );
-
-    let creator_info = mock_info("owner1", &[]);
-    let res = execute(deps.as_mut(), mock_env(), creator_info, update_prams).unwrap();
-    assert_eq!(res.messages.len(), 0);
-
-    let get_params = QueryMsg::Parameters {};
-    let parmas: Parameters =
-        from_binary(&query(deps.as_ref(), mock_env(), get_params).unwrap()).unwrap();
-    assert_eq!(parmas.epoch_period, 30);
-    assert_eq!(parmas.underlying_coin_denom, "uluna");
-    assert_eq!(parmas.unbonding_period, 2);
-    assert_eq!(parmas.peg_recovery_fee.to_string(), "0.001");
-    assert_eq!(parmas.er_threshold.to_string(), "0.99");
+    init(&mut deps, owner, token_contract, validator.address.clone());
 
-    // register_validator
     do_register_validator(deps.as_mut(), validator.clone());
+    do_register_validator(deps.as_mut(), validator2.clone());
 
-    let bob = "bob".to_string();
-    let bond_msg = ExecuteMsg::Bond {
-        validator: validator.address.clone(),
-    };
+    deps.querier.with_token_balances(&[(
+        &"token".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &INITIAL_DEPOSIT_AMOUNT)],
+    )]);
 
-    //this will set the balance of the user in token contract
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+    let delegations: [FullDelegation; 2] = [
+        sample_delegation(validator.address.clone(), coin(500000, "uluna")),
+        sample_delegation(validator2.address.clone(), coin(500000, "uluna")),
+    ];
+    let validators: [Validator; 2] = [validator.clone(), validator2.clone()];
+    set_delegation_query(&mut deps.querier, &delegations, &validators);
 
-    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.selection_nonce, 0);
+
+    let env = mock_env();
+    for nonce in 0..2u64 {
+        let mut rng = XorShiftRng::seed_from_u64(selection_seed(env.block.height, nonce));
+        let expected = delegations[rng.gen_range(0, delegations.len())]
+            .validator
+            .clone();
+
+        let info = mock_info("rewards_contract", &[Coin::new(100, "uluna")]);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            ExecuteMsg::UpdateExchangeRate {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+                validator: expected,
+                amount: coin(100, "uluna"),
+            }))]
+        );
+    }
 
-    let res = execute(deps.as_mut(), mock_env(), info.clone(), bond_msg).unwrap();
-    assert_eq!(2, res.messages.len());
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.selection_nonce, 2);
+}
 
-    set_delegation(&mut deps.querier, validator.clone(), 900000, "uluna");
+#[test]
+pub fn proper_pause() {
+    let mut deps = dependencies(&[]);
 
-    let report_slashing = CheckSlashing {};
-    let res = execute(deps.as_mut(), mock_env(), info, report_slashing).unwrap();
-    assert_eq!(0, res.messages.len());
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
 
-    let ex_rate = QueryMsg::State {};
-    let query_exchange_rate: StateResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), ex_rate).unwrap()).unwrap();
-    assert_eq!(query_exchange_rate.exchange_rate.to_string(), "0.9");
+    let owner = "owner1".to_string();
+    let token_contract = "token".to_string();
 
-    //Bond again to see the applied result
-    let bob = "bob".to_string();
-    let bond_msg = ExecuteMsg::Bond {
-        validator: validator.address.clone(),
-    };
+    init(&mut deps, owner, token_contract, validator.address);
 
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+    let is_pause = PAUSE.load(&deps.storage).unwrap();
+    assert!(!is_pause);
 
-    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
+    let pause = ExecuteMsg::Pause {};
 
-    let res = execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
-    let mint_amount = decimal_division(
-        bond_amount,
-        Decimal::from_ratio(Uint128::new(9), Uint128::new(10)),
-    );
-    let max_peg_fee = mint_amount * parmas.peg_recovery_fee;
-    let required_peg_fee = ((bond_amount + mint_amount + Uint128::zero())
-        .checked_sub(Uint128::new(900000) + bond_amount))
-    .unwrap();
-    let peg_fee = Uint128::min(max_peg_fee, required_peg_fee);
-    let mint_amount_with_fee = (mint_amount.checked_sub(peg_fee)).unwrap();
+    let owner_info = mock_info("owner2", &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info, pause.clone()).is_err();
+    assert!(res);
 
-    let mint_msg = &res.messages[1].msg;
-    match mint_msg {
-        CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: _,
-            msg,
-            funds: _,
-        }) => assert_eq!(
-            msg,
-            &to_binary(&Mint {
-                recipient: bob.clone(),
-                amount: mint_amount_with_fee
-            })
-            .unwrap()
-        ),
-        _ => panic!("Unexpected message: {:?}", mint_msg),
-    }
+    let owner_info = mock_info("owner1", &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info, pause).unwrap();
+    assert_eq!(res.messages.len(), 0);
 
-    // check unbond message
-    let unbond = Unbond {};
-    let receive = Receive(Cw20ReceiveMsg {
-        sender: token_contract.clone(),
-        amount: unbond_amount,
-        msg: to_binary(&unbond).unwrap(),
-    });
+    let is_pause = PAUSE.load(&deps.storage).unwrap();
+    assert!(is_pause);
 
-    let new_balance = bond_amount.checked_sub(unbond_amount).unwrap();
+    // try to execute one
+    let register_msg = UpdateConfig {
+        token_contract: None,
+        protocol_fee_collector: None,
+        reward_validator: None,
+        rewards_contract: None,
+    };
 
-    let mut token_env = mock_env();
-    let token_info = mock_info(&token_contract, &[]);
-    let res = execute(
-        deps.as_mut(),
-        token_env.clone(),
-        token_info.clone(),
-        receive,
-    )
-    .unwrap();
-    assert_eq!(1, res.messages.len());
+    let owner_info = mock_info("owner1", &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info, register_msg).unwrap_err();
+    assert_eq!(res, HubError::Paused {});
+
+    // try to execute one
+    let register_msg = UpdateAdmin {
+        admin: "new owner".to_string(),
+    };
 
-    //check current batch
-    let bonded_with_fee =
-        unbond_amount * Decimal::from_ratio(Uint128::new(999), Uint128::new(1000));
-    let current_batch = QueryMsg::CurrentBatch {};
-    let query_batch: CurrentBatchResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), current_batch).unwrap()).unwrap();
-    assert_eq!(query_batch.id, 1);
-    assert_eq!(query_batch.requested_with_fee, bonded_with_fee);
+    let owner_info = mock_info("new owner", &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info, register_msg).unwrap_err();
+    assert_eq!(res, HubError::Paused {});
 
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &new_balance)])]);
+    let unpause = ExecuteMsg::Unpause {};
+    let owner_info = mock_info("owner1", &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info, unpause).unwrap();
+    assert_eq!(res.messages.len(), 0);
 
-    token_env.block.time = token_env.block.time.plus_seconds(60);
+    // try to execute one
+    let register_msg = UpdateConfig {
+        token_contract: None,
+        protocol_fee_collector: None,
+        reward_validator: None,
+        rewards_contract: None,
+    };
 
-    let second_unbond = Unbond {};
-    let receive = Receive(Cw20ReceiveMsg {
-        sender: token_contract,
-        amount: unbond_amount,
-        msg: to_binary(&second_unbond).unwrap(),
-    });
-    let res = execute(
-        deps.as_mut(),
-        token_env.clone(),
-        token_info.clone(),
-        receive,
-    )
-    .unwrap();
-    assert_eq!(2, res.messages.len());
+    let owner_info = mock_info("owner1", &[]);
+    let res = execute(deps.as_mut(), mock_env(), owner_info, register_msg).unwrap();
+    assert_eq!(res.messages.len(), 0);
+}
 
-    let ex_rate = QueryMsg::State {};
-    let query_exchange_rate: StateResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), ex_rate).unwrap()).unwrap();
-    let new_exchange = query_exchange_rate.exchange_rate;
+/// Covers if the contract can be instantiated without an initial bond,
+/// starts at an exchange rate of one, and accepts the first bond.
+#[test]
+pub fn proper_instantiate_without_bond() {
+    let mut deps = dependencies(&[]);
 
-    let expected = bonded_with_fee + bonded_with_fee;
-    let undelegate_message = &res.messages[0].msg;
-    match undelegate_message {
-        CosmosMsg::Staking(StakingMsg::Undelegate {
-            validator: val,
-            amount,
-        }) => {
-            assert_eq!(&validator.address, val);
-            assert_eq!(amount.amount, expected * new_exchange);
-        }
-        _ => panic!("Unexpected message: {:?}", mint_msg),
-    }
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
 
-    //got slashed during unbonding
-    deps.querier.with_native_balances(&[(
-        MOCK_CONTRACT_ADDR.to_string(),
-        Coin {
-            denom: "uluna".to_string(),
-            amount: Uint128::new(161870),
-        },
-    )]);
+    let owner = "owner1".to_string();
+    let bob = "bob".to_string();
 
-    token_env.block.time = token_env.block.time.plus_seconds(90);
-    //check withdrawUnbonded message
-    let withdraw_unbond_msg = ExecuteMsg::WithdrawUnbonded {};
-    let wdraw_unbonded_res =
-        execute(deps.as_mut(), token_env, token_info, withdraw_unbond_msg).unwrap();
-    assert_eq!(wdraw_unbonded_res.messages.len(), 1);
+    let msg = InstantiateMsg {
+        epoch_period: 30,
+        underlying_coin_denom: "uluna".to_string(),
+        unbonding_period: 2,
+        peg_recovery_fee: Decimal::zero(),
+        er_threshold: Decimal::one(),
+        validator: validator.address.clone(),
+        protocol_fee: Default::default(),
+        rewards_contract: "rewards_contract".to_string(),
+        start_paused: None,
+    };
+    let owner_info = mock_info(&owner, &[]);
+    let res = instantiate(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
 
-    let sent_message = &wdraw_unbonded_res.messages[0].msg;
-    let expected = ((expected
-        * new_exchange
-        * Decimal::from_ratio(Uint128::new(161870), expected * new_exchange))
-    .checked_sub(Uint128::new(1)))
-    .unwrap();
-    match sent_message {
-        CosmosMsg::Bank(BankMsg::Send {
-            to_address: _,
-            amount,
-        }) => {
-            assert_eq!(amount[0].amount, expected);
-        }
+    // no delegate message is sent without funds
+    assert_eq!(res.messages.len(), 2);
+    assert!(!res
+        .messages
+        .iter()
+        .any(|msg| matches!(msg.msg, CosmosMsg::Staking(StakingMsg::Delegate { .. }))));
 
-        _ => panic!("Unexpected message: {:?}", sent_message),
-    }
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.exchange_rate, Decimal::one());
+    assert_eq!(state.total_bond_amount, Uint128::zero());
 
-    let all_batches = AllHistory {
-        start_from: None,
-        limit: None,
+    let register_msg = UpdateConfig {
+        token_contract: Some("token".to_string()),
+        protocol_fee_collector: None,
+        reward_validator: None,
+        rewards_contract: None,
     };
-    let res: AllHistoryResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), all_batches).unwrap()).unwrap();
-    // amount should be 99 + 99 since we store the requested amount with peg fee applied.
-    assert_eq!(res.history[0].amount, bonded_with_fee + bonded_with_fee);
-    assert_eq!(res.history[0].applied_exchange_rate, new_exchange);
+    execute(deps.as_mut(), mock_env(), owner_info, register_msg).unwrap();
+    do_register_validator(deps.as_mut(), validator.clone());
+
+    let bond_amount = Uint128::new(1000);
+    let bond = ExecuteMsg::Bond {
+        validator: validator.address,
+    };
+    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
+    let res = execute(deps.as_mut(), mock_env(), info, bond).unwrap();
     assert_eq!(
-        res.history[0].withdraw_rate,
-        Decimal::from_ratio(Uint128::new(161869), bonded_with_fee + bonded_with_fee)
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: bob,
+                amount: bond_amount,
+            })
+            .unwrap(),
+            funds: vec![],
+        }))
     );
-    assert!(res.history[0].released);
-    assert_eq!(res.history[0].batch_id, 1);
+
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.exchange_rate, Decimal::one());
+    assert_eq!(state.total_bond_amount, bond_amount);
 }
 
-/// Covers if the storage affected by update_config are updated properly
+/// Covers if a failed registration of the initial validator keeps the initial bond
+/// on the hub instead of delegating it.
 #[test]
-pub fn proper_update_config() {
+pub fn proper_instantiate_register_failure() {
     let mut deps = dependencies(&[]);
 
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
     set_validator_mock(&mut deps.querier);
 
-    let owner = "owner1".to_string();
-    let new_owner = "new_owner".to_string();
-    let invalid_owner = "invalid_owner".to_string();
-    let token_contract = "token".to_string();
-    let protocol_fee_collector = "fee_collector".to_string();
-
-    init(&mut deps, owner.clone(), token_contract, validator.address);
-
-    let admin = Admin {};
-    let query_admin: AdminResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), admin).unwrap()).unwrap();
-    //make sure the other configs are still the same.
-    assert_eq!(query_admin.admin.unwrap(), owner);
-
-    // only the owner can call this message
-    let update_admin = UpdateAdmin {
-        admin: new_owner.clone(),
+    let msg = InstantiateMsg {
+        epoch_period: 30,
+        underlying_coin_denom: "uluna".to_string(),
+        unbonding_period: 2,
+        peg_recovery_fee: Decimal::zero(),
+        er_threshold: Decimal::one(),
+        validator: validator.address.clone(),
+        protocol_fee: Default::default(),
+        rewards_contract: "rewards_contract".to_string(),
+        start_paused: None,
     };
+    let owner_info = mock_info("owner1", &[coin(1000000, "uluna")]);
+    let res = instantiate(deps.as_mut(), mock_env(), owner_info, msg).unwrap();
+    assert_eq!(res.messages[0].id, INSTANTIATE_REGISTER_REPLY_ID);
+    assert_eq!(res.messages[0].reply_on, ReplyOn::Always);
 
-    let info = mock_info(&invalid_owner, &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, update_admin);
+    let failure = Reply {
+        id: INSTANTIATE_REGISTER_REPLY_ID,
+        result: SubMsgResult::Err("validator is not in the active set".to_string()),
+    };
+    let res = reply(deps.as_mut(), mock_env(), failure).unwrap();
+    assert_eq!(res.messages.len(), 0);
     assert_eq!(
-        res.unwrap_err(),
-        StdError::generic_err("Caller is not admin")
+        res.attributes[0].value,
+        "instantiate_register_validator_failed"
+    );
+    assert_eq!(
+        res.attributes[1].value,
+        "validator is not in the active set"
     );
 
-    // change the owner
-    let update_admin = UpdateAdmin {
-        admin: new_owner.clone(),
+    // the initial bond is not counted as bonded
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.total_bond_amount, Uint128::zero());
+
+    let query_validator = QueryMsg::WhitelistedValidators {
+        start_after: None,
+        limit: None,
+        reverse: None,
     };
+    let query_res: WhitelistedValidatorsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), query_validator).unwrap()).unwrap();
+    assert!(query_res.validators.is_empty());
+}
 
-    let info = mock_info(&owner, &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, update_admin).unwrap();
-    assert_eq!(res.messages.len(), 0);
+/// Covers if the contract can be instantiated paused, registers the initial
+/// validator anyway, and rejects bonds until the owner unpauses it.
+#[test]
+pub fn proper_start_paused() {
+    let mut deps = dependencies(&[]);
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
 
-    // let config = CONFIG.load(&deps.storage).unwrap();
-    let new_owner_raw = new_owner.clone();
-    let admin = ADMIN.get(deps.as_ref()).unwrap().unwrap();
-    assert_eq!(new_owner_raw, admin);
+    let msg = InstantiateMsg {
+        epoch_period: 30,
+        underlying_coin_denom: "uluna".to_string(),
+        unbonding_period: 2,
+        peg_recovery_fee: Decimal::zero(),
+        er_threshold: Decimal::one(),
+        validator: validator.address.clone(),
+        protocol_fee: Default::default(),
+        rewards_contract: "rewards_contract".to_string(),
+        start_paused: Some(true),
+    };
+    let owner_info = mock_info("owner1", &[coin(1000000, "uluna")]);
+    instantiate(deps.as_mut(), mock_env(), owner_info, msg).unwrap();
+    assert!(PAUSE.load(&deps.storage).unwrap());
 
-    // new owner can send the owner related messages
-    let update_prams = UpdateParams {
-        epoch_period: None,
-        unbonding_period: None,
-        peg_recovery_fee: None,
-        er_threshold: None,
-        protocol_fee: None,
+    // the registration message sent by instantiate is not blocked
+    let register_validator = ExecuteMsg::RegisterValidator {
+        validator: validator.address.clone(),
     };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        register_validator,
+    )
+    .unwrap();
 
-    let new_owner_info = mock_info(&new_owner, &[]);
-    let res = execute(deps.as_mut(), mock_env(), new_owner_info, update_prams).unwrap();
-    assert_eq!(res.messages.len(), 0);
+    let bond = ExecuteMsg::Bond {
+        validator: validator.address.clone(),
+    };
+    let bob_info = mock_info("bob", &[coin(100, "uluna")]);
+    let res = execute(deps.as_mut(), mock_env(), bob_info.clone(), bond.clone()).unwrap_err();
+    assert_eq!(res, HubError::Paused {});
 
-    //previous owner cannot send this message
-    let update_prams = UpdateParams {
-        epoch_period: None,
-        unbonding_period: None,
-        peg_recovery_fee: None,
-        er_threshold: None,
-        protocol_fee: None,
+    let owner_info = mock_info("owner1", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::Unpause {},
+    )
+    .unwrap();
+    let register_token = UpdateConfig {
+        token_contract: Some("token".to_string()),
+        protocol_fee_collector: None,
+        reward_validator: None,
+        rewards_contract: None,
     };
+    execute(deps.as_mut(), mock_env(), owner_info, register_token).unwrap();
 
-    let new_owner_info = mock_info(&owner, &[]);
-    let res = execute(deps.as_mut(), mock_env(), new_owner_info, update_prams);
-    assert_eq!(
-        res.unwrap_err(),
-        StdError::generic_err("Caller is not admin")
-    );
+    let res = execute(deps.as_mut(), mock_env(), bob_info, bond).unwrap();
+    assert_eq!(res.messages.len(), 2);
+}
 
-    let update_config = UpdateConfig {
-        token_contract: Some("new token".to_string()),
+/// Covers if the token contract is only registered when the hub is its minter.
+#[test]
+pub fn proper_register_token_minter() {
+    let mut deps = dependencies(&[]);
+
+    let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    set_validator_mock(&mut deps.querier);
+
+    let owner = "owner1".to_string();
+
+    let msg = InstantiateMsg {
+        epoch_period: 30,
+        underlying_coin_denom: "uluna".to_string(),
+        unbonding_period: 2,
+        peg_recovery_fee: Decimal::zero(),
+        er_threshold: Decimal::one(),
+        validator: validator.address,
+        protocol_fee: Default::default(),
+        rewards_contract: "rewards_contract".to_string(),
+        start_paused: None,
+    };
+    let owner_info = mock_info(&owner, &[coin(1000000, "uluna")]);
+    instantiate(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
+
+    // a token minted by someone else is rejected
+    deps.querier.with_token_minter("other_token", "someone");
+    let register_msg = UpdateConfig {
+        token_contract: Some("other_token".to_string()),
         protocol_fee_collector: None,
+        reward_validator: None,
+        rewards_contract: None,
     };
-    //cannot register the new token
-    let new_owner_info = mock_info(&new_owner, &[]);
-    let res = execute(deps.as_mut(), mock_env(), new_owner_info, update_config).unwrap_err();
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), register_msg).unwrap_err();
     assert_eq!(
         res,
-        StdError::generic_err(
-            "Token contract has been registered. Cannot change the token contract"
-        )
+        HubError::Std(StdError::generic_err(
+            "The hub must be the minter of the token contract"
+        ))
     );
 
-    let config = QueryMsg::Config {};
-    let config_query: ConfigResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), config).unwrap()).unwrap();
-    assert_eq!(config_query.token_contract.unwrap(), "token".to_string());
-
-    let admin = Admin {};
-    let query_admin: AdminResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), admin).unwrap()).unwrap();
-    //make sure the other configs are still the same.
-    assert_eq!(query_admin.admin.unwrap(), new_owner);
-
-    let update_config = UpdateConfig {
-        token_contract: None,
-        protocol_fee_collector: Some(protocol_fee_collector),
+    // the rejected token is not registered, so a valid one can still be set
+    let register_msg = UpdateConfig {
+        token_contract: Some("token".to_string()),
+        protocol_fee_collector: None,
+        reward_validator: None,
+        rewards_contract: None,
     };
-    let new_owner_info = mock_info(&new_owner, &[]);
-    let res = execute(deps.as_mut(), mock_env(), new_owner_info, update_config).unwrap();
-    assert_eq!(res.messages.len(), 0);
+    execute(deps.as_mut(), mock_env(), owner_info, register_msg).unwrap();
 
     let config = QueryMsg::Config {};
     let config_query: ConfigResponse =
         from_binary(&query(deps.as_ref(), mock_env(), config).unwrap()).unwrap();
-    assert_eq!(
-        config_query.protocol_fee_collector.unwrap(),
-        "fee_collector".to_string()
-    );
-
-    let admin = Admin {};
-    let query_admin: AdminResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), admin).unwrap()).unwrap();
-    //make sure the other configs are still the same.
-    assert_eq!(query_admin.admin.unwrap(), new_owner);
+    assert_eq!(config_query.token_contract, Some("token".to_string()));
 }
 
+/// Covers if the emergency undelegation only runs while paused, undelegates every
+/// delegation and sends the returned coins to the recovery address.
 #[test]
-pub fn proper_protocol_fee() {
+pub fn proper_emergency_undelegate_all() {
     let mut deps = dependencies(&[]);
+
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
+    let validator2 = sample_validator(DEFAULT_VALIDATOR2.to_string());
     set_validator_mock(&mut deps.querier);
 
-    let update_prams = UpdateParams {
-        epoch_period: None,
-        unbonding_period: None,
-        peg_recovery_fee: Some(Decimal::from_ratio(Uint128::new(1), Uint128::new(1000))),
-        er_threshold: Some(Decimal::from_ratio(Uint128::new(99), Uint128::new(100))),
-        protocol_fee: Some(Decimal::from_ratio(Uint128::new(1), Uint128::new(100))),
-    };
     let owner = "owner1".to_string();
     let token_contract = "token".to_string();
-    let protocol_fee_collector = "fee_collector".to_string();
-
-    let bond_amount = Uint128::new(1000000u128);
-
-    init(
-        &mut deps,
-        owner.clone(),
-        token_contract,
-        validator.address.clone(),
-    );
-
-    let creator_info = mock_info("owner1", &[]);
-    let res = execute(deps.as_mut(), mock_env(), creator_info, update_prams).unwrap();
-    assert_eq!(res.messages.len(), 0);
 
-    let get_params = QueryMsg::Parameters {};
-    let parmas: Parameters =
-        from_binary(&query(deps.as_ref(), mock_env(), get_params).unwrap()).unwrap();
-    assert_eq!(parmas.epoch_period, 30);
-    assert_eq!(parmas.underlying_coin_denom, "uluna");
-    assert_eq!(parmas.unbonding_period, 2);
-    assert_eq!(parmas.peg_recovery_fee.to_string(), "0.001");
-    assert_eq!(parmas.er_threshold.to_string(), "0.99");
-    assert_eq!(parmas.protocol_fee.to_string(), "0.01");
+    init(&mut deps, owner, token_contract, validator.address.clone());
 
-    // register_validator
-    do_register_validator(deps.as_mut(), validator.clone());
+    let delegations: [FullDelegation; 2] = [
+        sample_delegation(validator.address.clone(), coin(600, "uluna")),
+        sample_delegation(validator2.address.clone(), coin(400, "uluna")),
+    ];
+    let validators: [Validator; 2] = [validator.clone(), validator2.clone()];
+    set_delegation_query(&mut deps.querier, &delegations, &validators);
 
-    let bob = "bob".to_string();
-    let bond_msg = ExecuteMsg::Bond {
-        validator: validator.address.clone(),
+    let emergency = ExecuteMsg::EmergencyUndelegateAll {
+        recovery_note: "incident".to_string(),
+        recovery_address: "recovery".to_string(),
     };
 
-    //this will set the balance of the user in token contract
-    deps.querier
-        .with_token_balances(&[(&"token".to_string(), &[(&bob, &bond_amount)])]);
+    // the contract must be paused
+    let owner_info = mock_info("owner1", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        emergency.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::NotPaused {});
 
-    let info = mock_info(&bob, &[coin(bond_amount.u128(), "uluna")]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::Pause {},
+    )
+    .unwrap();
 
-    let res = execute(deps.as_mut(), mock_env(), info, bond_msg).unwrap();
-    assert_eq!(2, res.messages.len());
+    // only the admin can undelegate everything
+    let invalid_info = mock_info("invalid", &[]);
+    let res = execute(deps.as_mut(), mock_env(), invalid_info, emergency.clone()).unwrap_err();
+    assert_eq!(res, HubError::NotAdmin {});
 
-    // set balance before executing the exchange rate update
-    set_delegation(
-        &mut deps.querier,
-        validator.clone(),
-        bond_amount.u128(),
-        "uluna",
+    let res = execute(deps.as_mut(), mock_env(), owner_info.clone(), emergency).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Staking(StakingMsg::Undelegate {
+                validator: validator.address,
+                amount: coin(600, "uluna"),
+            })),
+            SubMsg::new(CosmosMsg::Staking(StakingMsg::Undelegate {
+                validator: validator2.address,
+                amount: coin(400, "uluna"),
+            })),
+        ]
     );
 
-    let reward_msg = ExecuteMsg::UpdateGlobalIndex {};
-
-    let info = mock_info(&owner, &[]);
-    let res = execute(deps.as_mut(), mock_env(), info, reward_msg).unwrap();
-    assert_eq!(2, res.messages.len());
-
-    let last_index_query = QueryMsg::State {};
-    let last_modification: StateResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), last_index_query).unwrap()).unwrap();
+    let log = EMERGENCY_LOG.load(&deps.storage).unwrap();
     assert_eq!(
-        &last_modification.last_index_modification,
-        &mock_env().block.time.seconds()
+        log,
+        (mock_env().block.time.seconds(), "incident".to_string())
+    );
+    assert_eq!(
+        EMERGENCY_RECOVERY_ADDRESS.load(&deps.storage).unwrap(),
+        Addr::unchecked("recovery")
     );
 
-    let withdraw = &res.messages[0].msg;
-    match withdraw {
-        CosmosMsg::Distribution(DistributionMsg::WithdrawDelegatorReward { validator: val }) => {
-            assert_eq!(val, &validator.address);
-        }
-        _ => panic!("Unexpected message: {:?}", withdraw),
-    }
-
-    let swap = &res.messages[1].msg;
-    match swap {
-        CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr,
-            msg: _,
-            funds: _,
-        }) => {
-            assert_eq!(contract_addr, "rewards_contract");
-        }
-        _ => panic!("Unexpected message: {:?}", swap),
-    }
-
-    // need to set the protocol fee collector address
-    let register_msg = UpdateConfig {
-        token_contract: None,
-        protocol_fee_collector: Some(protocol_fee_collector.clone()),
-    };
-
-    let owner_info = mock_info("owner1", &[]);
-    let res = execute(deps.as_mut(), mock_env(), owner_info, register_msg).unwrap();
-    assert_eq!(0, res.messages.len());
-
-    let query_msg = QueryMsg::Config {};
-
-    let config: ConfigResponse =
-        from_binary(&query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap();
-
+    // nothing has returned from the undelegation yet
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::EmergencyRecover {},
+    )
+    .unwrap_err();
     assert_eq!(
-        config.protocol_fee_collector.unwrap(),
-        protocol_fee_collector
+        res,
+        HubError::NothingToWithdraw {
+            denom: "uluna".to_string()
+        }
     );
 
-    // set balance before executing the exchange rate update
-    let new_balance = Uint128::new(1100);
+    // the returned coins go to the recovery address
     deps.querier.with_native_balances(&[(
         MOCK_CONTRACT_ADDR.to_string(),
         Coin {
             denom: "uluna".to_string(),
-            amount: new_balance,
+            amount: Uint128::new(1000),
         },
     )]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("invalid", &[]),
+        ExecuteMsg::EmergencyRecover {},
+    )
+    .unwrap_err();
+    assert_eq!(res, HubError::NotAdmin {});
 
-    let update_exchange_rate = ExecuteMsg::UpdateExchangeRate {};
-
-    let info = mock_info("rewards_contract", &[Coin::new(100, "uluna")]);
-    let res = execute(deps.as_mut(), mock_env(), info, update_exchange_rate).unwrap();
-
-    assert_eq!(res.messages.len(), 2);
-
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info,
+        ExecuteMsg::EmergencyRecover {},
+    )
+    .unwrap();
     assert_eq!(
-        res.messages[0],
-        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-            to_address: "fee_collector".to_string(),
-            amount: vec![Coin::new(1u128, "uluna")],
-        })),
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "recovery".to_string(),
+            amount: coins(1000, "uluna"),
+        }))]
     );
 }
+
+/// Covers if the emergency recovery keeps the unbonded amount owed to a matured
+/// batch that is not withdrawn yet.
 #[test]
-pub fn proper_pause() {
+pub fn proper_emergency_recover_keeps_matured_batch() {
     let mut deps = dependencies(&[]);
 
     let validator = sample_validator(DEFAULT_VALIDATOR.to_string());
@@ -2973,63 +10322,98 @@ pub fn proper_pause() {
     let owner = "owner1".to_string();
     let token_contract = "token".to_string();
 
-    init(&mut deps, owner, token_contract, validator.address);
-
-    let is_pause = PAUSE.load(&deps.storage).unwrap();
-    assert!(!is_pause);
+    init(&mut deps, owner, token_contract, validator.address.clone());
 
-    let pause = ExecuteMsg::Pause {};
+    let delegations: [FullDelegation; 1] = [sample_delegation(
+        validator.address.clone(),
+        coin(1000, "uluna"),
+    )];
+    let validators: [Validator; 1] = [validator.clone()];
+    set_delegation_query(&mut deps.querier, &delegations, &validators);
 
-    let owner_info = mock_info("owner2", &[]);
-    let res = execute(deps.as_mut(), mock_env(), owner_info, pause.clone()).is_err();
-    assert!(res);
+    // a matured batch of 300 that is neither released nor withdrawn
+    store_unbond_history(
+        &mut deps.storage,
+        1,
+        UnbondHistory {
+            batch_id: 1,
+            time: mock_env().block.time.seconds() - 10,
+            amount: Uint128::new(300),
+            applied_exchange_rate: Decimal::one(),
+            withdraw_rate: Decimal::one(),
+            released: false,
+        },
+    )
+    .unwrap();
 
     let owner_info = mock_info("owner1", &[]);
-    let res = execute(deps.as_mut(), mock_env(), owner_info, pause).unwrap();
-    assert_eq!(res.messages.len(), 0);
-
-    let is_pause = PAUSE.load(&deps.storage).unwrap();
-    assert!(is_pause);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::Pause {},
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::EmergencyUndelegateAll {
+            recovery_note: "incident".to_string(),
+            recovery_address: "recovery".to_string(),
+        },
+    )
+    .unwrap();
 
-    // try to execute one
-    let register_msg = UpdateConfig {
-        token_contract: None,
-        protocol_fee_collector: None,
-    };
+    // the batch funds arrived with the undelegated coins
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(1300),
+        },
+    )]);
 
-    let owner_info = mock_info("owner1", &[]);
-    let res = execute(deps.as_mut(), mock_env(), owner_info, register_msg).unwrap_err();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::EmergencyRecover {},
+    )
+    .unwrap();
     assert_eq!(
-        res,
-        StdError::generic_err("Contract is paused cannot perform the tx")
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "recovery".to_string(),
+            amount: coins(1000, "uluna"),
+        }))]
     );
 
-    // try to execute one
-    let register_msg = UpdateAdmin {
-        admin: "new owner".to_string(),
-    };
+    // the batch amount stays on the hub for the withdrawals
+    let state = STATE.load(&deps.storage).unwrap();
+    assert_eq!(state.actual_unbonded_amount, Uint128::new(300));
+    assert_eq!(state.prev_hub_balance, Uint128::new(300));
 
-    let owner_info = mock_info("new owner", &[]);
-    let res = execute(deps.as_mut(), mock_env(), owner_info, register_msg).unwrap_err();
+    deps.querier.with_native_balances(&[(
+        MOCK_CONTRACT_ADDR.to_string(),
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(300),
+        },
+    )]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info,
+        ExecuteMsg::EmergencyRecover {},
+    )
+    .unwrap_err();
     assert_eq!(
         res,
-        StdError::generic_err("Contract is paused cannot perform the tx")
+        HubError::NothingToWithdraw {
+            denom: "uluna".to_string()
+        }
     );
-
-    let unpause = ExecuteMsg::Unpause {};
-    let owner_info = mock_info("owner1", &[]);
-    let res = execute(deps.as_mut(), mock_env(), owner_info, unpause).unwrap();
-    assert_eq!(res.messages.len(), 0);
-
-    // try to execute one
-    let register_msg = UpdateConfig {
-        token_contract: None,
-        protocol_fee_collector: None,
-    };
-
-    let owner_info = mock_info("owner1", &[]);
-    let res = execute(deps.as_mut(), mock_env(), owner_info, register_msg).unwrap();
-    assert_eq!(res.messages.len(), 0);
 }
 
 fn set_delegation(querier: &mut WasmMockQuerier, validator: Validator, amount: u128, denom: &str) {
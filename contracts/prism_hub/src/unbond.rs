@@ -1,12 +1,18 @@
-use crate::contract::{query_total_issued, slashing};
+use crate::autho_compounding::draw_idle_liquidity;
+use crate::contract::{query_token_balance, query_total_issued, slashing};
+use crate::error::HubError;
+use crate::math::peg_fee;
 use crate::state::{
-    get_finished_amount, get_unbond_batches, read_unbond_history, remove_unbond_wait_list,
-    store_unbond_history, store_unbond_wait_list, CONFIG, CURRENT_BATCH, PARAMETERS, STATE,
+    get_finished_amount, get_unbond_batches, get_unbond_requests, read_unbond_history,
+    read_unreleased_unbond_history, remove_unbond_wait_list, store_unbond_history,
+    store_unbond_wait_list, ADMIN, BATCH_EXCHANGE_RATE, CONFIG, CURRENT_BATCH, LAST_BOND_TIME,
+    PARAMETERS, STATE, USER_STATS,
 };
-use basset::hub::{State, UnbondHistory};
+use crate::utility::unwrap_assert_admin;
+use basset::hub::{CurrentBatch, State, UnbondHistory, UserStats};
 use cosmwasm_std::{
-    attr, coin, coins, to_binary, BankMsg, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo,
-    Response, StakingMsg, StdError, StdResult, Storage, Uint128, WasmMsg,
+    attr, coin, coins, to_binary, BankMsg, CosmosMsg, Decimal, Delegation, Deps, DepsMut, Env,
+    MessageInfo, Response, StakingMsg, StdError, StdResult, Storage, SubMsg, Uint128, WasmMsg,
 };
 use cw20::Cw20ExecuteMsg;
 use rand::{Rng, SeedableRng, XorShiftRng};
@@ -20,15 +26,43 @@ pub(crate) fn execute_unbond(
     _info: MessageInfo,
     amount: Uint128,
     sender: String,
-) -> StdResult<Response> {
+) -> Result<Response, HubError> {
     // Read params
     let params = PARAMETERS.load(deps.storage)?;
     let epoch_period = params.epoch_period;
     let threshold = params.er_threshold;
     let recovery_fee = params.peg_recovery_fee;
+    let round_up_peg_fee = params.round_up_peg_fee;
+
+    assert_cooloff_passed(deps.as_ref(), &env, params.unbond_cooloff, &sender)?;
 
     let mut current_batch = CURRENT_BATCH.load(deps.storage)?;
 
+    // a request in a batch the sender is not waiting on yet must stay under the limit
+    let max_open_batches = params.max_open_unbond_batches_per_user;
+    if max_open_batches != 0 {
+        let requests = get_unbond_requests(deps.storage, sender.clone())?;
+        if !requests
+            .iter()
+            .any(|(batch_id, _)| *batch_id == current_batch.id)
+        {
+            let mut open_batches = 0u64;
+            for (batch_id, _) in requests {
+                let released = read_unbond_history(deps.storage, batch_id)
+                    .map(|history| history.released)
+                    .unwrap_or(false);
+                if !released {
+                    open_batches += 1;
+                }
+            }
+            if open_batches >= max_open_batches {
+                return Err(HubError::TooManyOpenBatches {
+                    max: max_open_batches,
+                });
+            }
+        }
+    }
+
     // Check slashing, update state, and calculate the new exchange rate.
     slashing(&mut deps, env.clone())?;
 
@@ -38,16 +72,20 @@ pub(crate) fn execute_unbond(
 
     // Collect all the requests within a epoch period
     // Apply peg recovery fee
-    let amount_with_fee: Uint128 = if state.exchange_rate < threshold {
-        let max_peg_fee = amount * recovery_fee;
-        let required_peg_fee = ((total_supply + current_batch.requested_with_fee)
-            .checked_sub(state.total_bond_amount))?;
-        let peg_fee = Uint128::min(max_peg_fee, required_peg_fee);
-        (amount.checked_sub(peg_fee))?
-    } else {
-        amount
-    };
-    current_batch.requested_with_fee += amount_with_fee;
+    let peg_fee = peg_fee(
+        amount,
+        state.exchange_rate,
+        threshold,
+        recovery_fee,
+        round_up_peg_fee,
+        total_supply.checked_add(current_batch.requested_with_fee)?,
+        state.total_bond_amount,
+    );
+    let amount_with_fee = amount.saturating_sub(peg_fee);
+    current_batch.requested_with_fee = current_batch
+        .requested_with_fee
+        .checked_add(amount_with_fee)?;
+    current_batch.requests_count += 1;
 
     store_unbond_wait_list(
         deps.storage,
@@ -56,8 +94,7 @@ pub(crate) fn execute_unbond(
         amount_with_fee,
     )?;
 
-    total_supply = (total_supply.checked_sub(amount))
-        .expect("the requested can not be more than the total supply");
+    total_supply = total_supply.checked_sub(amount)?;
 
     // Update exchange rate
     state.update_exchange_rate(total_supply, current_batch.requested_with_fee);
@@ -67,56 +104,34 @@ pub(crate) fn execute_unbond(
 
     let mut messages: Vec<CosmosMsg> = vec![];
 
-    // If the epoch period is passed, the undelegate message would be sent.
-    if passed_time > epoch_period {
-        // Apply the current exchange rate.
-        let undelegation_amount = current_batch.requested_with_fee * state.exchange_rate;
-
-        // the contract must stop if
-        if undelegation_amount == Uint128::new(1) {
-            return Err(StdError::generic_err(
-                "Burn amount must be greater than 1 ubluna",
-            ));
-        }
-
-        let delegator = env.contract.address;
-
-        let block_height = env.block.height;
-
-        // Send undelegated requests to possibly more than one validators
-        let mut undelegated_msgs = pick_validator(
-            deps.as_ref(),
-            undelegation_amount,
-            delegator.to_string(),
-            block_height,
-        )?;
+    // A full batch is undelegated early to keep the withdraw iteration bounded
+    let batch_is_full = params.max_requests_per_batch != 0
+        && current_batch.requests_count >= params.max_requests_per_batch;
 
+    // If the epoch period is passed, the undelegate message would be sent.
+    if passed_time > epoch_period || batch_is_full {
+        let mut undelegated_msgs =
+            undelegate_current_batch(&mut deps, &env, &mut state, &mut current_batch)?;
         messages.append(&mut undelegated_msgs);
-
-        state.total_bond_amount = (state.total_bond_amount.checked_sub(undelegation_amount))
-            .expect("undelegation amount can not be more than stored total bonded amount");
-
-        // Store history for withdraw unbonded
-        let history = UnbondHistory {
-            batch_id: current_batch.id,
-            time: env.block.time.seconds(),
-            amount: current_batch.requested_with_fee,
-            applied_exchange_rate: state.exchange_rate,
-            withdraw_rate: state.exchange_rate,
-            released: false,
-        };
-        store_unbond_history(deps.storage, current_batch.id, history)?;
-        // batch info must be updated to new batch
-        current_batch.id += 1;
-        current_batch.requested_with_fee = Uint128::zero();
-
-        // state.last_unbonded_time must be updated to the current block time
-        state.last_unbonded_time = env.block.time.seconds();
     }
 
     // Store the new requested_with_fee or id in the current batch
     CURRENT_BATCH.save(deps.storage, &current_batch)?;
 
+    state.total_burned = state.total_burned.checked_add(amount)?;
+
+    let sender_addr = deps.api.addr_validate(&sender)?;
+    USER_STATS.update(
+        deps.storage,
+        &sender_addr,
+        |stats| -> StdResult<UserStats> {
+            let mut stats = stats.unwrap_or_default();
+            stats.total_unbonded = stats.total_unbonded.checked_add(amount)?;
+            stats.unbond_count += 1;
+            Ok(stats)
+        },
+    )?;
+
     // Store state's new exchange rate
     STATE.save(deps.storage, &state)?;
 
@@ -143,18 +158,302 @@ pub(crate) fn execute_unbond(
     ]))
 }
 
+/// A user can not exit within `cooloff` seconds of their last bond
+fn assert_cooloff_passed(
+    deps: Deps,
+    env: &Env,
+    cooloff: u64,
+    sender: &str,
+) -> Result<(), HubError> {
+    if cooloff == 0 {
+        return Ok(());
+    }
+    let sender_addr = deps.api.addr_validate(sender)?;
+    if let Some(last_bond_time) = LAST_BOND_TIME.may_load(deps.storage, &sender_addr)? {
+        let until = last_bond_time + cooloff;
+        if env.block.time.seconds() < until {
+            return Err(HubError::UnbondCoolOff { until });
+        }
+    }
+    Ok(())
+}
+
+/// Burn basset and pay its value minus the instant redeem fee from the idle balance.
+/// The unbonded amount owed to the unreleased batches, the released withdrawals
+/// and the accrued protocol fee are never paid out.
+pub(crate) fn execute_instant_redeem(
+    mut deps: DepsMut,
+    env: Env,
+    amount: Uint128,
+    sender: String,
+) -> Result<Response, HubError> {
+    let params = PARAMETERS.load(deps.storage)?;
+    let fee_rate = params
+        .instant_redeem_fee
+        .ok_or_else(|| StdError::generic_err("Instant redeem is disabled"))?;
+    let coin_denom = params.underlying_coin_denom;
+    assert_cooloff_passed(deps.as_ref(), &env, params.unbond_cooloff, &sender)?;
+
+    // Check slashing, update state, and calculate the new exchange rate.
+    slashing(&mut deps, env.clone())?;
+
+    let mut state = STATE.load(deps.storage)?;
+    let payout = amount * state.exchange_rate;
+    let fee = payout * fee_rate;
+    let redeemed = payout.checked_sub(fee)?;
+    if redeemed.is_zero() {
+        return Err(StdError::generic_err("Nothing to redeem for the sent amount").into());
+    }
+
+    // the idle balance is what RestakeIdle would delegate, without the dust threshold
+    let hub_balance = deps
+        .querier
+        .query_balance(&env.contract.address, &*coin_denom)?
+        .amount;
+    let unbonded_pool =
+        state.actual_unbonded_amount + hub_balance.saturating_sub(state.prev_hub_balance);
+    let reserved = read_unreleased_unbond_history(deps.storage, state.last_processed_batch)?
+        .iter()
+        .fold(Uint128::zero(), |total, history| {
+            total + history.amount * history.withdraw_rate
+        });
+    let received_idle = unbonded_pool.saturating_sub(reserved);
+    if redeemed > received_idle + state.liquidity_reserve {
+        return Err(StdError::generic_err(
+            "Not enough idle liquidity to redeem instantly, unbond instead",
+        )
+        .into());
+    }
+
+    // the received balance is accounted like a release does, minus the paid amount.
+    // The payout comes from the idle balance, which is not part of total_bond_amount,
+    // and the delegations are untouched: the remaining supply keeps the whole bonded
+    // share of the burned basset, so the exchange rate rises by the payout over the
+    // remaining supply, while the idle balance left to restake shrinks by the paid amount
+    draw_idle_liquidity(
+        &mut state,
+        hub_balance,
+        unbonded_pool,
+        received_idle,
+        redeemed,
+    )?;
+    let requested_with_fee = CURRENT_BATCH.load(deps.storage)?.requested_with_fee;
+    let total_supply = query_total_issued(deps.as_ref())?.checked_sub(amount)?;
+    state.update_exchange_rate(total_supply, requested_with_fee);
+    state.total_burned = state.total_burned.checked_add(amount)?;
+    STATE.save(deps.storage, &state)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let token_address = deps.api.addr_humanize(
+        &config
+            .token_contract
+            .expect("the token contract must have been registered"),
+    )?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token_address.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn { amount })?,
+            funds: vec![],
+        }))
+        .add_message(BankMsg::Send {
+            to_address: sender.clone(),
+            amount: coins(redeemed.u128(), coin_denom),
+        })
+        .add_attributes(vec![
+            attr("action", "instant_redeem"),
+            attr("from", sender),
+            attr("burnt_amount", amount),
+            attr("redeemed_amount", redeemed),
+            attr("fee", fee),
+        ]))
+}
+
+/// Unbond with an allowance instead of a cw20 send.
+/// The token is transferred to the hub before it is burned by the batch logic.
+/// The sender still holds the token here, unlike a cw20 send that has already moved it
+pub(crate) fn execute_unbond_direct(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> Result<Response, HubError> {
+    // The sender can not unbond more than its balance
+    if amount > query_token_balance(deps.as_ref(), info.sender.to_string())? {
+        return Err(HubError::UnbondExceedsBalance {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let token_address = deps.api.addr_humanize(
+        &config
+            .token_contract
+            .expect("the token contract must have been registered"),
+    )?;
+
+    let transfer_msg = Cw20ExecuteMsg::TransferFrom {
+        owner: info.sender.to_string(),
+        recipient: env.contract.address.to_string(),
+        amount,
+    };
+    let transfer = SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: token_address.to_string(),
+        msg: to_binary(&transfer_msg)?,
+        funds: vec![],
+    }));
+
+    let sender = info.sender.to_string();
+    let mut res = execute_unbond(deps, env, info, amount, sender)?;
+    // the transfer must run before the burn
+    res.messages.insert(0, transfer);
+    Ok(res)
+}
+
+/// Undelegate the current batch at the current exchange rate, store its history
+/// and open the next batch
+fn undelegate_current_batch(
+    deps: &mut DepsMut,
+    env: &Env,
+    state: &mut State,
+    current_batch: &mut CurrentBatch,
+) -> StdResult<Vec<CosmosMsg>> {
+    // Apply the current exchange rate, it is the rate the batch is recorded with
+    let applied_exchange_rate = state.exchange_rate;
+    let undelegation_amount = current_batch.requested_with_fee * applied_exchange_rate;
+
+    // the contract must stop if
+    if undelegation_amount == Uint128::new(1) {
+        return Err(StdError::generic_err(
+            "Burn amount must be greater than 1 ubluna",
+        ));
+    }
+
+    let delegator = &env.contract.address;
+
+    let block_height = env.block.height;
+
+    // Send undelegated requests to possibly more than one validators
+    let undelegated_msgs = pick_validator(
+        deps.as_ref(),
+        undelegation_amount,
+        delegator.to_string(),
+        block_height,
+    )?;
+
+    state.total_bond_amount = state.total_bond_amount.checked_sub(undelegation_amount)?;
+
+    // Store history for withdraw unbonded
+    let history = UnbondHistory {
+        batch_id: current_batch.id,
+        time: env.block.time.seconds(),
+        amount: current_batch.requested_with_fee,
+        applied_exchange_rate,
+        withdraw_rate: applied_exchange_rate,
+        released: false,
+    };
+    store_unbond_history(deps.storage, current_batch.id, history)?;
+    BATCH_EXCHANGE_RATE.save(deps.storage, current_batch.id, &applied_exchange_rate)?;
+    state.open_batches_count += 1;
+    // batch info must be updated to new batch
+    current_batch.id += 1;
+    current_batch.requested_with_fee = Uint128::zero();
+    current_batch.requests_count = 0;
+
+    // state.last_unbonded_time must be updated to the current block time
+    state.last_unbonded_time = env.block.time.seconds();
+
+    Ok(undelegated_msgs)
+}
+
+/// Undelegate the current batch without waiting for the epoch period.
+/// Only creator/owner is allowed to execute
+pub fn execute_force_batch_rollover(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, HubError> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    let mut current_batch = CURRENT_BATCH.load(deps.storage)?;
+    if current_batch.requested_with_fee.is_zero() {
+        return Err(HubError::EmptyBatch {});
+    }
+    let batch_id = current_batch.id;
+
+    // Check slashing, update state, and calculate the new exchange rate.
+    slashing(&mut deps, env.clone())?;
+
+    let mut state = STATE.load(deps.storage)?;
+    let messages = undelegate_current_batch(&mut deps, &env, &mut state, &mut current_batch)?;
+
+    CURRENT_BATCH.save(deps.storage, &current_batch)?;
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "force_batch_rollover"),
+        attr("batch_id", batch_id.to_string()),
+    ]))
+}
+
+pub fn execute_reassign_unbond_requests(
+    deps: DepsMut,
+    info: MessageInfo,
+    from: String,
+    to: String,
+) -> Result<Response, HubError> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    let from = deps.api.addr_validate(&from)?;
+    let to = deps.api.addr_validate(&to)?;
+    if from == to {
+        return Err(
+            StdError::generic_err("Can not reassign unbond requests to the same address").into(),
+        );
+    }
+
+    let requests = get_unbond_requests(deps.storage, from.to_string())?;
+    if requests.is_empty() {
+        return Err(StdError::generic_err("No unbond requests to reassign").into());
+    }
+
+    // the wait list adds to the amount `to` already requested in the batch
+    let mut batches = vec![];
+    for (batch_id, amount) in requests {
+        store_unbond_wait_list(deps.storage, batch_id, to.to_string(), amount)?;
+        batches.push(batch_id);
+    }
+    let moved = batches.len();
+    remove_unbond_wait_list(deps.storage, batches, from.clone())?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "reassign_unbond_requests"),
+        attr("from", from),
+        attr("to", to),
+        attr("batches", moved.to_string()),
+    ]))
+}
+
 pub fn execute_withdraw_unbonded(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-) -> StdResult<Response> {
+    recipient: Option<String>,
+) -> Result<Response, HubError> {
     let sender_human = info.sender;
     let contract_address = env.contract.address.clone();
 
+    // the requests stay the sender's, only the funds go to the recipient
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => sender_human.clone(),
+    };
+
     // read params
     let params = PARAMETERS.load(deps.storage)?;
     let unbonding_period = params.unbonding_period;
     let coin_denom = params.underlying_coin_denom;
+    let return_denom = params
+        .unbond_return_denom
+        .unwrap_or_else(|| coin_denom.clone());
 
     let historical_time = env.block.time.seconds() - unbonding_period;
 
@@ -165,15 +464,12 @@ pub fn execute_withdraw_unbonded(
         .amount;
 
     // calculate withdraw rate for user requests
-    process_withdraw_rate(deps.storage, historical_time, hub_balance)?;
+    process_withdraw_rate(deps.storage, historical_time, hub_balance, None)?;
 
     let withdraw_amount = get_finished_amount(deps.storage, sender_human.to_string()).unwrap();
 
     if withdraw_amount.is_zero() {
-        return Err(StdError::generic_err(format!(
-            "No withdrawable {} assets are available yet",
-            coin_denom
-        )));
+        return Err(HubError::NothingToWithdraw { denom: coin_denom });
     }
 
     // remove the previous batches for the user
@@ -189,8 +485,8 @@ pub fn execute_withdraw_unbonded(
 
     // Send the money to the user
     let bank_msg: CosmosMsg = BankMsg::Send {
-        to_address: sender_human.to_string(),
-        amount: coins(withdraw_amount.u128(), &*coin_denom),
+        to_address: recipient.to_string(),
+        amount: coins(withdraw_amount.u128(), &*return_denom),
     }
     .into();
 
@@ -203,13 +499,52 @@ pub fn execute_withdraw_unbonded(
         .add_message(bank_msg))
 }
 
+/// Release every matured batch and store its final withdraw rate
+/// without sending any funds, so withdrawals only read the stored rates.
+/// Permissionless
+pub fn execute_process_matured_batches(
+    deps: DepsMut,
+    env: Env,
+    limit: Option<u32>,
+) -> Result<Response, HubError> {
+    // read params
+    let params = PARAMETERS.load(deps.storage)?;
+    let unbonding_period = params.unbonding_period;
+    let coin_denom = params.underlying_coin_denom;
+
+    let historical_time = env.block.time.seconds() - unbonding_period;
+
+    let hub_balance = deps
+        .querier
+        .query_balance(&env.contract.address, &*coin_denom)?
+        .amount;
+
+    let released_batches =
+        process_withdraw_rate(deps.storage, historical_time, hub_balance, limit)?;
+
+    // nothing is sent, so the whole balance is the base for the next release
+    STATE.update(deps.storage, |mut last_state| -> StdResult<State> {
+        last_state.prev_hub_balance = hub_balance;
+        Ok(last_state)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "process_matured_batches"),
+        attr("released_batches", released_batches.to_string()),
+    ]))
+}
+
 /// This is designed for an accurate unbonded amount calculation.
 /// Execute while processing withdraw_unbonded
+/// If `limit` stops the release before all the matured batches,
+/// the unbonded amount received for the rest is kept for the next call.
+/// Returns the number of released batches.
 fn process_withdraw_rate(
     storage: &mut dyn Storage,
     historical_time: u64,
     hub_balance: Uint128,
-) -> StdResult<()> {
+    limit: Option<u32>,
+) -> StdResult<u64> {
     // balance change of the hub contract must be checked.
     let mut total_unbonded_amount = Uint128::zero();
 
@@ -221,6 +556,10 @@ fn process_withdraw_rate(
     let last_processed_batch = state.last_processed_batch;
     let mut batch_count: u64 = 0;
 
+    // the batches that are released in this call
+    let release_limit = limit.map(u64::from).unwrap_or(u64::MAX);
+    let mut released_unbonded_amount = Uint128::zero();
+
     // Iterate over unbonded histories that have been processed
     // to calculate newly added unbonded amount
     let mut i = last_processed_batch + 1;
@@ -243,18 +582,30 @@ fn process_withdraw_rate(
         let historical_rate = history.withdraw_rate;
         let unbonded_amount = burnt_amount * historical_rate;
         total_unbonded_amount += unbonded_amount;
+        if batch_count < release_limit {
+            released_unbonded_amount += unbonded_amount;
+        }
         batch_count += 1;
         i += 1;
     }
+    let released_count = batch_count.min(release_limit);
+
+    // batches beyond the limit keep their share of the received amount
+    let mut actual_unbonded_amount = state.actual_unbonded_amount;
+    if released_count < batch_count && !total_unbonded_amount.is_zero() {
+        actual_unbonded_amount = state
+            .actual_unbonded_amount
+            .multiply_ratio(released_unbonded_amount, total_unbonded_amount);
+    }
 
-    if batch_count >= 1 {
+    if released_count >= 1 {
         // Use signed integer in case of some rogue transfers.
         let slashed_amount =
-            SignedInt::from_subtraction(total_unbonded_amount, state.actual_unbonded_amount);
+            SignedInt::from_subtraction(released_unbonded_amount, actual_unbonded_amount);
 
         // Iterate again to calculate the withdraw rate for each unprocessed history
         let mut iterator = last_processed_batch + 1;
-        loop {
+        while iterator <= last_processed_batch + released_count {
             let history: UnbondHistory;
             match read_unbond_history(storage, iterator) {
                 Ok(h) => {
@@ -277,7 +628,7 @@ fn process_withdraw_rate(
 
             // the slashed amount for each batch must be proportional to the unbonded amount of batch
             let batch_slashing_weight =
-                Decimal::from_ratio(unbonded_amount_of_batch, total_unbonded_amount);
+                Decimal::from_ratio(unbonded_amount_of_batch, released_unbonded_amount);
 
             let mut slashed_amount_of_batch = batch_slashing_weight * slashed_amount.0;
             let actual_unbonded_amount_of_batch: Uint128;
@@ -305,14 +656,16 @@ fn process_withdraw_rate(
             history_for_i.released = true;
             store_unbond_history(storage, iterator, history_for_i)?;
             state.last_processed_batch = iterator;
+            // batches closed before the counter existed are not counted
+            state.open_batches_count = state.open_batches_count.saturating_sub(1);
             iterator += 1;
         }
     }
     // Store state.actual_unbonded_amount for future new batches release
-    state.actual_unbonded_amount = Uint128::zero();
+    state.actual_unbonded_amount = state.actual_unbonded_amount - actual_unbonded_amount;
     STATE.save(storage, &state)?;
 
-    Ok(())
+    Ok(released_count)
 }
 
 fn pick_validator(
@@ -325,25 +678,69 @@ fn pick_validator(
     let params = PARAMETERS.load(deps.storage)?;
     let coin_denom = params.underlying_coin_denom;
 
-    let mut messages: Vec<CosmosMsg> = vec![];
-    let mut claimed = claim;
-
     let all_delegations = deps
         .querier
         .query_all_delegations(delegator)
         .expect("There must be at least one delegation");
 
+    let undelegations = plan_undelegations(
+        all_delegations,
+        claim,
+        params.min_undelegate_per_validator,
+        block_height,
+    )?;
+    Ok(undelegations
+        .into_iter()
+        .map(|(validator, amount)| {
+            CosmosMsg::Staking(StakingMsg::Undelegate {
+                validator,
+                amount: coin(amount.u128(), &*coin_denom),
+            })
+        })
+        .collect())
+}
+
+/// Spread `claim` over the delegations, returning the amount to undelegate per validator.
+/// The same block height and delegations always give the same plan.
+pub(crate) fn plan_undelegations(
+    all_delegations: Vec<Delegation>,
+    claim: Uint128,
+    min_undelegation: Uint128,
+    block_height: u64,
+) -> StdResult<Vec<(String, Uint128)>> {
+    let mut undelegations: Vec<(String, Uint128)> = vec![];
+    let mut claimed = claim;
+
     // pick a random validator
     // if it does not have requested amount, undelegate all it has
     // and pick another random validator
     let mut iteration_index = 0;
     let mut deletable_delegations = all_delegations;
 
+    // validators that could only cover a dust part of the claim are set aside
+    // and only used if the others cannot cover the whole claim
+    let mut dust_delegations = vec![];
+    let mut allow_dust = min_undelegation.is_zero();
+
     while claimed.u128() > 0 {
+        if deletable_delegations.is_empty() && !dust_delegations.is_empty() {
+            deletable_delegations.append(&mut dust_delegations);
+            allow_dust = true;
+        }
+        if deletable_delegations.is_empty() {
+            return Err(StdError::generic_err(
+                "The delegations can not cover the undelegation amount",
+            ));
+        }
         let mut rng = XorShiftRng::seed_from_u64(block_height + iteration_index);
         let random_index = rng.gen_range(0, deletable_delegations.len());
         let delegation = deletable_delegations.remove(random_index);
         let val = delegation.amount.amount;
+        if !allow_dust && val < claimed && val < min_undelegation {
+            dust_delegations.push(delegation);
+            iteration_index += 1;
+            continue;
+        }
         let undelegated_amount: Uint128;
         if val.u128() > claimed.u128() {
             undelegated_amount = claimed;
@@ -353,13 +750,9 @@ fn pick_validator(
             claimed = (claimed.checked_sub(val))?;
         }
         if undelegated_amount.u128() > 0 {
-            let msgs: CosmosMsg = CosmosMsg::Staking(StakingMsg::Undelegate {
-                validator: delegation.validator,
-                amount: coin(undelegated_amount.u128(), &*coin_denom),
-            });
-            messages.push(msgs);
+            undelegations.push((delegation.validator, undelegated_amount));
         }
         iteration_index += 1;
     }
-    Ok(messages)
+    Ok(undelegations)
 }
@@ -1,6 +1,7 @@
-use crate::state::PAUSE;
-use basset::hub::InstantiateMsg;
-use cosmwasm_std::{Addr, CustomQuery, Decimal, Deps, Response, StdError, StdResult};
+use crate::error::HubError;
+use crate::state::{PAUSE, RATE_FROZEN, STATE};
+use basset::hub::{InstantiateMsg, State};
+use cosmwasm_std::{Addr, CustomQuery, Decimal, Deps, Response, StdError, StdResult, Storage};
 use cw_controllers::{Admin, AdminError};
 
 const MAINNET_UNDELEGATION_TIME: u64 = 1814400;
@@ -10,12 +11,12 @@ pub fn unwrap_assert_admin<Q: CustomQuery>(
     deps: Deps<Q>,
     admin: Admin,
     sender: &Addr,
-) -> Result<(), StdError> {
+) -> Result<(), HubError> {
     match admin.assert_admin(deps, sender) {
         Ok(_) => Ok(()),
         Err(e) => match e {
-            AdminError::NotAdmin {} => Err(StdError::generic_err("Caller is not admin")),
-            AdminError::Std(std_error) => Err(std_error),
+            AdminError::NotAdmin {} => Err(HubError::NotAdmin {}),
+            AdminError::Std(std_error) => Err(std_error.into()),
         },
     }
 }
@@ -43,14 +44,35 @@ pub fn validate_params(msg: InstantiateMsg) -> Result<(), StdError> {
     Ok(())
 }
 
-pub fn is_contract_paused<Q: CustomQuery>(deps: Deps<Q>) -> StdResult<Response> {
+/// Seed of a random validator selection.
+pub fn selection_seed(block_height: u64, selection_nonce: u64) -> u64 {
+    block_height ^ selection_nonce.rotate_left(32)
+}
+
+/// Return the seed for the next random validator selection and advance the stored nonce,
+/// so that several selections in a block do not all land on the same index.
+pub fn next_selection_seed(storage: &mut dyn Storage, block_height: u64) -> StdResult<u64> {
+    let mut state: State = STATE.load(storage)?;
+    let seed = selection_seed(block_height, state.selection_nonce);
+    state.selection_nonce += 1;
+    STATE.save(storage, &state)?;
+    Ok(seed)
+}
+
+pub fn is_contract_paused<Q: CustomQuery>(deps: Deps<Q>) -> Result<Response, HubError> {
     let is_paused = PAUSE.load(deps.storage)?;
 
     if is_paused {
-        return Err(StdError::generic_err(
-            "Contract is paused cannot perform the tx",
-        ));
+        return Err(HubError::Paused {});
     }
 
     Ok(Response::new())
 }
+
+pub fn is_rate_frozen<Q: CustomQuery>(deps: Deps<Q>) -> Result<(), HubError> {
+    if RATE_FROZEN.may_load(deps.storage)?.unwrap_or_default() {
+        return Err(HubError::RateFrozen {});
+    }
+
+    Ok(())
+}
@@ -1,14 +1,17 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    attr, to_binary, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response, StdError,
-    StdResult, SubMsg, WasmMsg,
+    attr, to_binary, Binary, CanonicalAddr, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, Event,
+    MessageInfo, Response, StdError, StdResult, SubMsg, WasmMsg,
 };
 
 use crate::state::{ADMIN, CONFIG, PAUSE};
 use crate::utility::{is_contract_paused, unwrap_assert_admin};
 use basset::hub::ExecuteMsg::UpdateExchangeRate;
-use basset::rewards::{Config, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
+use basset::rewards::{
+    AccruedRewardsResponse, Config, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg,
+    RewardSwapConfig, SwapAsset, SwapAssetInfo, SwapMsg,
+};
 use cw_controllers::AdminError;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -31,7 +34,12 @@ pub fn instantiate(
     // store config
     let conf = Config {
         hub_contract: deps.api.addr_canonicalize(&msg.hub_addr)?,
-        underlying_coin_denom: msg.underlying_coin_denom,
+        underlying_coin_denom: msg.underlying_coin_denom.clone(),
+        min_process_amount: msg.min_process_amount,
+        keepers: vec![],
+        allowed_denoms: vec![msg.underlying_coin_denom.clone()],
+        reward_swap: None,
+        swappable_denoms: vec![],
     };
     CONFIG.save(deps.storage, &conf)?;
 
@@ -67,6 +75,22 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
                 },
             }
         }
+        ExecuteMsg::UpdateKeepers { keepers } => {
+            is_contract_paused(deps.as_ref())?;
+            execute_update_keepers(deps, info, keepers)
+        }
+        ExecuteMsg::UpdateAllowedDenoms { denoms } => {
+            is_contract_paused(deps.as_ref())?;
+            execute_update_allowed_denoms(deps, info, denoms)
+        }
+        ExecuteMsg::UpdateRewardSwapConfig { reward_swap } => {
+            is_contract_paused(deps.as_ref())?;
+            execute_update_reward_swap_config(deps, info, reward_swap)
+        }
+        ExecuteMsg::UpdateSwappableDenoms { denoms } => {
+            is_contract_paused(deps.as_ref())?;
+            execute_update_swappable_denoms(deps, info, denoms)
+        }
         ExecuteMsg::ProcessRewards {} => {
             is_contract_paused(deps.as_ref())?;
             execute_process_rewards(deps, env, info)
@@ -74,35 +98,210 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
     }
 }
 
+/// Replace the keepers that can process the rewards.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_keepers(
+    deps: DepsMut,
+    info: MessageInfo,
+    keepers: Vec<String>,
+) -> StdResult<Response> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    let keepers = keepers
+        .iter()
+        .map(|keeper| deps.api.addr_canonicalize(keeper))
+        .collect::<StdResult<Vec<CanonicalAddr>>>()?;
+
+    CONFIG.update(deps.storage, |mut config| -> StdResult<Config> {
+        config.keepers = keepers;
+        Ok(config)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_keepers")]))
+}
+
+/// Replace the denoms that are forwarded to the hub.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_allowed_denoms(
+    deps: DepsMut,
+    info: MessageInfo,
+    denoms: Vec<String>,
+) -> StdResult<Response> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    CONFIG.update(deps.storage, |mut config| -> StdResult<Config> {
+        config.allowed_denoms = denoms;
+        Ok(config)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_allowed_denoms")]))
+}
+
+/// Set or clear the swap of the non-underlying reward denoms.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_reward_swap_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    reward_swap: Option<RewardSwapConfig>,
+) -> StdResult<Response> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    if let Some(swap) = &reward_swap {
+        if swap.min_output_ratio.is_zero() {
+            return Err(StdError::generic_err(
+                "The min output ratio must be greater than zero",
+            ));
+        }
+        deps.api.addr_validate(swap.swap_contract.as_str())?;
+    }
+
+    CONFIG.update(deps.storage, |mut config| -> StdResult<Config> {
+        config.reward_swap = reward_swap;
+        Ok(config)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_reward_swap_config")]))
+}
+
+/// Replace the denoms that can be swapped to the underlying coin.
+/// Only creator/owner is allowed to execute
+pub fn execute_update_swappable_denoms(
+    deps: DepsMut,
+    info: MessageInfo,
+    denoms: Vec<String>,
+) -> StdResult<Response> {
+    unwrap_assert_admin(deps.as_ref(), ADMIN, &info.sender)?;
+
+    CONFIG.update(deps.storage, |mut config| -> StdResult<Config> {
+        config.swappable_denoms = denoms;
+        Ok(config)
+    })?;
+
+    Ok(Response::new().add_attributes(vec![attr("action", "update_swappable_denoms")]))
+}
+
+/// Swap the offered coin to the underlying coin. The belief price is the
+/// inverse of the min output ratio and the spread is zero, so the pair
+/// rejects any swap returning less than offered amount * min output ratio
+fn swap_msg(swap: &RewardSwapConfig, offer: Coin) -> StdResult<CosmosMsg> {
+    let belief_price = swap
+        .min_output_ratio
+        .inv()
+        .ok_or_else(|| StdError::generic_err("The min output ratio must be greater than zero"))?;
+
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: swap.swap_contract.to_string(),
+        msg: to_binary(&SwapMsg::Swap {
+            offer_asset: SwapAsset {
+                info: SwapAssetInfo::NativeToken {
+                    denom: offer.denom.clone(),
+                },
+                amount: offer.amount,
+            },
+            belief_price: Some(belief_price),
+            max_spread: Some(Decimal::zero()),
+            to: None,
+        })?,
+        funds: vec![offer],
+    }))
+}
+
 pub fn execute_process_rewards(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<Response> {
     let config = CONFIG.load(deps.storage)?;
     let hub_contract = deps.api.addr_humanize(&config.hub_contract)?;
 
-    if info.sender != hub_contract {
-        return Err(StdError::generic_err("Caller is not hub contract"));
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if info.sender != hub_contract && !config.keepers.contains(&sender_raw) {
+        return Err(StdError::generic_err(
+            "Caller is not hub contract or a keeper",
+        ));
     }
 
+    let allowed_denoms = if config.allowed_denoms.is_empty() {
+        vec![config.underlying_coin_denom.clone()]
+    } else {
+        config.allowed_denoms
+    };
+
+    // balances outside the allow-list stay on the contract
     let contract_address = env.contract.address;
-    let balance: Coin = deps
+    let funds: Vec<Coin> = deps
         .querier
-        .query_balance(contract_address, &config.underlying_coin_denom)?;
+        .query_all_balances(contract_address)?
+        .into_iter()
+        .filter(|coin| !coin.amount.is_zero() && allowed_denoms.contains(&coin.denom))
+        .collect();
 
-    let messages: Vec<SubMsg> = vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+    let balance = funds
+        .iter()
+        .find(|coin| coin.denom == config.underlying_coin_denom)
+        .map(|coin| coin.amount)
+        .unwrap_or_default();
+
+    // one attribute per allowed denom
+    let summary = Event::new("process_rewards").add_attributes(
+        funds
+            .iter()
+            .map(|coin| attr(coin.denom.clone(), coin.amount))
+            .collect::<Vec<_>>(),
+    );
+
+    let mut messages: Vec<SubMsg> = vec![];
+
+    // only the underlying coin goes to the hub, which rejects any other denom.
+    // With a swap configured the swappable denoms are swapped and the swapped
+    // amount is forwarded by the next ProcessRewards, every other allowed
+    // denom stays on the contract
+    let (funds, offers): (Vec<Coin>, Vec<Coin>) = funds
+        .into_iter()
+        .partition(|coin| coin.denom == config.underlying_coin_denom);
+    if let Some(swap) = &config.reward_swap {
+        for offer in offers {
+            if config.swappable_denoms.contains(&offer.denom) {
+                messages.push(SubMsg::new(swap_msg(swap, offer)?));
+            }
+        }
+    }
+
+    // the hub rejects an exchange rate update without funds,
+    // so a compound without rewards is a no-op, as is a compound below
+    // the processing threshold. The hub dispatches ProcessRewards without
+    // a reply on error, so failing here would revert its global index update
+    let skipped = if funds.is_empty() {
+        Some("no rewards to forward")
+    } else if balance < config.min_process_amount {
+        Some("reward balance below processing threshold")
+    } else {
+        None
+    };
+    if let Some(reason) = skipped {
+        return Ok(Response::new()
+            .add_submessages(messages)
+            .add_attributes(vec![
+                attr("reward_accumulated", balance),
+                attr("skipped", reason),
+            ])
+            .add_event(summary));
+    }
+
+    messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: hub_contract.to_string(),
         msg: to_binary(&UpdateExchangeRate {}).unwrap(),
-        funds: vec![Coin::new(balance.amount.u128(), balance.denom)],
-    }))];
+        funds,
+    })));
 
     Ok(Response::new()
         .add_submessages(messages)
-        .add_attributes(vec![attr("reward_accumulated", balance.amount)]))
+        .add_attributes(vec![attr("reward_accumulated", balance)])
+        .add_event(summary))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::Admin {} => to_binary(&ADMIN.query_admin(deps)?),
+        QueryMsg::AccruedRewards {} => to_binary(&query_accrued_rewards(deps, env)?),
     }
 }
 
@@ -115,7 +314,30 @@ fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         .unwrap()
         .to_string();
 
+    let keepers = config
+        .keepers
+        .iter()
+        .map(|keeper| Ok(deps.api.addr_humanize(keeper)?.to_string()))
+        .collect::<StdResult<Vec<String>>>()?;
+
     Ok(ConfigResponse {
         hub_contract: hub_addr,
+        keepers,
+        allowed_denoms: config.allowed_denoms,
+        reward_swap: config.reward_swap,
+        swappable_denoms: config.swappable_denoms,
+    })
+}
+
+fn query_accrued_rewards(deps: Deps, env: Env) -> StdResult<AccruedRewardsResponse> {
+    let config = CONFIG.load(deps.storage)?;
+
+    let balance: Coin = deps
+        .querier
+        .query_balance(env.contract.address, &config.underlying_coin_denom)?;
+
+    Ok(AccruedRewardsResponse {
+        amount: balance.amount,
+        denom: balance.denom,
     })
 }
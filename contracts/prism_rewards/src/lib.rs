@@ -4,3 +4,6 @@ pub mod contract;
 pub mod state;
 
 mod utility;
+
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,451 @@
+use cosmwasm_std::testing::{
+    mock_dependencies_with_balance, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+};
+use cosmwasm_std::{
+    attr, coin, from_binary, to_binary, Addr, CosmosMsg, Decimal, Event, OwnedDeps, StdError,
+    SubMsg, Uint128, WasmMsg,
+};
+
+use crate::contract::{execute, instantiate, query};
+use basset::hub::ExecuteMsg::UpdateExchangeRate;
+use basset::rewards::{
+    AccruedRewardsResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg, RewardSwapConfig,
+    SwapAsset, SwapAssetInfo, SwapMsg,
+};
+
+pub fn init(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, min_process_amount: Uint128) {
+    let msg = InstantiateMsg {
+        hub_addr: "hub".to_string(),
+        underlying_coin_denom: "uluna".to_string(),
+        min_process_amount,
+    };
+
+    let owner_info = mock_info("owner1", &[]);
+    instantiate(deps.as_mut(), mock_env(), owner_info, msg).unwrap();
+}
+
+/// Covers if the accrued rewards report the balance of the contract in the underlying denom.
+#[test]
+fn proper_accrued_rewards() {
+    let mut deps = mock_dependencies_with_balance(&[coin(500, "uluna"), coin(100, "uusd")]);
+    init(&mut deps, Uint128::zero());
+
+    let res: AccruedRewardsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::AccruedRewards {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        res,
+        AccruedRewardsResponse {
+            amount: Uint128::new(500),
+            denom: "uluna".to_string(),
+        }
+    );
+}
+
+/// Covers if ProcessRewards below the processing threshold succeeds without calling the hub.
+#[test]
+fn proper_process_rewards_below_threshold() {
+    let mut deps = mock_dependencies_with_balance(&[coin(499, "uluna")]);
+    init(&mut deps, Uint128::new(500));
+
+    let hub_info = mock_info("hub", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        hub_info,
+        ExecuteMsg::ProcessRewards {},
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("reward_accumulated", "499"),
+            attr("skipped", "reward balance below processing threshold")
+        ]
+    );
+}
+
+/// Covers if ProcessRewards forwards the balance to the hub at the processing threshold.
+#[test]
+fn proper_process_rewards_at_threshold() {
+    let mut deps = mock_dependencies_with_balance(&[coin(500, "uluna")]);
+    init(&mut deps, Uint128::new(500));
+
+    let hub_info = mock_info("hub", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        hub_info,
+        ExecuteMsg::ProcessRewards {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "hub".to_string(),
+            msg: to_binary(&UpdateExchangeRate {}).unwrap(),
+            funds: vec![coin(500, "uluna")],
+        }))]
+    );
+}
+
+/// Covers if ProcessRewards without any reward succeeds without calling the hub.
+#[test]
+fn proper_process_rewards_zero_balance() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+    init(&mut deps, Uint128::zero());
+
+    let hub_info = mock_info("hub", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        hub_info,
+        ExecuteMsg::ProcessRewards {},
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("reward_accumulated", "0"),
+            attr("skipped", "no rewards to forward")
+        ]
+    );
+}
+
+/// Covers if an allowlisted keeper can process the rewards while others are rejected.
+#[test]
+fn proper_process_rewards_by_keeper() {
+    let mut deps = mock_dependencies_with_balance(&[coin(500, "uluna")]);
+    init(&mut deps, Uint128::zero());
+
+    let keeper_info = mock_info("keeper", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        keeper_info.clone(),
+        ExecuteMsg::ProcessRewards {},
+    )
+    .unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err("Caller is not hub contract or a keeper")
+    );
+
+    // only the admin can update the keepers
+    let update_keepers = ExecuteMsg::UpdateKeepers {
+        keepers: vec!["keeper".to_string()],
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        keeper_info.clone(),
+        update_keepers.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("Caller is not admin"));
+
+    let owner_info = mock_info("owner1", &[]);
+    execute(deps.as_mut(), mock_env(), owner_info, update_keepers).unwrap();
+
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.keepers, vec!["keeper".to_string()]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        keeper_info,
+        ExecuteMsg::ProcessRewards {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    let random_info = mock_info("random", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        random_info,
+        ExecuteMsg::ProcessRewards {},
+    )
+    .unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err("Caller is not hub contract or a keeper")
+    );
+}
+
+/// Covers if only the underlying coin is forwarded to the hub, whether other denoms
+/// are allowed or not.
+#[test]
+fn proper_process_rewards_ignores_not_allowed_denoms() {
+    let mut deps = mock_dependencies_with_balance(&[coin(500, "uluna"), coin(100, "uusd")]);
+    init(&mut deps, Uint128::zero());
+
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.allowed_denoms, vec!["uluna".to_string()]);
+
+    let hub_info = mock_info("hub", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        hub_info.clone(),
+        ExecuteMsg::ProcessRewards {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "hub".to_string(),
+            msg: to_binary(&UpdateExchangeRate {}).unwrap(),
+            funds: vec![coin(500, "uluna")],
+        }))]
+    );
+
+    // only the admin can update the allowed denoms
+    let update_denoms = ExecuteMsg::UpdateAllowedDenoms {
+        denoms: vec!["uluna".to_string(), "uusd".to_string()],
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        hub_info.clone(),
+        update_denoms.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("Caller is not admin"));
+
+    let owner_info = mock_info("owner1", &[]);
+    execute(deps.as_mut(), mock_env(), owner_info, update_denoms).unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        hub_info,
+        ExecuteMsg::ProcessRewards {},
+    )
+    .unwrap();
+    // without a swap the allowed uusd stays on the contract
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "hub".to_string(),
+            msg: to_binary(&UpdateExchangeRate {}).unwrap(),
+            funds: vec![coin(500, "uluna")],
+        }))]
+    );
+}
+
+/// Covers if every allowed denom is reported in the process rewards event.
+#[test]
+fn proper_process_rewards_event() {
+    let mut deps = mock_dependencies_with_balance(&[coin(500, "uluna"), coin(100, "uusd")]);
+    init(&mut deps, Uint128::zero());
+
+    let update_denoms = ExecuteMsg::UpdateAllowedDenoms {
+        denoms: vec!["uluna".to_string(), "uusd".to_string()],
+    };
+    let owner_info = mock_info("owner1", &[]);
+    execute(deps.as_mut(), mock_env(), owner_info, update_denoms).unwrap();
+
+    let hub_info = mock_info("hub", &[]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        hub_info,
+        ExecuteMsg::ProcessRewards {},
+    )
+    .unwrap();
+    assert_eq!(res.attributes, vec![attr("reward_accumulated", "500")]);
+    assert_eq!(
+        res.events,
+        vec![Event::new("process_rewards")
+            .add_attribute("uluna", "500")
+            .add_attribute("uusd", "100")]
+    );
+}
+
+/// Covers if the allowed non-underlying denoms are swapped with the slippage bound
+/// derived from the min output ratio while the underlying coin goes to the hub.
+#[test]
+fn proper_process_rewards_swap() {
+    let mut deps = mock_dependencies_with_balance(&[coin(500, "uluna"), coin(100, "uusd")]);
+    init(&mut deps, Uint128::zero());
+
+    let owner_info = mock_info("owner1", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::UpdateAllowedDenoms {
+            denoms: vec!["uluna".to_string(), "uusd".to_string()],
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::UpdateSwappableDenoms {
+            denoms: vec!["uusd".to_string()],
+        },
+    )
+    .unwrap();
+
+    // a zero ratio would allow any slippage
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::UpdateRewardSwapConfig {
+            reward_swap: Some(RewardSwapConfig {
+                min_output_ratio: Decimal::zero(),
+                swap_contract: Addr::unchecked("pair"),
+            }),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err("The min output ratio must be greater than zero")
+    );
+
+    let reward_swap = RewardSwapConfig {
+        min_output_ratio: Decimal::percent(50),
+        swap_contract: Addr::unchecked("pair"),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info,
+        ExecuteMsg::UpdateRewardSwapConfig {
+            reward_swap: Some(reward_swap.clone()),
+        },
+    )
+    .unwrap();
+
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.reward_swap, Some(reward_swap));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("hub", &[]),
+        ExecuteMsg::ProcessRewards {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "pair".to_string(),
+                msg: to_binary(&SwapMsg::Swap {
+                    offer_asset: SwapAsset {
+                        info: SwapAssetInfo::NativeToken {
+                            denom: "uusd".to_string(),
+                        },
+                        amount: Uint128::new(100),
+                    },
+                    belief_price: Some(Decimal::from_ratio(2u128, 1u128)),
+                    max_spread: Some(Decimal::zero()),
+                    to: None,
+                })
+                .unwrap(),
+                funds: vec![coin(100, "uusd")],
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "hub".to_string(),
+                msg: to_binary(&UpdateExchangeRate {}).unwrap(),
+                funds: vec![coin(500, "uluna")],
+            })),
+        ]
+    );
+}
+
+/// Covers if only the swappable denoms are swapped while the other
+/// non-underlying rewards stay on the contract.
+#[test]
+fn proper_process_rewards_swappable_denoms() {
+    let mut deps =
+        mock_dependencies_with_balance(&[coin(500, "uluna"), coin(100, "uusd"), coin(300, "ukrw")]);
+    init(&mut deps, Uint128::zero());
+
+    let owner_info = mock_info("owner1", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::UpdateAllowedDenoms {
+            denoms: vec!["uluna".to_string(), "uusd".to_string(), "ukrw".to_string()],
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        owner_info.clone(),
+        ExecuteMsg::UpdateRewardSwapConfig {
+            reward_swap: Some(RewardSwapConfig {
+                min_output_ratio: Decimal::one(),
+                swap_contract: Addr::unchecked("pair"),
+            }),
+        },
+    )
+    .unwrap();
+
+    // only the admin can update the swappable denoms
+    let update_denoms = ExecuteMsg::UpdateSwappableDenoms {
+        denoms: vec!["uusd".to_string()],
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("hub", &[]),
+        update_denoms.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("Caller is not admin"));
+
+    execute(deps.as_mut(), mock_env(), owner_info, update_denoms).unwrap();
+
+    let config: ConfigResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.swappable_denoms, vec!["uusd".to_string()]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("hub", &[]),
+        ExecuteMsg::ProcessRewards {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "pair".to_string(),
+                msg: to_binary(&SwapMsg::Swap {
+                    offer_asset: SwapAsset {
+                        info: SwapAssetInfo::NativeToken {
+                            denom: "uusd".to_string(),
+                        },
+                        amount: Uint128::new(100),
+                    },
+                    belief_price: Some(Decimal::one()),
+                    max_spread: Some(Decimal::zero()),
+                    to: None,
+                })
+                .unwrap(),
+                funds: vec![coin(100, "uusd")],
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "hub".to_string(),
+                msg: to_binary(&UpdateExchangeRate {}).unwrap(),
+                funds: vec![coin(500, "uluna")],
+            })),
+        ]
+    );
+}
@@ -2,9 +2,17 @@ use cosmwasm_std::{CanonicalAddr, Decimal, Uint128};
 use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use signed_integer::SignedInt;
 
 pub type UnbondRequest = Vec<(u64, Uint128)>;
 
+/// Default cap on the number of whitelisted validators
+pub const DEFAULT_MAX_VALIDATORS: u64 = 100;
+
+fn default_max_validators() -> u64 {
+    DEFAULT_MAX_VALIDATORS
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct InstantiateMsg {
     pub epoch_period: u64,
@@ -15,6 +23,9 @@ pub struct InstantiateMsg {
     pub validator: String,
     pub protocol_fee: Decimal,
     pub rewards_contract: String,
+    /// Start paused so that nothing can be bonded before the owner unpauses
+    #[serde(default)]
+    pub start_paused: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -25,12 +36,84 @@ pub struct Parameters {
     pub peg_recovery_fee: Decimal,
     pub er_threshold: Decimal,
     pub protocol_fee: Decimal,
+    /// Undelegations smaller than this are avoided when spreading an unbond
+    /// over several validators
+    #[serde(default)]
+    pub min_undelegate_per_validator: Uint128,
+    /// Maximum number of whitelisted validators, keeps `UpdateGlobalIndex` gas bounded
+    #[serde(default = "default_max_validators")]
+    pub max_validators: u64,
+    /// Compounding fails instead of raising the exchange rate above this bound
+    #[serde(default)]
+    pub max_exchange_rate: Option<Decimal>,
+    /// A batch is undelegated early once it holds this many unbond requests, zero disables the cap
+    #[serde(default)]
+    pub max_requests_per_batch: u64,
+    /// Slashing checks also raise the bonded amount when the delegations grow outside the hub
+    #[serde(default)]
+    pub track_positive_drift: bool,
+    /// Round the protocol fee to the nearest unit instead of down.
+    /// Rounding down leaves the fractional unit to the holders, rounding to
+    /// the nearest captures the exact fee on average.
+    #[serde(default)]
+    pub round_protocol_fee: bool,
+    /// Keep the protocol fee on the hub until the collector claims it
+    #[serde(default)]
+    pub accrue_protocol_fee: bool,
+    /// Round the maximum peg recovery fee up instead of down.
+    /// Rounding down never charges more than `peg_recovery_fee`, rounding up
+    /// never charges less, in both cases the fee is capped by the required peg fee.
+    #[serde(default)]
+    pub round_up_peg_fee: bool,
+    /// Minimum number of seconds between two `UpdateGlobalIndex` calls, zero disables the guard
+    #[serde(default)]
+    pub min_compound_interval: u64,
+    /// Idle balance kept on the hub by `RestakeIdle`
+    #[serde(default)]
+    pub restake_dust_threshold: Uint128,
+    /// Protocol fees are accrued on the hub until they reach this amount, then sent at once
+    #[serde(default)]
+    pub min_protocol_fee_transfer: Uint128,
+    /// `UpdateGlobalIndex` withdraws from at most this many validators,
+    /// rotating over the delegations across calls
+    #[serde(default)]
+    pub max_withdraws_per_update: Option<u32>,
+    /// Denom sent by `WithdrawUnbonded` on chains that undelegate into another coin,
+    /// the underlying coin denom is sent if unset
+    #[serde(default)]
+    pub unbond_return_denom: Option<String>,
+    /// No protocol fee is taken from the rewards compounded before this unix time
+    #[serde(default)]
+    pub fee_free_until: Option<u64>,
+    /// Validators with a higher commission can not be registered
+    #[serde(default)]
+    pub max_validator_commission: Option<Decimal>,
+    /// Fee withheld from an instant redeem, instant redeems are disabled while unset
+    #[serde(default)]
+    pub instant_redeem_fee: Option<Decimal>,
+    /// Maximum number of unreleased batches a user can have requests in, zero means no limit
+    #[serde(default)]
+    pub max_open_unbond_batches_per_user: u64,
+    /// Seconds after their last own bond during which a user can not unbond, zero means no cool-off.
+    /// A bond by someone else on behalf of the user does not count
+    #[serde(default)]
+    pub unbond_cooloff: u64,
+    /// Fraction of the compounded rewards kept idle for instant redeems,
+    /// RestakeIdle leaves this fraction of the total bond amount idle
+    #[serde(default)]
+    pub liquidity_reserve_ratio: Decimal,
+    /// Incentive paid to the caller of UpdateGlobalIndex out of the accrued protocol fee
+    #[serde(default)]
+    pub keeper_reward: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct CurrentBatch {
     pub id: u64,
     pub requested_with_fee: Uint128,
+    /// Number of unbond requests in the batch
+    #[serde(default)]
+    pub requests_count: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, JsonSchema, Default)]
@@ -44,6 +127,27 @@ pub struct State {
     pub principle_balance_before_exchange_update: Uint128,
     pub last_unbonded_time: u64,
     pub last_processed_batch: u64,
+    /// Number of closed batches that have not been released yet
+    #[serde(default)]
+    pub open_batches_count: u64,
+    /// Total amount of token burned by unbond requests
+    #[serde(default)]
+    pub total_burned: Uint128,
+    /// Total rewards added to the bonded amount by compounding, net of the protocol fee
+    #[serde(default)]
+    pub total_rewards_compounded: Uint128,
+    /// Total rewards claimed by compounding, before the protocol fee
+    #[serde(default)]
+    pub cumulative_rewards_compounded: Uint128,
+    /// Total protocol fee taken from the compounded rewards
+    #[serde(default)]
+    pub cumulative_protocol_fees: Uint128,
+    /// Advances on every random validator selection and is mixed into its seed
+    #[serde(default)]
+    pub selection_nonce: u64,
+    /// Compounded rewards kept idle by `liquidity_reserve_ratio`, held below `prev_hub_balance`
+    #[serde(default)]
+    pub liquidity_reserve: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
@@ -52,6 +156,23 @@ pub struct Config {
     pub token_contract: Option<CanonicalAddr>,
     pub protocol_fee_collector: Option<CanonicalAddr>,
     pub rewards_contract: Option<CanonicalAddr>,
+    /// Weighted protocol fee collectors, takes precedence over `protocol_fee_collector` when set
+    #[serde(default)]
+    pub protocol_fee_collectors: Vec<(CanonicalAddr, Decimal)>,
+    /// Whitelisted validator that receives all compounded rewards, if set
+    #[serde(default)]
+    pub reward_validator: Option<String>,
+}
+
+/// Lifetime bond and unbond totals of a user
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema, Default)]
+pub struct UserStats {
+    /// Total underlying coin bonded, including bonds made for the user with `BondFor`
+    pub total_bonded: Uint128,
+    /// Total token burned by unbond requests
+    pub total_unbonded: Uint128,
+    pub bond_count: u64,
+    pub unbond_count: u64,
 }
 
 impl State {
@@ -76,11 +197,51 @@ pub enum ExecuteMsg {
     Pause {},
     // Unpause contract functionalities
     Unpause {},
+    /// Reject compounding while a slashing is investigated, withdrawals keep working
+    FreezeExchangeRate {},
+    UnfreezeExchangeRate {},
+    /// Undelegate every delegation of the hub, only while the contract is paused.
+    /// The undelegated coins are sent to `recovery_address` by `EmergencyRecover`
+    EmergencyUndelegateAll {
+        recovery_note: String,
+        recovery_address: String,
+    },
+    /// Send the coins returned by the emergency undelegation to its recovery address
+    EmergencyRecover {},
 
     /// Set the owener
     UpdateConfig {
         token_contract: Option<String>,
         protocol_fee_collector: Option<String>,
+        reward_validator: Option<String>,
+        rewards_contract: Option<String>,
+    },
+
+    /// Replace the protocol fee collectors, the weights must sum to one
+    SetFeeCollectors {
+        collectors: Vec<(String, Decimal)>,
+    },
+
+    /// Set the compounding incentive and throttle of the keepers
+    SetKeeperConfig {
+        reward: Uint128,
+        min_interval: u64,
+    },
+
+    /// Undelegate the current batch without waiting for the epoch period
+    ForceBatchRollover {},
+
+    /// Move every unbond request of `from` to `to`, merging the batches both share
+    ReassignUnbondRequests {
+        from: String,
+        to: String,
+    },
+
+    /// Transfer cw20 tokens sent to the hub by mistake, the basset token can not be recovered
+    RecoverToken {
+        token_addr: String,
+        amount: Uint128,
+        recipient: String,
     },
 
     /// Change the admin (must be called by current admin)
@@ -91,14 +252,46 @@ pub enum ExecuteMsg {
     // Update the exchange rate
     UpdateExchangeRate {},
 
+    /// Send the accrued protocol fee to the fee collectors
+    ClaimProtocolFee {},
+
     /// Register receives the reward contract address
     RegisterValidator {
         validator: String,
     },
 
-    // Remove the validator from validators whitelist
+    /// Register every validator of the list, or none of them
+    RegisterValidators {
+        validators: Vec<String>,
+    },
+
+    /// Prevent the validator from ever being registered, it stays delegated if already whitelisted
+    BlocklistValidator {
+        validator: String,
+    },
+    UnblocklistValidator {
+        validator: String,
+    },
+
+    // Remove the validator from validators whitelist,
+    // optionally whitelisting a replacement in the same call
     DeregisterValidator {
         validator: String,
+        replacement: Option<String>,
+    },
+
+    /// Stop new bonds to a whitelisted validator without redelegating its stake,
+    /// registering the validator again accepts new bonds
+    SoftDeregisterValidator {
+        validator: String,
+    },
+
+    /// Move `amount` of the pooled delegation from one validator to a whitelisted one.
+    /// Delegations are not attributed per user, so this is an owner operation.
+    Rebond {
+        from: String,
+        to: String,
+        amount: Uint128,
     },
 
     /// update the parameters that is needed for the contract
@@ -108,6 +301,30 @@ pub enum ExecuteMsg {
         peg_recovery_fee: Option<Decimal>,
         er_threshold: Option<Decimal>,
         protocol_fee: Option<Decimal>,
+        min_undelegate_per_validator: Option<Uint128>,
+        max_validators: Option<u64>,
+        max_exchange_rate: Option<Decimal>,
+        max_requests_per_batch: Option<u64>,
+        track_positive_drift: Option<bool>,
+        round_protocol_fee: Option<bool>,
+        accrue_protocol_fee: Option<bool>,
+        round_up_peg_fee: Option<bool>,
+        min_compound_interval: Option<u64>,
+        restake_dust_threshold: Option<Uint128>,
+        min_protocol_fee_transfer: Option<Uint128>,
+        max_withdraws_per_update: Option<u32>,
+        unbond_return_denom: Option<String>,
+        fee_free_until: Option<u64>,
+        max_validator_commission: Option<Decimal>,
+        instant_redeem_fee: Option<Decimal>,
+        max_open_unbond_batches_per_user: Option<u64>,
+        unbond_cooloff: Option<u64>,
+        liquidity_reserve_ratio: Option<Decimal>,
+    },
+
+    /// Unset optional parameters, a parameter left out of UpdateParams is kept
+    UnsetParams {
+        params: Vec<OptionalParam>,
     },
 
     ////////////////////
@@ -121,15 +338,40 @@ pub enum ExecuteMsg {
         validator: String,
     },
 
+    /// Same as `Bond`, but the token is issued for `recipient` instead of the sender.
+    /// Fails if less than `min_mint` would be issued.
+    BondFor {
+        validator: String,
+        recipient: String,
+        min_mint: Option<Uint128>,
+    },
+
+    /// Same as sending the basset token with `Cw20HookMsg::Unbond`, but the token
+    /// is pulled from the sender with `TransferFrom`, so an allowance is required.
+    Unbond {
+        amount: Uint128,
+    },
+
     /// Update global index
     UpdateGlobalIndex {},
 
-    /// Send back unbonded coin to the user
-    WithdrawUnbonded {},
+    /// Send back unbonded coin to the user, or to `recipient` if given
+    WithdrawUnbonded {
+        recipient: Option<String>,
+    },
 
     /// Check whether the slashing has happened or not
     CheckSlashing {},
 
+    /// Delegate the idle underlying balance above the dust threshold
+    /// to the least staked validator
+    RestakeIdle {},
+
+    /// Release matured unbond batches without sending any funds
+    ProcessMaturedBatches {
+        limit: Option<u32>,
+    },
+
     ////////////////////
     /// bAsset's operations
     ///////////////////
@@ -145,7 +387,11 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     Config {},
     State {},
-    WhitelistedValidators {},
+    WhitelistedValidators {
+        start_after: Option<String>,
+        limit: Option<u32>,
+        reverse: Option<bool>,
+    },
     CurrentBatch {},
     WithdrawableUnbonded {
         address: String,
@@ -158,13 +404,82 @@ pub enum QueryMsg {
         start_from: Option<u64>,
         limit: Option<u32>,
     },
+    /// History of a single undelegated batch
+    BatchHistory {
+        batch_id: u64,
+    },
     Admin {},
+    ValidatorInfo {
+        validator: String,
+    },
+    CompoundHealth {},
+    StakedValue {
+        address: String,
+    },
+    BatchMaturities {},
+    KeeperConfig {},
+    StuckBatches {},
+    UnbondTimeline {
+        address: String,
+    },
+    IdleBalance {},
+    UserStats {
+        address: String,
+    },
+    ActiveBatches {},
+    BondReconciliation {},
+    BatchWaitList {
+        batch_id: u64,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    SimulateCompound {},
+    ContractVersion {},
+    /// Peg recovery fee that a bond or unbond of `amount` would be charged now
+    PegFeePreview {
+        action: BondOrUnbond,
+        amount: Uint128,
+    },
+    /// Undelegations a batch of `amount` basset would be spread over in the current block
+    SimulateUndelegate {
+        amount: Uint128,
+    },
+    /// Idle liquidity of the hub against the reserve target
+    LiquidityReserve {},
+    /// Number of delegations of the hub, UpdateGlobalIndex withdraws from each of them
+    DelegationCount {},
+    /// Slashes detected by the hub, oldest first
+    SlashHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Exchange rate applied to the batch when it was undelegated
+    BatchExchangeRate {
+        batch_id: u64,
+    },
+    /// Requesters with funds to withdraw from released batches, for keepers.
+    /// A page reads at most `limit` requesters, so it can list fewer addresses
+    WithdrawableAddresses {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BondOrUnbond {
+    /// `amount` is in the underlying coin
+    Bond,
+    /// `amount` is in the basset token
+    Unbond,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Cw20HookMsg {
     Unbond {},
+    /// Burn the basset and receive the underlying coin right away from the idle balance
+    InstantRedeem {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -177,6 +492,42 @@ pub struct UnbondHistory {
     pub released: bool,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct WhitelistedValidator {
+    pub address: String,
+    pub added_at: u64,
+    #[serde(default)]
+    pub validator_status: ValidatorStatus,
+}
+
+/// Optional parameters that can be unset
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OptionalParam {
+    MaxExchangeRate,
+    MaxWithdrawsPerUpdate,
+    UnbondReturnDenom,
+    FeeFreeUntil,
+    MaxValidatorCommission,
+    InstantRedeemFee,
+    /// Compounded rewards go to a random delegation again
+    RewardValidator,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidatorStatus {
+    Active,
+    /// Keeps its delegation but does not accept new bonds
+    NoNewBonds,
+}
+
+impl Default for ValidatorStatus {
+    fn default() -> Self {
+        ValidatorStatus::Active
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct StateResponse {
     pub exchange_rate: Decimal,
@@ -187,6 +538,13 @@ pub struct StateResponse {
     pub actual_unbonded_amount: Uint128,
     pub last_unbonded_time: u64,
     pub last_processed_batch: u64,
+    pub open_batches_count: u64,
+    pub total_burned: Uint128,
+    pub total_rewards_compounded: Uint128,
+    pub cumulative_rewards_compounded: Uint128,
+    pub cumulative_protocol_fees: Uint128,
+    pub selection_nonce: u64,
+    pub liquidity_reserve: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -194,6 +552,8 @@ pub struct ConfigResponse {
     pub token_contract: Option<String>,
     pub protocol_fee_collector: Option<String>,
     pub rewards_contract: Option<String>,
+    pub protocol_fee_collectors: Vec<(String, Decimal)>,
+    pub reward_validator: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -201,10 +561,178 @@ pub struct WhitelistedValidatorsResponse {
     pub validators: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ValidatorInfoResponse {
+    pub added_at: u64,
+    pub current_delegation: Uint128,
+    pub validator_status: ValidatorStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct CompoundHealthResponse {
+    /// True if a reward of one unit can not move the exchange rate anymore
+    pub stalled: bool,
+    /// The smallest reward that still increases the exchange rate
+    pub min_effective_reward: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct StakedValueResponse {
+    pub bluna_balance: Uint128,
+    /// The bluna balance valued in the underlying coin at the current exchange rate
+    pub underlying_value: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct IdleBalanceResponse {
+    /// Underlying balance of the hub, including unbonded funds that wait to be withdrawn
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct UserStatsResponse {
+    pub address: String,
+    pub total_bonded: Uint128,
+    pub total_unbonded: Uint128,
+    pub bond_count: u64,
+    pub unbond_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct StuckBatchesResponse {
+    pub batches: Vec<StuckBatch>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct StuckBatch {
+    pub batch_id: u64,
+    pub matures_at: u64,
+    /// The unbonded amount the batch is still waiting for
+    pub expected_amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct BatchMaturity {
+    pub batch_id: u64,
+    /// The time at which the unbonded funds of the batch can be withdrawn
+    pub matures_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct BatchMaturitiesResponse {
+    pub batches: Vec<BatchMaturity>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ContractVersionResponse {
+    pub contract: String,
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PegFeePreviewResponse {
+    /// Fee in the basset token, withheld from the minted or the unbonded amount
+    pub fee: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LiquidityReserveResponse {
+    /// Idle balance available to instant redeems
+    pub reserve: Uint128,
+    /// liquidity_reserve_ratio of the total bond amount
+    pub target: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct DelegationCountResponse {
+    pub count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SimulateUndelegateResponse {
+    /// Validator and the underlying amount undelegated from it
+    pub undelegations: Vec<(String, Uint128)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SlashEvent {
+    pub id: u64,
+    pub time: u64,
+    /// Total bond amount stored before the slash was detected
+    pub old_bond_amount: Uint128,
+    /// Total bond amount actually delegated after the slash
+    pub new_bond_amount: Uint128,
+    pub exchange_rate: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SlashHistoryResponse {
+    pub slashes: Vec<SlashEvent>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct BatchExchangeRateResponse {
+    pub batch_id: u64,
+    pub applied_exchange_rate: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct WithdrawableAddressesResponse {
+    /// Address and withdrawable amount of every requester with released funds
+    pub addresses: Vec<(String, Uint128)>,
+    /// Last requester read by this page, the next page starts after it.
+    /// Unset once every requester has been read
+    pub last_requester: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SimulateCompoundResponse {
+    /// Validators that would receive a withdraw message
+    pub validators: Vec<String>,
+    /// Rewards in the underlying coin currently claimable from the delegations
+    pub claimable_rewards: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct BatchWaitListResponse {
+    pub batch_id: u64,
+    /// Address and requested amount of every unbond request in the batch
+    pub requests: Vec<(String, Uint128)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct BondReconciliationResponse {
+    pub state_total_bond: Uint128,
+    pub chain_total_delegated: Uint128,
+    /// Delegated minus stored amount, negative if a slashing has not been checked yet
+    pub drift: SignedInt,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ActiveBatch {
+    pub id: u64,
+    pub requested_with_fee: Uint128,
+    pub creation_time: u64,
+    /// True once the unbonding period of the batch has elapsed
+    pub matured: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ActiveBatchesResponse {
+    pub batches: Vec<ActiveBatch>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct KeeperConfigResponse {
+    pub reward: Uint128,
+    pub min_interval: u64,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct CurrentBatchResponse {
     pub id: u64,
     pub requested_with_fee: Uint128,
+    pub requests_count: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -217,6 +745,20 @@ pub struct UnbondRequestsResponse {
     pub requests: UnbondRequest,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct UnbondTimelineResponse {
+    pub address: String,
+    pub requests: Vec<UnbondTimelineEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct UnbondTimelineEntry {
+    pub batch_id: u64,
+    pub requested_amount: Uint128,
+    /// Batch time plus the unbonding period, the open batch is assumed to close at the end of the epoch
+    pub estimated_release_time: u64,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct AllHistoryResponse {
     pub history: Vec<UnbondHistory>,
@@ -1,4 +1,4 @@
-use cosmwasm_std::CanonicalAddr;
+use cosmwasm_std::{Addr, CanonicalAddr, Decimal, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -6,12 +6,39 @@ use serde::{Deserialize, Serialize};
 pub struct InstantiateMsg {
     pub hub_addr: String,
     pub underlying_coin_denom: String,
+    /// ProcessRewards forwards nothing while the reward balance is below this amount
+    #[serde(default)]
+    pub min_process_amount: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 pub struct Config {
     pub hub_contract: CanonicalAddr,
     pub underlying_coin_denom: String,
+    #[serde(default)]
+    pub min_process_amount: Uint128,
+    /// Addresses allowed to call ProcessRewards besides the hub
+    #[serde(default)]
+    pub keepers: Vec<CanonicalAddr>,
+    /// Denoms forwarded to the hub by ProcessRewards, other balances are ignored.
+    /// Only the underlying coin denom is forwarded when empty
+    #[serde(default)]
+    pub allowed_denoms: Vec<String>,
+    /// Swaps the allowed non-underlying denoms to the underlying coin
+    /// instead of forwarding them to the hub
+    #[serde(default)]
+    pub reward_swap: Option<RewardSwapConfig>,
+    /// Denoms that ProcessRewards may swap, other non-underlying balances stay on the contract
+    #[serde(default)]
+    pub swappable_denoms: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct RewardSwapConfig {
+    /// Minimum amount of underlying coin received per offered unit,
+    /// the swap reverts below it
+    pub min_output_ratio: Decimal,
+    pub swap_contract: Addr,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
@@ -31,8 +58,29 @@ pub enum ExecuteMsg {
         admin: String,
     },
 
+    /// Replace the keepers that can process the rewards
+    UpdateKeepers {
+        keepers: Vec<String>,
+    },
+
+    /// Replace the denoms that are forwarded to the hub
+    UpdateAllowedDenoms {
+        denoms: Vec<String>,
+    },
+
+    /// Set or clear the swap of the non-underlying reward denoms
+    UpdateRewardSwapConfig {
+        reward_swap: Option<RewardSwapConfig>,
+    },
+
+    /// Replace the denoms that can be swapped to the underlying coin
+    UpdateSwappableDenoms {
+        denoms: Vec<String>,
+    },
+
     /// Sends the rewards that has been accumulated
-    /// on the contract back to the hub contract
+    /// on the contract back to the hub contract.
+    /// Only the hub and the keepers are allowed to execute
     ProcessRewards {},
 }
 
@@ -41,12 +89,48 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     Config {},
     Admin {},
+    AccruedRewards {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct ConfigResponse {
     pub hub_contract: String,
+    pub keepers: Vec<String>,
+    pub allowed_denoms: Vec<String>,
+    pub reward_swap: Option<RewardSwapConfig>,
+    pub swappable_denoms: Vec<String>,
+}
+
+/// Rewards held by the contract that have not been sent to the hub yet
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct AccruedRewardsResponse {
+    pub amount: Uint128,
+    pub denom: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct MigrateMsg {}
+
+/// Swap message of the pair contract used by ProcessRewards
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapMsg {
+    Swap {
+        offer_asset: SwapAsset,
+        belief_price: Option<Decimal>,
+        max_spread: Option<Decimal>,
+        to: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SwapAsset {
+    pub info: SwapAssetInfo,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapAssetInfo {
+    NativeToken { denom: String },
+}